@@ -0,0 +1,122 @@
+//! Allocations and time per decoded PhotonResult frame, owned vs reused vs
+//! borrowed. Run with `cargo bench --bench photon_alloc`.
+
+use pathforger::photon_serde::{deserialize, deserialize_into, PhotonResult, PhotonResultView};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const FRAMES: usize = 10_000;
+
+/// A frame with `targets` targets of 4 + 4 corners and a multi-tag result, in
+/// photon's layout
+fn frame(targets: u8) -> Vec<u8> {
+    let mut out = vec![];
+    let f64s = |out: &mut Vec<u8>, n: usize| {
+        for i in 0..n {
+            out.extend((i as f64 * 0.25).to_le_bytes());
+        }
+    };
+
+    for micros in [1u64, 2, 3, 4] {
+        out.extend(micros.to_le_bytes());
+    }
+
+    out.push(targets);
+    for id in 0..targets {
+        f64s(&mut out, 4);
+        out.extend((id as i32).to_le_bytes());
+        out.extend(u64::MAX.to_le_bytes());
+        out.extend(0f32.to_le_bytes());
+        f64s(&mut out, 14 + 1);
+
+        for _ in 0..2 {
+            out.push(4);
+            f64s(&mut out, 8);
+        }
+    }
+
+    out.push(1);
+    f64s(&mut out, 14 + 3);
+    out.extend(2u16.to_le_bytes());
+
+    out
+}
+
+/// Allocations and nanoseconds per call of `decode`
+fn measure(mut decode: impl FnMut()) -> (f64, f64) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+
+    for _ in 0..FRAMES {
+        decode();
+    }
+
+    let nanos = start.elapsed().as_nanos() as f64;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    (allocations as f64 / FRAMES as f64, nanos / FRAMES as f64)
+}
+
+fn main() {
+    for targets in [0, 1, 4, 16] {
+        let bytes = frame(targets);
+
+        let owned = measure(|| {
+            black_box(deserialize::<PhotonResult>(black_box(&bytes)).unwrap());
+        });
+
+        let mut reused = deserialize::<PhotonResult>(&bytes).unwrap();
+        let into = measure(|| {
+            deserialize_into(black_box(&bytes), &mut reused).unwrap();
+            black_box(&reused);
+        });
+
+        let view = measure(|| {
+            let view = PhotonResultView::new(black_box(&bytes)).unwrap();
+            for target in view.targets() {
+                black_box(target.yaw());
+                black_box(
+                    target
+                        .detected_corners()
+                        .map(|corner| corner.x)
+                        .sum::<f64>(),
+                );
+            }
+        });
+
+        println!("{targets:>2} targets, {} bytes", bytes.len());
+        for (name, (allocations, nanos)) in [
+            ("deserialize", owned),
+            ("deserialize_into", into),
+            ("view", view),
+        ] {
+            println!("  {name:<16} {allocations:>6.1} allocs {nanos:>9.0} ns");
+        }
+    }
+}
@@ -0,0 +1,113 @@
+#!/usr/bin/env python3
+"""Writes each `<version>/<name>.bin` from the hand-written `<name>.json` next
+to it, laid out the way that PhotonVision release sends `rawData`.
+
+This deliberately shares no code with pathforger's decoder, so the golden test
+checks the decoder against an independent encoding rather than against
+itself. Run it from anywhere after editing one of the `HAND_WRITTEN` goldens.
+"""
+
+import json
+import struct
+from pathlib import Path
+
+HERE = Path(__file__).parent
+# When the golden test takes pre-2025 packets to have arrived
+RECEIVED_MICROS = 100_000_000
+# Captures are never rewritten, only these
+HAND_WRITTEN = [
+    "2023/apriltags",
+    "2024/apriltag_multitag",
+    "2025/apriltag_multitag",
+    "2025/empty",
+    "2025/object_detection",
+]
+
+
+def f64(value):
+    return struct.pack("<d", value)
+
+
+def transform(t):
+    return b"".join(map(f64, t["translation"] + t["rotation"]))
+
+
+def corners(points):
+    return b"".join(f64(x) + f64(y) for x, y in points)
+
+
+def corner_list(points):
+    return struct.pack("<B", len(points)) + corners(points)
+
+
+def target(t, version):
+    out = b"".join(f64(t[key]) for key in ("yaw", "pitch", "area", "skew"))
+    fiducial = t["fiducial_id"]
+    out += struct.pack("<i", -1 if fiducial is None else fiducial)
+
+    if version >= 2025:
+        cls = t["class"]
+        out += struct.pack("<q", -1 if cls is None else cls)
+        out += struct.pack("<f", t["confidence"])
+    else:
+        assert t["class"] is None and t["confidence"] == 0.0, "no object detection before 2025"
+
+    out += transform(t["best"]) + transform(t["alt"]) + f64(t["ambiguity"])
+
+    if version >= 2024:
+        out += corner_list(t["area_rect_corners"])
+    else:
+        assert len(t["area_rect_corners"]) == 4, "2023 always sends four corners"
+        out += corners(t["area_rect_corners"])
+
+    return out + corner_list(t["detected_corners"])
+
+
+def pnp(multi):
+    return (
+        transform(multi["best"])
+        + transform(multi["alt"])
+        + b"".join(f64(multi[key]) for key in ("error", "alt_error", "ambiguity"))
+    )
+
+
+def result(golden, version):
+    meta = golden["metadata"]
+    multi = golden["multi_tag"]
+
+    if version >= 2025:
+        out = struct.pack(
+            "<4Q",
+            meta["seqid"],
+            meta["capture_micros"],
+            meta["publish_micros"],
+            meta["last_handshake_micros"],
+        )
+    else:
+        assert meta["seqid"] == 0 and meta["last_handshake_micros"] == 0
+        assert meta["publish_micros"] == RECEIVED_MICROS
+        out = f64((meta["publish_micros"] - meta["capture_micros"]) / 1000)
+
+    targets = golden["targets"]
+    out += struct.pack("<B", len(targets))
+    out += b"".join(target(t, version) for t in targets)
+
+    if version >= 2025:
+        out += b"\x00" if multi is None else b"\x01" + pnp(multi)
+        if multi is not None:
+            out += struct.pack("<H", multi["num_fiducials"])
+    elif version >= 2024:
+        out += b"\x00" if multi is None else b"\x01" + pnp(multi)
+        ids = [t["fiducial_id"] for t in targets if multi is not None]
+        assert multi is None or len(ids) == multi["num_fiducials"]
+        out += struct.pack("<32h", *(ids + [-1] * (32 - len(ids))))
+    else:
+        assert multi is None, "no multi-tag result before 2024"
+
+    return out
+
+
+for name in HAND_WRITTEN:
+    golden = HERE / f"{name}.json"
+    version = int(golden.parent.name)
+    golden.with_suffix(".bin").write_bytes(result(json.loads(golden.read_text()), version))
@@ -0,0 +1,22 @@
+//! Every decoder that takes bytes off the network has to turn garbage into an
+//! error, never a panic. Run with `cargo fuzz run photon_result`.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use pathforger::photon_serde::{deserialize, PhotonResult, PhotonResultView, PhotonVersion};
+use std::time::Duration;
+
+fuzz_target!(|bytes: &[u8]| {
+    let _ = deserialize::<PhotonResult>(bytes);
+
+    if let Ok(view) = PhotonResultView::new(bytes) {
+        for target in view.targets() {
+            let _ = target.to_owned();
+        }
+    }
+
+    for version in PhotonVersion::ALL {
+        let _ = version.decode(bytes, Duration::ZERO);
+    }
+});
@@ -0,0 +1,434 @@
+//! Derives for pathforger's `photon_serde`.
+//!
+//! `#[derive(PhotonSerde)]` implements `Deserialize` and `Shaped`, and
+//! `Serialize` in pathforger's tests, with fields in declaration order. Field
+//! attributes:
+//! - `#[photon(len = u16)]` on a `Vec`: the length prefix type (default `u8`)
+//! - `#[photon(with = module)]`: `module::deserialize(data)`,
+//!   `module::serialize(&value, out)` (only in tests) and `module::shape(out)`
+//!   handle the field instead
+//! - `#[photon(version >= 2024)]`: only on the wire from that protocol version
+//!   on, and defaulted before it
+//! - `#[photon(legacy = module)]`, next to `version`: older versions read the
+//!   field with `module::deserialize(data, version)` instead of defaulting it,
+//!   for fields whose layout changed rather than appeared
+//! - `#[photon(skip)]`: never on the wire, always defaulted
+//! - `#[photon(default = expr)]`: what skipped and missing fields are set to,
+//!   instead of `Default::default()`
+//!
+//! Fieldless enums need `#[photon(repr = u8)]` (or any integer), and are read as
+//! that integer.
+//!
+//! `#[derive(FloatHash)]` implements `Eq` and `Hash` for structs with float
+//! fields (`f32`, `f64`, `Angle`, `Length`), hashing them by their bits.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Data, DataEnum, DataStruct, DeriveInput,
+    Error, Expr, Fields, GenericArgument, Generics, LitInt, Member, Path, PathArguments, Token,
+    Type,
+};
+
+fn krate() -> TokenStream2 {
+    quote!(::pathforger::photon_serde)
+}
+
+#[proc_macro_derive(PhotonSerde, attributes(photon))]
+pub fn derive_photon_serde(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match &input.data {
+        Data::Struct(data) => photon_struct(&input, data),
+        Data::Enum(data) => photon_enum(&input, data),
+        Data::Union(_) => Err(Error::new(input.span(), "unions can't be PhotonSerde")),
+    }
+    .unwrap_or_else(Error::into_compile_error)
+    .into()
+}
+
+#[proc_macro_derive(FloatHash)]
+pub fn derive_float_hash(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    float_hash(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct FieldAttrs {
+    len: Option<Type>,
+    with: Option<Path>,
+    since: Option<LitInt>,
+    legacy: Option<Path>,
+    skip: bool,
+    default: Option<Expr>,
+}
+
+impl FieldAttrs {
+    fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+        let mut out = Self::default();
+
+        for attr in attrs.iter().filter(|attr| attr.path().is_ident("photon")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("len") {
+                    out.len = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("with") {
+                    out.with = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("version") {
+                    meta.input.parse::<Token![>=]>()?;
+                    out.since = Some(meta.input.parse()?);
+                } else if meta.path.is_ident("legacy") {
+                    out.legacy = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("skip") {
+                    out.skip = true;
+                } else if meta.path.is_ident("default") {
+                    out.default = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("expected len, with, version, legacy, skip or default"));
+                }
+
+                Ok(())
+            })?;
+        }
+
+        if out.len.is_some() && out.with.is_some() {
+            return Err(Error::new(
+                attrs[0].span(),
+                "len and with can't be used together",
+            ));
+        }
+
+        if out.legacy.is_some() && (out.since.is_none() || out.default.is_some()) {
+            return Err(Error::new(
+                attrs[0].span(),
+                "legacy needs version >= N, and replaces default",
+            ));
+        }
+
+        Ok(out)
+    }
+}
+
+/// `T` out of `Vec<T>`
+fn vec_element(ty: &Type) -> Option<&Type> {
+    let Type::Path(path) = ty else {
+        return None;
+    };
+
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Vec" {
+        return None;
+    }
+
+    let PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    match args.args.first()? {
+        GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+/// Whether `ty` can be a list's length prefix, which is read into a `u64`
+fn is_length_type(ty: &Type) -> bool {
+    let Type::Path(path) = ty else {
+        return false;
+    };
+
+    ["u8", "u16", "u32", "u64"]
+        .iter()
+        .any(|name| path.path.is_ident(name))
+}
+
+fn members(fields: &Fields) -> Vec<Member> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(i.into()),
+        })
+        .collect()
+}
+
+/// `generics` with every type parameter bound by `bound`
+fn bounded(generics: &Generics, bound: TokenStream2) -> Generics {
+    let mut generics = generics.clone();
+    for param in generics.type_params_mut() {
+        param.bounds.push(parse_quote!(#bound));
+    }
+    generics
+}
+
+fn photon_struct(input: &DeriveInput, data: &DataStruct) -> syn::Result<TokenStream2> {
+    let krate = krate();
+    let name = &input.ident;
+
+    let mut reads = vec![];
+    let mut in_place = vec![];
+    let mut writes = vec![];
+    let mut shapes = vec![];
+
+    let members = members(&data.fields);
+
+    for (field, member) in data.fields.iter().zip(&members) {
+        let attrs = FieldAttrs::parse(&field.attrs)?;
+        let ty = &field.ty;
+
+        let default = match &attrs.default {
+            Some(expr) => quote!(#expr),
+            None => quote!(::core::default::Default::default()),
+        };
+
+        let (read, write, shape) = if let Some(with) = &attrs.with {
+            (
+                quote!(#with::deserialize(data)?),
+                quote!(#with::serialize(&self.#member, out)?;),
+                quote!(#with::shape(out);),
+            )
+        } else if let Some(len) = &attrs.len {
+            let Some(element) = vec_element(ty) else {
+                return Err(Error::new(ty.span(), "len only applies to Vec fields"));
+            };
+            if !is_length_type(len) {
+                return Err(Error::new(len.span(), "len has to be u8, u16, u32 or u64"));
+            }
+
+            (
+                quote!(#krate::deserialize_list::<#len, #element>(data, version)?),
+                quote!(#krate::serialize_list::<#len, #element>(&self.#member, out)?;),
+                quote!(#krate::schema::list_shape::<#len, #element>(out);),
+            )
+        } else {
+            (
+                quote!(<#ty as #krate::Deserialize>::deserialize_version(data, version)?),
+                quote!(<#ty as #krate::Serialize>::serialize(&self.#member, out)?;),
+                quote!(<#ty as #krate::Shaped>::shape(out);),
+            )
+        };
+
+        let plain = attrs.with.is_none() && attrs.len.is_none() && attrs.since.is_none();
+        let before = match &attrs.legacy {
+            Some(legacy) => quote!(#legacy::deserialize(data, version)?),
+            None => default.clone(),
+        };
+        let read = match (&attrs.since, attrs.skip) {
+            (_, true) => default,
+            (Some(since), false) => quote!(if version >= #since { #read } else { #before }),
+            (None, false) => read,
+        };
+
+        in_place.push(if plain && !attrs.skip {
+            quote!(<#ty as #krate::Deserialize>::deserialize_in_place(data, &mut out.#member)?;)
+        } else {
+            quote!(out.#member = #read;)
+        });
+        reads.push(read);
+
+        if !attrs.skip {
+            writes.push(write);
+            shapes.push(shape);
+        }
+    }
+
+    let generics = bounded(&input.generics, quote!(#krate::Deserialize));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let serialize_generics = bounded(&input.generics, quote!(#krate::Serialize));
+    let (serialize_impl, _, serialize_where) = serialize_generics.split_for_impl();
+
+    let shaped_generics = bounded(&input.generics, quote!(#krate::Shaped));
+    let (shaped_impl, _, shaped_where) = shaped_generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics #krate::Deserialize for #name #ty_generics #where_clause {
+            fn deserialize(
+                data: &mut ::std::io::Cursor<&[u8]>,
+            ) -> ::core::result::Result<Self, #krate::DeserializeError> {
+                Self::deserialize_version(data, #krate::LATEST)
+            }
+
+            #[allow(unused_variables)]
+            fn deserialize_version(
+                data: &mut ::std::io::Cursor<&[u8]>,
+                version: u32,
+            ) -> ::core::result::Result<Self, #krate::DeserializeError> {
+                Ok(Self {
+                    #(#members: #reads),*
+                })
+            }
+
+            #[allow(unused_variables)]
+            fn deserialize_in_place(
+                data: &mut ::std::io::Cursor<&[u8]>,
+                out: &mut Self,
+            ) -> ::core::result::Result<(), #krate::DeserializeError> {
+                let version = #krate::LATEST;
+                #(#in_place)*
+                Ok(())
+            }
+        }
+
+        #[cfg(test)]
+        impl #serialize_impl #krate::Serialize for #name #ty_generics #serialize_where {
+            #[allow(unused_variables)]
+            fn serialize(
+                &self,
+                out: &mut Vec<u8>,
+            ) -> ::core::result::Result<(), #krate::SerializeError> {
+                #(#writes)*
+                Ok(())
+            }
+        }
+
+        impl #shaped_impl #krate::Shaped for #name #ty_generics #shaped_where {
+            #[allow(unused_variables)]
+            fn shape(out: &mut Vec<#krate::schema::Shape>) {
+                #(#shapes)*
+            }
+        }
+    })
+}
+
+fn photon_enum(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream2> {
+    let krate = krate();
+    let name = &input.ident;
+
+    let mut repr = None;
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("photon"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("repr") {
+                repr = Some(meta.value()?.parse::<Type>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected repr"))
+            }
+        })?;
+    }
+
+    let Some(repr) = repr else {
+        return Err(Error::new(
+            input.span(),
+            "enums need #[photon(repr = <integer type>)]",
+        ));
+    };
+
+    if !input.generics.params.is_empty() {
+        return Err(Error::new(input.generics.span(), "enums can't be generic"));
+    }
+
+    let variants = data
+        .variants
+        .iter()
+        .map(|variant| match variant.fields {
+            Fields::Unit => Ok(&variant.ident),
+            _ => Err(Error::new(
+                variant.span(),
+                "enum variants can't have fields",
+            )),
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #krate::Deserialize for #name {
+            #[track_caller]
+            fn deserialize(
+                data: &mut ::std::io::Cursor<&[u8]>,
+            ) -> ::core::result::Result<Self, #krate::DeserializeError> {
+                let value = <#repr as #krate::Deserialize>::deserialize(data)?;
+
+                #(
+                    if value == Self::#variants as #repr {
+                        return Ok(Self::#variants);
+                    }
+                )*
+
+                Err(#krate::DeserializeError::BadDiscriminant {
+                    name: stringify!(#name),
+                    value: value as i64,
+                    location: ::std::panic::Location::caller(),
+                })
+            }
+        }
+
+        #[cfg(test)]
+        impl #krate::Serialize for #name {
+            fn serialize(
+                &self,
+                out: &mut Vec<u8>,
+            ) -> ::core::result::Result<(), #krate::SerializeError> {
+                let value = match self {
+                    #(Self::#variants => Self::#variants as #repr),*
+                };
+
+                <#repr as #krate::Serialize>::serialize(&value, out)
+            }
+        }
+
+        impl #krate::Shaped for #name {
+            fn shape(out: &mut Vec<#krate::schema::Shape>) {
+                <#repr as #krate::Shaped>::shape(out);
+            }
+        }
+    })
+}
+
+/// How a field's value is fed to the hasher. Floats go through their bits, since
+/// they don't implement `Hash`.
+fn hash_field(krate: &TokenStream2, member: &Member, ty: &Type) -> TokenStream2 {
+    let ident = match ty {
+        Type::Path(path) => path.path.segments.last().map(|segment| &segment.ident),
+        _ => None,
+    };
+
+    let value = match ident {
+        Some(ident) if ident == "f64" => quote!(#krate::float_bits(self.#member)),
+        Some(ident) if ident == "f32" => quote!(#krate::float_bits(self.#member as f64)),
+        Some(ident) if ident == "Angle" || ident == "Length" => {
+            quote!(#krate::float_bits(self.#member.value))
+        }
+        _ => quote!(self.#member),
+    };
+
+    quote!(::core::hash::Hash::hash(&#value, state);)
+}
+
+fn float_hash(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let krate = krate();
+    let name = &input.ident;
+
+    let Data::Struct(data) = &input.data else {
+        return Err(Error::new(input.span(), "FloatHash only works on structs"));
+    };
+
+    let hashes = members(&data.fields)
+        .iter()
+        .zip(&data.fields)
+        .map(|(member, field)| hash_field(&krate, member, &field.ty))
+        .collect::<Vec<_>>();
+
+    let generics = bounded(&input.generics, quote!(::core::hash::Hash));
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let eq_generics = bounded(&input.generics, quote!(::core::cmp::Eq));
+    let (eq_impl, _, eq_where) = eq_generics.split_for_impl();
+
+    Ok(quote! {
+        impl #eq_impl ::core::cmp::Eq for #name #ty_generics #eq_where {}
+
+        impl #impl_generics ::core::hash::Hash for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn hash<H: ::core::hash::Hasher>(&self, state: &mut H) {
+                #(#hashes)*
+            }
+        }
+    })
+}
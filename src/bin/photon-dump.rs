@@ -0,0 +1,259 @@
+//! Decodes PhotonVision's `rawData` results and prints what's in them, for
+//! checking a coprocessor at competition without a debugger.
+
+use nt_client::{
+    data::SubscriptionOptions, subscribe::ReceivedMessage, Client, NTAddr, NewClientOptions,
+};
+use pathforger::{
+    networktables::photon_camera,
+    photon_serde::dump::{parse_hex, DumpFilter, Summary},
+    prelude::*,
+};
+use std::{
+    env,
+    io::{self, BufRead},
+    net::Ipv4Addr,
+    process::ExitCode,
+    time::Duration,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+/// Older layouts only send a latency, which capture times are worked back from
+/// the arrival time with. Printing the latency only needs this to be later.
+const ARRIVAL: Duration = Duration::from_secs(3600);
+
+const USAGE: &str = "\
+usage: photon-dump [options] [file...]
+
+Reads each file as one result's raw bytes, or lines of hex from stdin for -,
+each optionally prefixed with `<camera>:`. With no files, prints every
+camera's results as they're published over NetworkTables.
+
+  --team <n>             connect to this team's robot (default localhost)
+  --address <ip>         connect to this address instead
+  --camera <name>        only this camera, can be repeated
+  --fiducial <id>        only targets of this tag, can be repeated
+  --photon-version <v>   decode as 2023, 2024 or 2025 (default detects it)";
+
+struct Args {
+    files: Vec<String>,
+    addr: NTAddr,
+    version: Option<PhotonVersion>,
+    filter: DumpFilter,
+}
+
+fn parse_args() -> Result<Option<Args>, String> {
+    let mut files = vec![];
+    let mut addr = NTAddr::Local;
+    let mut version = None;
+    let mut filter = DumpFilter::default();
+    let mut args = env::args().skip(1);
+
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("{flag} needs a value"));
+
+        match flag.as_str() {
+            "--team" => {
+                let team = value()?;
+                let team = team
+                    .parse()
+                    .map_err(|_| format!("--team: {team} is not a team number"))?;
+                addr = NTAddr::TeamNumber(team);
+            }
+            "--address" => {
+                let ip = value()?;
+                let ip = ip
+                    .parse::<Ipv4Addr>()
+                    .map_err(|_| format!("--address: {ip} is not an IPv4 address"))?;
+                addr = NTAddr::Custom(ip);
+            }
+            "--camera" => filter.cameras.push(value()?),
+            "--fiducial" => {
+                let id = value()?;
+                let id = id
+                    .parse()
+                    .map_err(|_| format!("--fiducial: {id} is not a tag id"))?;
+                filter.fiducials.push(id);
+            }
+            "--photon-version" => {
+                version = Some(value()?.parse().map_err(|err| format!("{err}"))?);
+            }
+            "-h" | "--help" => return Ok(None),
+            other if other.starts_with("--") => {
+                return Err(format!("unknown option {other}\n\n{USAGE}"))
+            }
+            _ => files.push(flag),
+        }
+    }
+
+    Ok(Some(Args {
+        files,
+        addr,
+        version,
+        filter,
+    }))
+}
+
+/// Decodes and prints one result. `type_string` is what it was announced as
+/// over NT, which is empty for results read from files.
+fn dump(camera: Option<&str>, type_string: &str, bytes: &[u8], args: &Args) -> Result<(), String> {
+    let version = args
+        .version
+        .or_else(|| PhotonVersion::detect(type_string, bytes))
+        .ok_or(format!(
+            "{} bytes don't fit any PhotonVision layout",
+            bytes.len()
+        ))?;
+
+    let result = version
+        .decode(bytes, ARRIVAL)
+        .map_err(|err| format!("decoding as {version:?}: {err}"))?;
+
+    if let Some(result) = args.filter.apply(result) {
+        let summary = Summary {
+            camera,
+            version,
+            result: &result,
+        };
+        print!("{summary}");
+    }
+
+    Ok(())
+}
+
+/// Every line of stdin, returning whether they all decoded
+fn dump_stdin(args: &Args) -> bool {
+    let mut ok = true;
+
+    for (i, line) in io::stdin().lock().lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(err) => {
+                eprintln!("stdin: {err}");
+                return false;
+            }
+        };
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let (camera, hex) = match line.split_once(':') {
+            Some((camera, hex)) => (Some(camera.trim()), hex),
+            None => (None, line.as_str()),
+        };
+
+        if camera.is_some_and(|camera| !args.filter.camera(camera)) {
+            continue;
+        }
+
+        let dumped = parse_hex(hex)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| dump(camera, "", &bytes, args));
+
+        if let Err(err) = dumped {
+            eprintln!("stdin line {}: {err}", i + 1);
+            ok = false;
+        }
+    }
+
+    ok
+}
+
+/// Prints every camera's results until NT disconnects
+async fn dump_nt(args: Args) -> Result<(), String> {
+    let client = Client::new(NewClientOptions {
+        addr: args.addr,
+        ..Default::default()
+    });
+
+    let photon = client.topic("/photonvision/");
+    let printer = tokio::spawn(async move {
+        let mut sub = photon
+            .subscribe(SubscriptionOptions {
+                prefix: Some(true),
+                ..Default::default()
+            })
+            .await;
+
+        loop {
+            let (announced, value) = match sub.recv().await {
+                Ok(ReceivedMessage::Updated(update)) => update,
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    eprintln!("Fell behind, skipped {skipped} updates");
+                    continue;
+                }
+                Err(RecvError::Closed) => return,
+            };
+
+            let Some(camera) = photon_camera(&announced.name) else {
+                continue;
+            };
+
+            if !args.filter.camera(camera) {
+                continue;
+            }
+
+            if let Some(bytes) = value.as_slice() {
+                if let Err(err) = dump(Some(camera), &announced.r#type, bytes, &args) {
+                    eprintln!("[{camera}] {err}");
+                }
+            }
+        }
+    });
+
+    tokio::select! {
+        connected = client.connect() => connected.map_err(|err| format!("NetworkTables: {err}")),
+        _ = printer => Ok(()),
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if args.files.is_empty() {
+        return match dump_nt(args).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(err) => {
+                eprintln!("{err}");
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let mut ok = true;
+
+    for file in &args.files {
+        if file == "-" {
+            ok &= dump_stdin(&args);
+            continue;
+        }
+
+        let dumped = std::fs::read(file)
+            .map_err(|err| err.to_string())
+            .and_then(|bytes| dump(None, "", &bytes, &args));
+
+        if let Err(err) = dumped {
+            eprintln!("{file}: {err}");
+            ok = false;
+        }
+    }
+
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
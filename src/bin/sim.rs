@@ -0,0 +1,212 @@
+//! Runs scripted or random enemy robots around the field, feeds what a virtual
+//! camera would see through the preprocessor and tracker, and scores the tracks
+//! against the truth.
+
+use pathforger::{
+    eval::{Estimate, Evaluator},
+    game::{layout::FieldLayout, tracker::Tracker},
+    prelude::*,
+    render::{Renderer, ReplayFrame},
+    sim::{SimConfig, Simulation, MAX_ENEMIES},
+    util::preprocessor,
+};
+use std::{env, process::ExitCode, time::Duration};
+
+/// Estimates further than this from every real robot count as false positives
+const MATCH_DISTANCE: f64 = 1.0;
+
+/// Time between frames of the SVG replay, which gets huge at the camera's rate
+const REPLAY_PERIOD: Duration = Duration::from_millis(100);
+
+const USAGE: &str = "\
+usage: sim [options]
+
+  --enemies <n>          enemy robots, at most 256 (default 3)
+  --seconds <s>          simulated time (default 30)
+  --seed <n>             random seed (default 0)
+  --noise <deg>          yaw/pitch noise std dev (default 0.3)
+  --dropout <p>          chance a visible robot is missed (default 0.05)
+  --false-positive <p>   chance of a spurious target per frame (default 0.02)
+  --layout <path>        AprilTag field layout JSON, so tags are seen too
+  --report <path>        write the evaluation report, as CSV if it ends in .csv
+                         and JSON otherwise (CSV without the serde feature)
+  --svg <path>           write an animated SVG replay of the tracks";
+
+struct Args {
+    config: SimConfig,
+    seconds: f64,
+    report: Option<String>,
+    svg: Option<String>,
+}
+
+fn parse_args() -> Result<Option<Args>, String> {
+    let mut config = SimConfig::default();
+    let mut seconds = 30.0;
+    let mut report = None;
+    let mut svg = None;
+    let mut args = env::args().skip(1);
+
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("{flag} needs a value"));
+        let number = |value: String| {
+            value
+                .parse::<f64>()
+                .map_err(|_| format!("{flag}: {value} is not a number"))
+        };
+        let count = |value: String| {
+            value
+                .parse::<u64>()
+                .map_err(|_| format!("{flag}: {value} is not a whole number"))
+        };
+
+        match flag.as_str() {
+            "--enemies" => {
+                config.enemies = match count(value()?)? {
+                    enemies if enemies <= MAX_ENEMIES as u64 => enemies as usize,
+                    enemies => return Err(format!("{flag}: {enemies} is more than {MAX_ENEMIES}")),
+                }
+            }
+            "--seconds" => seconds = number(value()?)?,
+            "--seed" => config.seed = count(value()?)?,
+            "--noise" => config.camera.noise = Angle::new::<degree>(number(value()?)?),
+            "--dropout" => config.camera.dropout = number(value()?)?.clamp(0.0, 1.0),
+            "--false-positive" => config.camera.false_positive = number(value()?)?.clamp(0.0, 1.0),
+            "--layout" => {
+                let path = value()?;
+                let layout = FieldLayout::load(&path).map_err(|err| format!("{path}: {err}"))?;
+                config.layout = Some(layout);
+            }
+            "--report" => report = Some(value()?),
+            "--svg" => svg = Some(value()?),
+            "-h" | "--help" => return Ok(None),
+            other => return Err(format!("unknown option {other}\n\n{USAGE}")),
+        }
+    }
+
+    Ok(Some(Args {
+        config,
+        seconds,
+        report,
+        svg,
+    }))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let args = match parse_args() {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("{message}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    time::initialize();
+
+    let camera = args.config.camera.clone();
+    let ours = args.config.ours;
+    let end = Duration::from_secs_f64(args.seconds);
+    let mut sim = Simulation::new(args.config);
+    let mut tracker = Tracker::default();
+    let mut evaluator = Evaluator::new(Length::new::<meter>(MATCH_DISTANCE));
+    let mut next_row = Duration::ZERO;
+    let mut replay = vec![];
+
+    println!("  time  visible  tracks   mota   motp (m)");
+
+    while sim.time < end {
+        let Some(frame) = sim.step() else {
+            continue;
+        };
+
+        let response = preprocessor::photon(frame, camera.robot_to_camera, ours).await;
+        let timestamp = response.timestamp;
+        let enemies = tracker.update(&response);
+
+        // robots out of view can't be tracked, so they aren't held against us
+        let visible = sim.visible();
+        let truth = sim
+            .truth()
+            .into_iter()
+            .filter(|truth| visible.contains(&truth.id))
+            .collect_vec();
+
+        // coasting tracks are stale by up to the tracker's max age, so only the
+        // ones this frame confirmed are scored
+        let estimates = enemies
+            .iter()
+            .filter(|enemy| enemy.last_update() == timestamp)
+            .map(Estimate::from)
+            .collect_vec();
+
+        evaluator.add(&truth, &estimates);
+
+        let next_replay = replay.last().map_or(Duration::ZERO, |frame: &ReplayFrame| {
+            frame.time + REPLAY_PERIOD
+        });
+        if args.svg.is_some() && sim.time >= next_replay {
+            replay.push(ReplayFrame {
+                time: sim.time,
+                enemies: enemies.to_vec(),
+                path: vec![],
+                ours: Some(ours),
+            });
+        }
+
+        if sim.time >= next_row {
+            let report = evaluator.report();
+            println!(
+                "{:>6.1}  {:>7}  {:>6}  {:>5.3}  {:>9.3}",
+                sim.time.as_secs_f64(),
+                visible.len(),
+                enemies.len(),
+                report.mota,
+                report.motp
+            );
+            next_row += Duration::from_secs(1);
+        }
+    }
+
+    let report = evaluator.report();
+
+    println!();
+    println!("frames:            {}", report.frames);
+    println!(
+        "objects:           {} (robot-frames in view)",
+        report.objects
+    );
+    println!("misses:            {}", report.misses);
+    println!("false positives:   {}", report.false_positives);
+    println!("id switches:       {}", report.id_switches);
+    println!("fragmentations:    {}", report.fragmentations);
+    println!(
+        "false tracks:      {} of {} ({:.1}%)",
+        report.false_tracks,
+        report.tracks,
+        report.false_track_rate * 100.0
+    );
+    println!("MOTA:              {:.3}", report.mota);
+    println!("MOTP:              {:.3}m", report.motp);
+    println!("position RMSE:     {:.3}m", report.position_rmse);
+    println!("velocity RMSE:     {:.3}m/s", report.velocity_rmse);
+
+    if let Some(path) = args.report {
+        if let Err(err) = report.save(&path) {
+            eprintln!("{path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    if let Some(path) = args.svg {
+        if let Err(err) = Renderer::default().save_animation(None, &replay, &path) {
+            eprintln!("{path}: {err}");
+            return ExitCode::FAILURE;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
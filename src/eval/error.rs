@@ -0,0 +1,33 @@
+use std::io;
+use std::{backtrace::Backtrace, panic::Location};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum EvalError {
+    #[error("At {location}: IO error:\n{source}")]
+    IOError {
+        #[from]
+        source: io::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[cfg(feature = "serde")]
+    #[error("At {location}: Couldn't serialize report:\n{source}")]
+    JsonError {
+        #[from]
+        source: serde_json::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: {log} line {line} is malformed: {reason}")]
+    BadRow {
+        /// Which of the logs it was
+        log: &'static str,
+        line: usize,
+        reason: String,
+        location: &'static Location<'static>,
+    },
+}
@@ -0,0 +1,437 @@
+//! CLEAR MOT style scoring of tracker output against ground truth, so tuning
+//! changes can be compared with numbers
+
+pub mod error;
+
+#[cfg(test)]
+mod test;
+
+use crate::prelude::*;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    panic::Location,
+    path::Path,
+    time::Duration,
+};
+
+use error::EvalError;
+use game::{enemy::Enemy, tracker::Tracker};
+use sim::GroundTruth;
+
+/// What the tracker believes about one enemy at one moment
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Estimate {
+    pub id: u8,
+    pub pose: Pose2d,
+    pub velocity: (Velocity, Velocity),
+}
+
+impl From<&Enemy> for Estimate {
+    fn from(enemy: &Enemy) -> Self {
+        Self {
+            id: enemy.id,
+            pose: enemy.pose(),
+            velocity: Tracker::velocity(enemy),
+        }
+    }
+}
+
+/// Rows of a log grouped into frames by their timestamp, in order
+pub type Frames<T> = BTreeMap<Duration, Vec<T>>;
+
+/// One `time,id,x,y,vx,vy` row, in seconds, meters and meters per second
+struct Row {
+    time: Duration,
+    id: u8,
+    pose: Pose2d,
+    velocity: (Velocity, Velocity),
+}
+
+/// Rows of a CSV log, one per robot per frame. A header row and blank lines
+/// are skipped.
+#[track_caller]
+fn rows(csv: &str, log: &'static str) -> Result<Vec<Row>, EvalError> {
+    let location = Location::caller();
+    let mut rows = vec![];
+
+    for (i, row) in csv.lines().enumerate() {
+        let line = i + 1;
+        let row = row.trim();
+        if row.is_empty() || (line == 1 && row.starts_with("time")) {
+            continue;
+        }
+
+        let bad = |reason: String| EvalError::BadRow {
+            log,
+            line,
+            reason,
+            location,
+        };
+
+        let fields = row
+            .split(',')
+            .map(|field| field.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|err| bad(err.to_string()))?;
+
+        let [time, id, x, y, vx, vy] = fields[..] else {
+            return Err(bad(format!("expected 6 columns, found {}", fields.len())));
+        };
+
+        let time = Duration::try_from_secs_f64(time).map_err(|err| bad(err.to_string()))?;
+        if id.fract() != 0.0 || !(0.0..=u8::MAX as f64).contains(&id) {
+            return Err(bad(format!("id {id} is not a tag id")));
+        }
+
+        rows.push(Row {
+            time,
+            id: id as u8,
+            pose: Pose2d {
+                translate: Translate2d {
+                    x: Length::new::<meter>(x),
+                    y: Length::new::<meter>(y),
+                },
+                rotate: Rotate2d::default(),
+            },
+            velocity: (Velocity::new::<mps>(vx), Velocity::new::<mps>(vy)),
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Ground truth from a hand-labeled CSV log, one `time,id,x,y,vx,vy` row per
+/// robot per frame, in seconds, meters and meters per second. A header row and
+/// blank lines are skipped.
+#[track_caller]
+pub fn truth_from_csv(csv: &str) -> Result<Frames<GroundTruth>, EvalError> {
+    let mut frames = Frames::new();
+
+    for Row {
+        time,
+        id,
+        pose,
+        velocity,
+    } in rows(csv, "Ground truth")?
+    {
+        frames.entry(time).or_default().push(GroundTruth {
+            id,
+            time,
+            pose,
+            velocity,
+        });
+    }
+
+    Ok(frames)
+}
+
+/// The tracker's estimates, logged in the same layout as [`truth_from_csv`]
+/// with track ids in place of robot ids
+#[track_caller]
+pub fn estimates_from_csv(csv: &str) -> Result<Frames<Estimate>, EvalError> {
+    let mut frames = Frames::new();
+
+    for Row {
+        time,
+        id,
+        pose,
+        velocity,
+    } in rows(csv, "Estimate")?
+    {
+        frames
+            .entry(time)
+            .or_default()
+            .push(Estimate { id, pose, velocity });
+    }
+
+    Ok(frames)
+}
+
+pub fn load_truth(path: impl AsRef<Path>) -> Result<Frames<GroundTruth>, EvalError> {
+    truth_from_csv(&fs::read_to_string(path)?)
+}
+
+pub fn load_estimates(path: impl AsRef<Path>) -> Result<Frames<Estimate>, EvalError> {
+    estimates_from_csv(&fs::read_to_string(path)?)
+}
+
+/// Summary of an evaluation. Rates are fractions, distances are meters.
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Report {
+    pub frames: usize,
+    /// Ground truth objects summed over every frame
+    pub objects: usize,
+    pub matches: usize,
+    pub misses: usize,
+    pub false_positives: usize,
+    pub id_switches: usize,
+    /// Times a ground truth track was lost and later picked up again
+    pub fragmentations: usize,
+    /// Track ids that were never matched to any ground truth
+    pub false_tracks: usize,
+    pub tracks: usize,
+    /// 1 - (misses + false positives + id switches) / objects
+    pub mota: f64,
+    /// Mean distance of matched pairs
+    pub motp: f64,
+    /// `false_tracks / tracks`
+    pub false_track_rate: f64,
+    pub position_rmse: f64,
+    pub velocity_rmse: f64,
+}
+
+impl Report {
+    const CSV_HEADER: &str = "frames,objects,matches,misses,false_positives,id_switches,\
+        fragmentations,false_tracks,tracks,mota,motp,false_track_rate,position_rmse,velocity_rmse";
+
+    #[cfg(feature = "serde")]
+    pub fn to_json(&self) -> Result<String, EvalError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// A header row and a value row, so reports from several runs can be
+    /// concatenated (minus the headers) into one table
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{}\n{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            Self::CSV_HEADER,
+            self.frames,
+            self.objects,
+            self.matches,
+            self.misses,
+            self.false_positives,
+            self.id_switches,
+            self.fragmentations,
+            self.false_tracks,
+            self.tracks,
+            self.mota,
+            self.motp,
+            self.false_track_rate,
+            self.position_rmse,
+            self.velocity_rmse,
+        )
+    }
+
+    /// Writes CSV if `path` ends in `.csv` or without the `serde` feature, JSON
+    /// otherwise
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), EvalError> {
+        let path = path.as_ref();
+        let contents = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => self.to_csv(),
+            #[cfg(feature = "serde")]
+            _ => self.to_json()?,
+            #[cfg(not(feature = "serde"))]
+            _ => self.to_csv(),
+        };
+
+        Ok(fs::write(path, contents)?)
+    }
+}
+
+/// Per ground truth id bookkeeping between frames
+#[derive(Clone, Copy, Debug, Default)]
+struct TruthState {
+    /// Track it was last matched to, if ever
+    track: Option<u8>,
+    /// Whether it was matched in the last frame it appeared in
+    tracked: bool,
+}
+
+/// Accumulates matches frame by frame. Ground truth and estimates are paired
+/// if they're within `match_distance`, preferring last frame's pairs, then
+/// nearest first.
+#[derive(Clone, Debug)]
+pub struct Evaluator {
+    pub match_distance: Length,
+    frames: usize,
+    objects: usize,
+    matches: usize,
+    misses: usize,
+    false_positives: usize,
+    id_switches: usize,
+    fragmentations: usize,
+    distance_sum: f64,
+    position_sq: f64,
+    velocity_sq: f64,
+    truths: HashMap<u8, TruthState>,
+    /// Whether each track id has ever been matched
+    tracks: HashMap<u8, bool>,
+}
+
+impl Default for Evaluator {
+    fn default() -> Self {
+        Self::new(Length::new::<meter>(1.0))
+    }
+}
+
+impl Evaluator {
+    pub fn new(match_distance: Length) -> Self {
+        Self {
+            match_distance,
+            frames: 0,
+            objects: 0,
+            matches: 0,
+            misses: 0,
+            false_positives: 0,
+            id_switches: 0,
+            fragmentations: 0,
+            distance_sum: 0.0,
+            position_sq: 0.0,
+            velocity_sq: 0.0,
+            truths: HashMap::new(),
+            tracks: HashMap::new(),
+        }
+    }
+
+    fn distance(truth: &GroundTruth, estimate: &Estimate) -> f64 {
+        let dx = (truth.pose.translate.x - estimate.pose.translate.x).get::<meter>();
+        let dy = (truth.pose.translate.y - estimate.pose.translate.y).get::<meter>();
+        dx.hypot(dy)
+    }
+
+    /// Scores one frame. `truth` should only hold robots the tracker could have
+    /// seen, or every hidden robot counts as a miss.
+    ///
+    /// Returns the (truth, estimate) index pairs that were matched
+    pub fn add(&mut self, truth: &[GroundTruth], estimates: &[Estimate]) -> Vec<(usize, usize)> {
+        let gate = self.match_distance.get::<meter>();
+
+        self.frames += 1;
+        self.objects += truth.len();
+
+        for estimate in estimates {
+            self.tracks.entry(estimate.id).or_insert(false);
+        }
+
+        let mut truth_matched = vec![false; truth.len()];
+        let mut estimate_matched = vec![false; estimates.len()];
+        let mut pairs = vec![];
+
+        // keep last frame's pairs while they're still inside the gate
+        for (i, gt) in truth.iter().enumerate() {
+            let Some(track) = self.truths.get(&gt.id).and_then(|state| state.track) else {
+                continue;
+            };
+
+            let kept = estimates.iter().enumerate().find(|(j, estimate)| {
+                !estimate_matched[*j]
+                    && estimate.id == track
+                    && Self::distance(gt, estimate) <= gate
+            });
+
+            if let Some((j, _)) = kept {
+                truth_matched[i] = true;
+                estimate_matched[j] = true;
+                pairs.push((i, j));
+            }
+        }
+
+        // then everything else, nearest first
+        let candidates = truth
+            .iter()
+            .enumerate()
+            .cartesian_product(estimates.iter().enumerate())
+            .map(|((i, gt), (j, estimate))| (Self::distance(gt, estimate), i, j))
+            .filter(|(dist, ..)| *dist <= gate)
+            .sorted_by(|a, b| a.0.total_cmp(&b.0));
+
+        for (_, i, j) in candidates {
+            if truth_matched[i] || estimate_matched[j] {
+                continue;
+            }
+
+            truth_matched[i] = true;
+            estimate_matched[j] = true;
+            pairs.push((i, j));
+        }
+
+        for &(i, j) in &pairs {
+            let (gt, estimate) = (&truth[i], &estimates[j]);
+            let dist = Self::distance(gt, estimate);
+            let dvx = (gt.velocity.0 - estimate.velocity.0).get::<mps>();
+            let dvy = (gt.velocity.1 - estimate.velocity.1).get::<mps>();
+
+            self.matches += 1;
+            self.distance_sum += dist;
+            self.position_sq += dist * dist;
+            self.velocity_sq += dvx * dvx + dvy * dvy;
+            self.tracks.insert(estimate.id, true);
+
+            let state = self.truths.entry(gt.id).or_default();
+            if state.track.is_some_and(|track| track != estimate.id) {
+                self.id_switches += 1;
+            }
+            if state.track.is_some() && !state.tracked {
+                self.fragmentations += 1;
+            }
+
+            state.track = Some(estimate.id);
+            state.tracked = true;
+        }
+
+        for (i, gt) in truth.iter().enumerate() {
+            if !truth_matched[i] {
+                self.misses += 1;
+                self.truths.entry(gt.id).or_default().tracked = false;
+            }
+        }
+
+        self.false_positives += estimate_matched.iter().filter(|matched| !**matched).count();
+
+        pairs
+    }
+
+    /// Scores each labeled frame of `truth` against the frame of `estimates`
+    /// logged closest to it, if that's within `tolerance`. A labeled frame with
+    /// no estimates near it counts all of its robots as missed. Estimates
+    /// between labeled frames aren't scored.
+    pub fn add_logs(
+        &mut self,
+        truth: &Frames<GroundTruth>,
+        estimates: &Frames<Estimate>,
+        tolerance: Duration,
+    ) {
+        for (&time, truth) in truth {
+            let before = estimates.range(..=time).next_back();
+            let after = estimates.range(time..).next();
+
+            let nearest = before
+                .into_iter()
+                .chain(after)
+                .map(|(&logged, estimates)| (logged.abs_diff(time), estimates))
+                .filter(|(offset, _)| *offset <= tolerance)
+                .min_by_key(|(offset, _)| *offset)
+                .map_or(&[][..], |(_, estimates)| estimates);
+
+            self.add(truth, nearest);
+        }
+    }
+
+    pub fn report(&self) -> Report {
+        let ratio = |a: f64, b: usize| if b == 0 { 0.0 } else { a / b as f64 };
+        let false_tracks = self.tracks.values().filter(|matched| !**matched).count();
+
+        Report {
+            frames: self.frames,
+            objects: self.objects,
+            matches: self.matches,
+            misses: self.misses,
+            false_positives: self.false_positives,
+            id_switches: self.id_switches,
+            fragmentations: self.fragmentations,
+            false_tracks,
+            tracks: self.tracks.len(),
+            mota: 1.0
+                - ratio(
+                    (self.misses + self.false_positives + self.id_switches) as f64,
+                    self.objects,
+                ),
+            motp: ratio(self.distance_sum, self.matches),
+            false_track_rate: ratio(false_tracks as f64, self.tracks.len()),
+            position_rmse: ratio(self.position_sq, self.matches).sqrt(),
+            velocity_rmse: ratio(self.velocity_sq, self.matches).sqrt(),
+        }
+    }
+}
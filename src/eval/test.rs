@@ -0,0 +1,146 @@
+use super::{estimates_from_csv, truth_from_csv, Estimate, Evaluator};
+use crate::prelude::*;
+use crate::util::fixtures::pose;
+use std::time::Duration;
+
+use sim::GroundTruth;
+
+fn truth(id: u8, x: f64) -> GroundTruth {
+    GroundTruth {
+        id,
+        time: Duration::ZERO,
+        pose: pose(x, 0.0, 0.0),
+        velocity: (Velocity::new::<mps>(1.0), Velocity::default()),
+    }
+}
+
+fn estimate(id: u8, x: f64) -> Estimate {
+    Estimate {
+        id,
+        pose: pose(x, 0.0, 0.0),
+        velocity: (Velocity::new::<mps>(1.0), Velocity::default()),
+    }
+}
+
+#[test]
+fn perfect_tracking() {
+    let mut evaluator = Evaluator::default();
+
+    for i in 0..10 {
+        let x = i as f64 * 0.1;
+        evaluator.add(
+            &[truth(0, x), truth(1, x + 5.0)],
+            &[estimate(3, x + 0.1), estimate(4, x + 5.0)],
+        );
+    }
+
+    let report = evaluator.report();
+    assert_eq!(report.objects, 20);
+    assert_eq!(report.matches, 20);
+    assert_eq!(report.id_switches, 0);
+    assert_eq!(report.mota, 1.0);
+    assert!((report.motp - 0.05).abs() < 1e-9);
+    assert!((report.position_rmse - 0.005f64.sqrt()).abs() < 1e-9);
+    assert_eq!(report.velocity_rmse, 0.0);
+}
+
+#[test]
+fn misses_switches_and_fragments() {
+    let mut evaluator = Evaluator::default();
+
+    evaluator.add(&[truth(0, 0.0)], &[estimate(1, 0.0)]);
+    // lost for a frame, with a false positive elsewhere
+    evaluator.add(&[truth(0, 0.0)], &[estimate(2, 5.0)]);
+    // picked back up under a new id
+    evaluator.add(&[truth(0, 0.0)], &[estimate(3, 0.0)]);
+
+    let report = evaluator.report();
+    assert_eq!(report.matches, 2);
+    assert_eq!(report.misses, 1);
+    assert_eq!(report.false_positives, 1);
+    assert_eq!(report.id_switches, 1);
+    assert_eq!(report.fragmentations, 1);
+    assert_eq!((report.false_tracks, report.tracks), (1, 3));
+    assert!((report.mota - 0.0).abs() < 1e-9);
+}
+
+#[test]
+fn keeps_previous_pairs() {
+    let mut evaluator = Evaluator::default();
+
+    evaluator.add(&[truth(0, 0.0)], &[estimate(1, 0.0)]);
+    // a closer track shows up, but the old one is still inside the gate
+    let pairs = evaluator.add(&[truth(0, 0.0)], &[estimate(2, 0.0), estimate(1, 0.5)]);
+
+    assert_eq!(pairs, vec![(0, 1)]);
+    assert_eq!(evaluator.report().id_switches, 0);
+}
+
+#[test]
+fn truth_csv() {
+    let csv = "time,id,x,y,vx,vy\n0.0,1,2.0,3.0,0.5,0\n\n0.02, 2, 4, 5, 0, -1\n0.02,3,6,7,0,0\n";
+    let parsed = truth_from_csv(csv).unwrap();
+
+    // grouped into frames by time
+    assert_eq!(parsed.len(), 2);
+    let frame = &parsed[&Duration::from_millis(20)];
+    assert_eq!(frame.iter().map(|truth| truth.id).collect_vec(), [2, 3]);
+    assert_eq!(
+        parsed[&Duration::ZERO][0].pose.translate.y,
+        Length::new::<meter>(3.0)
+    );
+
+    assert!(truth_from_csv("0,1,2,3").is_err());
+    assert!(truth_from_csv("0,1,2,3,4,x").is_err());
+    for row in [
+        "NaN,1,0,0,0,0",
+        "inf,1,0,0,0,0",
+        "-1,1,0,0,0,0",
+        "0,1.5,0,0,0,0",
+        "0,256,0,0,0,0",
+    ] {
+        assert!(truth_from_csv(row).is_err(), "{row}");
+    }
+}
+
+#[test]
+fn evaluate_logs() {
+    let truth = truth_from_csv("0.0,1,0,0,1,0\n0.1,1,0.1,0,1,0\n0.2,1,0.2,0,1,0\n").unwrap();
+    // logged a little after each labeled frame, and never for the last
+    let estimates =
+        estimates_from_csv("time,id,x,y,vx,vy\n0.004,7,0,0,1,0\n0.104,7,0.1,0,1,0\n").unwrap();
+    assert_eq!(estimates[&Duration::from_millis(104)][0].id, 7);
+
+    let mut evaluator = Evaluator::default();
+    evaluator.add_logs(&truth, &estimates, Duration::from_millis(10));
+    let report = evaluator.report();
+
+    assert_eq!(report.frames, 3);
+    assert_eq!(report.matches, 2);
+    assert_eq!(report.misses, 1);
+    assert_eq!(report.false_positives, 0);
+}
+
+#[test]
+fn report_formats() {
+    let mut evaluator = Evaluator::default();
+    evaluator.add(&[truth(0, 0.0)], &[estimate(1, 0.2)]);
+    let report = evaluator.report();
+
+    let csv = report.to_csv();
+    let rows = csv.lines().collect_vec();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].split(',').count(), rows[1].split(',').count());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn report_json() {
+    let mut evaluator = Evaluator::default();
+    evaluator.add(&[truth(0, 0.0)], &[estimate(1, 0.2)]);
+    let report = evaluator.report();
+
+    let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+    assert_eq!(json["matches"], 1);
+    assert_eq!(json["mota"], 1.0);
+}
@@ -8,3 +8,16 @@ pub const FIELD_LENGTH: fn() -> Length = || Length::new::<meter>(16.54);
 // Units in m/s
 pub const MAX_ACCEL: fn() -> Acceleration = || Acceleration::new::<mps2>(5.0);
 pub const MAX_SPEED: fn() -> Velocity = || Velocity::new::<mps>(14.0);
+
+// PathPlanner's defaults (540deg/s, 720deg/s^2)
+pub const MAX_ANGULAR_ACCEL: fn() -> AngularAcceleration =
+    || AngularAcceleration::new::<radps2>(4.0 * std::f64::consts::PI);
+pub const MAX_ANGULAR_SPEED: fn() -> AngularVelocity =
+    || AngularVelocity::new::<radps>(3.0 * std::f64::consts::PI);
+
+// Bumpers included, assuming everyone builds up to the frame perimeter limit
+pub const ROBOT_SIZE: fn() -> Length = || Length::new::<meter>(0.9);
+pub const BUMPER_HEIGHT: fn() -> Length = || Length::new::<meter>(0.15);
+
+// 2024 tags are 36h11, 6.5in across the black border
+pub const TAG_SIZE: fn() -> Length = || Length::new::<meter>(0.1651);
@@ -2,14 +2,21 @@ use crate::prelude::*;
 use std::time::Instant;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct DataPoint {
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "export::micros_since_start")
+    )]
     pub time: Instant,
     pub pose: Pose2d,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "export::meters_pair"))]
     pub size: (Length, Length),
     pub confidence: f64,
 }
 
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Enemy {
     pub id: u8,
     pub history: Vec<DataPoint>,
@@ -44,7 +51,7 @@ impl Enemy {
                     ..
                 },
             ..
-        } = self.entry(n);
+        } = self.entry(n + 1);
 
         let dt = t2 - t1;
         let dt = Time::new::<second>(dt.as_secs_f64());
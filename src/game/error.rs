@@ -0,0 +1,24 @@
+use std::io;
+use std::{backtrace::Backtrace, panic::Location};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum LayoutError {
+    #[error("At {location}: IO error:\n{source}")]
+    IOError {
+        #[from]
+        source: io::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[cfg(feature = "json")]
+    #[error("At {location}: Malformed field layout JSON:\n{source}")]
+    JsonError {
+        #[from]
+        source: serde_json::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+}
@@ -0,0 +1,121 @@
+use crate::prelude::*;
+#[cfg(feature = "json")]
+use std::{fs, path::Path};
+
+#[cfg(feature = "json")]
+use super::error::LayoutError;
+
+// On-disk layout of WPILib's AprilTagFieldLayout JSON
+#[cfg(feature = "json")]
+#[derive(serde::Deserialize)]
+struct LayoutFile {
+    tags: Vec<TagFile>,
+    field: FieldFile,
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Deserialize)]
+struct TagFile {
+    #[serde(rename = "ID")]
+    id: u32,
+    pose: PoseFile,
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Deserialize)]
+struct PoseFile {
+    translation: TranslationFile,
+    rotation: RotationFile,
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Deserialize)]
+struct TranslationFile {
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Deserialize)]
+struct RotationFile {
+    quaternion: QuaternionFile,
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+struct QuaternionFile {
+    w: f64,
+    x: f64,
+    y: f64,
+    z: f64,
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Deserialize)]
+struct FieldFile {
+    length: f64,
+    width: f64,
+}
+
+/// A tag on the field. Its pose's x axis points out of the tag's face.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AprilTag {
+    pub id: u32,
+    pub pose: Transform3d,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldLayout {
+    pub tags: Vec<AprilTag>,
+    pub length: Length,
+    pub width: Length,
+}
+
+impl FieldLayout {
+    #[cfg(feature = "json")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LayoutError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn from_json(json: &str) -> Result<Self, LayoutError> {
+        let file: LayoutFile = serde_json::from_str(json)?;
+
+        Ok(Self {
+            tags: file
+                .tags
+                .into_iter()
+                .map(|tag| {
+                    let TranslationFile { x, y, z } = tag.pose.translation;
+                    let QuaternionFile {
+                        w,
+                        x: qx,
+                        y: qy,
+                        z: qz,
+                    } = tag.pose.rotation.quaternion;
+
+                    AprilTag {
+                        id: tag.id,
+                        pose: Transform3d {
+                            translation: Translate3d { x, y, z },
+                            rotation: Quaternion {
+                                w,
+                                x: qx,
+                                y: qy,
+                                z: qz,
+                            },
+                        },
+                    }
+                })
+                .collect(),
+            length: Length::new::<meter>(file.field.length),
+            width: Length::new::<meter>(file.field.width),
+        })
+    }
+
+    pub fn tag(&self, id: u32) -> Option<&AprilTag> {
+        self.tags.iter().find(|tag| tag.id == id)
+    }
+}
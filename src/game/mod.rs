@@ -5,3 +5,9 @@ use crate::prelude::*;
 
 pub mod consts;
 pub mod enemy;
+pub mod error;
+pub mod layout;
+pub mod tracker;
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,39 @@
+use super::enemy::{DataPoint, Enemy};
+use crate::prelude::*;
+use crate::util::fixtures::pose;
+use std::time::{Duration, Instant};
+
+fn at(time: Instant, x: f64, y: f64) -> DataPoint {
+    DataPoint {
+        time,
+        pose: pose(x, y, 0.0),
+        size: Default::default(),
+        confidence: 1.0,
+    }
+}
+
+#[test]
+fn enemy_velocity() {
+    let start = Instant::now();
+    let enemy = Enemy {
+        id: 0,
+        history: vec![
+            at(start, 0.0, 0.0),
+            at(start + Duration::from_millis(500), 1.0, -0.5),
+            at(start + Duration::from_secs(1), 3.0, -0.5),
+        ],
+    };
+
+    // the newest point against the one before it, not against itself
+    let ((vx, vy), dt) = enemy.velocity(0);
+    assert!((vx.get::<mps>() - 4.0).abs() < 1e-9);
+    assert!(vy.get::<mps>().abs() < 1e-9);
+    assert!((dt.get::<second>() - 0.5).abs() < 1e-9);
+
+    let ((vx, vy), _) = enemy.velocity(1);
+    assert!((vx.get::<mps>() - 2.0).abs() < 1e-9);
+    assert!((vy.get::<mps>() + 1.0).abs() < 1e-9);
+
+    let ((ax, _), _) = enemy.acceleration(0);
+    assert!((ax.get::<mps2>() - 4.0).abs() < 1e-9);
+}
@@ -0,0 +1,144 @@
+use crate::prelude::*;
+use std::time::{Duration, Instant};
+
+use super::{
+    consts::ROBOT_SIZE,
+    enemy::{DataPoint, Enemy},
+};
+
+/// Data points used to estimate velocity
+const VELOCITY_WINDOW: usize = 10;
+
+/// Associates each frame's enemy positions with the enemies we already know
+/// about, by nearest predicted position
+#[derive(Clone, Debug)]
+pub struct Tracker {
+    pub enemies: Vec<Enemy>,
+    /// Detections further than this from every prediction start a new enemy
+    pub gate: Length,
+    /// Enemies that haven't been seen for this long are forgotten
+    pub max_age: Duration,
+    /// Data points kept per enemy
+    pub max_history: usize,
+    next_id: u8,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Self {
+            enemies: vec![],
+            gate: Length::new::<meter>(1.0),
+            max_age: Duration::from_millis(500),
+            max_history: 50,
+            next_id: 0,
+        }
+    }
+}
+
+impl Tracker {
+    /// Average velocity over the last few data points. Consecutive points are too
+    /// close together in time to difference without amplifying the noise.
+    pub fn velocity(enemy: &Enemy) -> (Velocity, Velocity) {
+        let n = enemy.history.len().min(VELOCITY_WINDOW).saturating_sub(1);
+        if n == 0 {
+            return Default::default();
+        }
+
+        let (old, new) = (enemy.entry(n), enemy.entry(0));
+        let dt = Time::new::<second>((new.time - old.time).as_secs_f64());
+        if dt <= Time::default() {
+            return Default::default();
+        }
+
+        (
+            (new.pose.translate.x - old.pose.translate.x) / dt,
+            (new.pose.translate.y - old.pose.translate.y) / dt,
+        )
+    }
+
+    /// Where `enemy` should be at `time`, assuming it kept its recent velocity
+    pub fn predict(enemy: &Enemy, time: Instant) -> Translate2d {
+        let Translate2d { x, y } = enemy.pose().translate;
+        let (vx, vy) = Self::velocity(enemy);
+        let dt = Time::new::<second>(
+            time.saturating_duration_since(enemy.last_update())
+                .as_secs_f64(),
+        );
+
+        Translate2d {
+            x: x + vx * dt,
+            y: y + vy * dt,
+        }
+    }
+
+    pub fn update(&mut self, res: &PreprocessorResponse) -> &[Enemy] {
+        let time = res.timestamp;
+        let gate = self.gate.get::<meter>();
+
+        let predictions = self
+            .enemies
+            .iter()
+            .map(|enemy| Self::predict(enemy, time))
+            .collect_vec();
+
+        // every (enemy, detection) pair inside the gate, closest first
+        let pairs = predictions
+            .iter()
+            .enumerate()
+            .cartesian_product(res.enemies.iter().enumerate())
+            .map(|((i, predicted), (j, detected))| {
+                let dx = (detected.translate.x - predicted.x).get::<meter>();
+                let dy = (detected.translate.y - predicted.y).get::<meter>();
+                (dx.hypot(dy), i, j)
+            })
+            .filter(|(dist, ..)| *dist <= gate)
+            .sorted_by(|a, b| a.0.total_cmp(&b.0));
+
+        let mut matched_enemies = vec![false; self.enemies.len()];
+        let mut matched_detections = vec![false; res.enemies.len()];
+
+        for (_, i, j) in pairs {
+            if matched_enemies[i] || matched_detections[j] {
+                continue;
+            }
+
+            matched_enemies[i] = true;
+            matched_detections[j] = true;
+            self.enemies[i].add_dp(Self::datapoint(res.enemies[j], time));
+        }
+
+        for (j, pose) in res.enemies.iter().enumerate() {
+            if matched_detections[j] {
+                continue;
+            }
+
+            self.enemies.push(Enemy {
+                id: self.next_id,
+                history: vec![Self::datapoint(*pose, time)],
+            });
+            self.next_id = self.next_id.wrapping_add(1);
+        }
+
+        let max_age = self.max_age;
+        let max_history = self.max_history;
+
+        self.enemies
+            .retain(|enemy| time.saturating_duration_since(enemy.last_update()) <= max_age);
+
+        for enemy in &mut self.enemies {
+            let excess = enemy.history.len().saturating_sub(max_history);
+            enemy.history.drain(..excess);
+        }
+
+        &self.enemies
+    }
+
+    fn datapoint(pose: Pose2d, time: Instant) -> DataPoint {
+        DataPoint {
+            time,
+            pose,
+            size: (ROBOT_SIZE(), ROBOT_SIZE()),
+            confidence: 1.0,
+        }
+    }
+}
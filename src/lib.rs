@@ -0,0 +1,47 @@
+#![feature(
+    never_type,
+    error_generic_member_access,
+    trait_alias,
+    const_float_methods,
+    array_windows,
+    array_try_from_fn,
+    async_fn_track_caller,
+    stmt_expr_attributes
+)]
+
+// so derives can name this crate from inside it
+extern crate self as pathforger;
+
+extern crate futures;
+extern crate itertools;
+extern crate lapjv;
+extern crate ndarray;
+extern crate ndarray_linalg;
+extern crate nt_client;
+extern crate photon_serde_derive;
+extern crate rand;
+#[cfg(feature = "serde")]
+extern crate rmp_serde;
+#[cfg(feature = "json")]
+extern crate serde;
+#[cfg(feature = "json")]
+extern crate serde_json;
+extern crate thiserror;
+extern crate tokio;
+extern crate tracing;
+extern crate tracing_appender;
+extern crate tracing_subscriber;
+extern crate uom;
+
+mod error;
+pub mod eval;
+pub mod game;
+pub mod networktables;
+pub mod photon_serde;
+pub mod planner;
+pub mod prelude;
+pub mod render;
+#[cfg(feature = "json")]
+pub mod service;
+pub mod sim;
+pub mod util;
@@ -1,30 +1,361 @@
-#![feature(
-    never_type,
-    error_generic_member_access,
-    trait_alias,
-    const_float_methods,
-    array_windows,
-    stmt_expr_attributes
-)]
-
-extern crate futures;
-extern crate itertools;
-extern crate lapjv;
-extern crate ndarray;
-extern crate ndarray_linalg;
-extern crate nt_client;
-extern crate thiserror;
-extern crate tokio;
-extern crate uom;
-
-mod error;
-mod game;
-mod networktables;
-mod photon_serde;
-mod prelude;
-mod util;
+//! The service the coprocessor runs at boot. Reads photon results and the
+//! robot's pose and destination over NT, tracks enemy robots, and publishes a
+//! path around them until it's told to stop.
+
+use futures::FutureExt;
+use nt_client::NTAddr;
+use pathforger::{
+    networktables::{codec::PathPublisher, worker},
+    planner::trajectory::Trajectory,
+    prelude::*,
+    render::Renderer,
+    service::{logging, prometheus, CameraConfig, Config, Exit, Pipeline},
+    util::{
+        metrics::{Metrics, Stage},
+        preprocessor,
+    },
+};
+use std::{
+    env,
+    net::Ipv4Addr,
+    process::ExitCode,
+    sync::{Arc, Mutex},
+    time::Instant,
+};
+use tracing::{debug_span, info, warn, Instrument};
+
+const USAGE: &str = "\
+usage: pathforger [options]
+
+  --config <path>        JSON config, which the options below override
+  --team <n>             connect to this team's robot (default localhost)
+  --address <ip>         connect to this address instead
+  --camera <name>        read this camera, can be repeated (default every
+                         camera in the config)
+  --navgrid <path>       PathPlanner navgrid.json (default an empty field)
+  --log-level <level>    error, warn, info or debug (default info, or
+                         RUST_LOG). debug logs how long each stage took
+  --log-file <path>      also log to this file, rotated daily
+  --replay <path>        write an animated SVG of the session when it stops
+  --photon-version <v>   decode as 2023, 2024 or 2025 (default detects it)
+  --metrics <addr:port>  serve the /pathforger/status metrics for Prometheus
+
+exit codes:
+  0  stopped by Ctrl-C or SIGTERM
+  2  bad arguments, config or navgrid
+  3  NetworkTables failed in a way reconnecting won't fix
+  4  photon results kept failing to decode, or their schema doesn't match
+  5  robot code's poses kept failing to decode
+  6  the replay couldn't be written";
+
+/// The config, and the cameras named without a mount, which can only be
+/// warned about once logging is up. `None` if only the usage was asked for.
+fn parse_args() -> Result<Option<(Config, Vec<String>)>, String> {
+    let mut args = env::args().skip(1).collect_vec();
+
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        return Ok(None);
+    }
+
+    // the config is the base everything else overrides, wherever it's given
+    let mut config = match args.iter().position(|arg| arg == "--config") {
+        Some(i) => {
+            let path = args
+                .get(i + 1)
+                .ok_or("--config needs a value".to_string())?
+                .clone();
+            args.drain(i..i + 2);
+            Config::load(&path).map_err(|err| format!("{path}: {err}"))?
+        }
+        None => Config::default(),
+    };
+
+    let mut cameras = vec![];
+    let mut unmounted = vec![];
+    let mut args = args.into_iter();
+
+    while let Some(flag) = args.next() {
+        let mut value = || args.next().ok_or(format!("{flag} needs a value"));
+
+        match flag.as_str() {
+            "--team" => {
+                let team = value()?;
+                let team = team
+                    .parse()
+                    .map_err(|_| format!("--team: {team} is not a team number"))?;
+                config.worker.addr = NTAddr::TeamNumber(team);
+            }
+            "--address" => {
+                let ip = value()?;
+                let ip = ip
+                    .parse::<Ipv4Addr>()
+                    .map_err(|_| format!("--address: {ip} is not an IPv4 address"))?;
+                config.worker.addr = NTAddr::Custom(ip);
+            }
+            "--camera" => cameras.push(value()?),
+            "--navgrid" => config.navgrid = Some(value()?.into()),
+            "--log-level" => config.log.level = value()?.parse().map_err(|err| format!("{err}"))?,
+            "--log-file" => config.log.file = Some(value()?.into()),
+            "--replay" => config.replay = Some(value()?.into()),
+            "--metrics" => {
+                let addr = value()?;
+                let addr = addr
+                    .parse()
+                    .map_err(|_| format!("--metrics: {addr} is not an address and port"))?;
+                config.metrics_address = Some(addr);
+            }
+            "--photon-version" => {
+                config.worker.photon_version =
+                    Some(value()?.parse().map_err(|err| format!("{err}"))?);
+            }
+            other => return Err(format!("unknown option {other}\n\n{USAGE}")),
+        }
+    }
+
+    // cameras named on the command line replace the config's, keeping mounts
+    // for the ones it knows
+    if !cameras.is_empty() {
+        config.cameras = cameras
+            .iter()
+            .map(|name| {
+                config
+                    .cameras
+                    .iter()
+                    .find(|camera| &camera.name == name)
+                    .cloned()
+                    .unwrap_or_else(|| {
+                        unmounted.push(name.clone());
+                        CameraConfig::centered(name)
+                    })
+            })
+            .collect();
+    }
+
+    if config.cameras.is_empty() {
+        return Err(format!("no cameras given\n\n{USAGE}"));
+    }
+
+    Ok(Some((config, unmounted)))
+}
+
+/// Resolves on Ctrl-C, or on SIGTERM from the service manager
+async fn shutdown() {
+    let terminate = async {
+        #[cfg(unix)]
+        if let Ok(mut signal) =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+        {
+            signal.recv().await;
+            return;
+        }
+
+        std::future::pending::<()>().await
+    };
+
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {}
+        _ = terminate => {}
+    }
+}
 
 #[tokio::main]
-async fn main() {
-    println!("Hello, world!");
+async fn main() -> ExitCode {
+    let (config, unmounted) = match parse_args() {
+        Ok(Some(parsed)) => parsed,
+        Ok(None) => {
+            println!("{USAGE}");
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("{message}");
+            return Exit::Config.into();
+        }
+    };
+
+    // flushes the log file when main returns
+    let _log = match logging::init(&config.log) {
+        Ok(guard) => guard,
+        Err(err) => {
+            eprintln!("{err}");
+            return Exit::Config.into();
+        }
+    };
+
+    for camera in unmounted {
+        warn!("{camera} has no mount in the config, assuming the robot's center");
+    }
+
+    let navgrid = match config.load_navgrid() {
+        Ok(navgrid) => navgrid,
+        Err(err) => {
+            logging::report(&err);
+            return Exit::Config.into();
+        }
+    };
+
+    time::initialize();
+
+    if let Some(addr) = config.metrics_address {
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                warn!("Couldn't serve metrics on {addr}");
+                logging::report(&err);
+                return Exit::Config.into();
+            }
+        };
+
+        info!("Serving metrics on {addr}");
+        tokio::spawn(prometheus::serve(listener, config.worker.metrics.clone()));
+    }
+
+    let pipeline = Arc::new(Mutex::new(Pipeline::new(&config, navgrid)));
+    let cameras = config
+        .cameras
+        .iter()
+        .map(|camera| camera.name.clone())
+        .collect_vec();
+
+    info!(
+        "Reading {} from {:?}",
+        cameras.join(", "),
+        config.worker.addr
+    );
+
+    // the callbacks are written inline so their signatures are inferred from
+    // `worker`'s bounds, which they have to be generic over the borrows in
+    let worker = worker(
+        cameras,
+        {
+            let pipeline = pipeline.clone();
+            move |_: &mut _, pose| {
+                pipeline.lock().unwrap().robot = Some(pose);
+                async {}.boxed()
+            }
+        },
+        {
+            let pipeline = pipeline.clone();
+            let metrics = config.worker.metrics.clone();
+            move |path: &mut PathPublisher, camera: &str, result| {
+                let pipeline = pipeline.clone();
+                let metrics = metrics.clone();
+                let camera = camera.to_string();
+
+                async move {
+                    let (mount, robot) = {
+                        let pipeline = pipeline.lock().unwrap();
+                        (pipeline.cameras.get(&camera).copied(), pipeline.robot)
+                    };
+
+                    // results from before we know where we are can't be placed
+                    let (Some(mount), Some(robot)) = (mount, robot) else {
+                        metrics.drop_frame();
+                        return;
+                    };
+
+                    let response = {
+                        let _timer = metrics.start(Stage::Preprocess);
+                        preprocessor::photon(result, mount, robot)
+                            .instrument(debug_span!("preprocess"))
+                            .await
+                    };
+                    let update = {
+                        let mut pipeline = pipeline.lock().unwrap();
+                        debug_span!("track").in_scope(|| pipeline.track(&response));
+                        replan(&mut pipeline)
+                    };
+
+                    publish(path, update, &metrics).await;
+                }
+                .boxed()
+            }
+        },
+        {
+            let pipeline = pipeline.clone();
+            let metrics = config.worker.metrics.clone();
+            move |path: &mut PathPublisher, dest| {
+                let update = {
+                    let mut pipeline = pipeline.lock().unwrap();
+                    pipeline.dest = Some(dest);
+                    replan(&mut pipeline)
+                };
+
+                let metrics = metrics.clone();
+
+                async move {
+                    publish(path, update, &metrics).await;
+                }
+                .boxed()
+            }
+        },
+        {
+            let pipeline = pipeline.clone();
+            move |field: &mut _| {
+                let mut pipeline = pipeline.lock().unwrap();
+                *field = pipeline.field();
+                pipeline.record(time::duration_of(Instant::now()));
+                async {}.boxed()
+            }
+        },
+        config.worker.clone(),
+    );
+
+    let exit = tokio::select! {
+        Err(err) = worker => {
+            logging::report(&err);
+            Exit::of(&err)
+        }
+        _ = shutdown() => {
+            info!("Shutting down");
+            Exit::Shutdown
+        }
+    };
+
+    if let Some(path) = &config.replay {
+        let pipeline = pipeline.lock().unwrap();
+        let replay = pipeline.replay.as_deref().unwrap_or_default();
+        let navgrid = Some(&pipeline.navgrid);
+
+        if let Err(err) = Renderer::default().save_animation(navgrid, replay, path) {
+            warn!("Couldn't write the replay to {}", path.display());
+            logging::report(&err);
+            return Exit::Replay.into();
+        }
+    }
+
+    exit.into()
+}
+
+/// What a replan means for the published path
+enum PathUpdate {
+    Publish(Trajectory),
+    /// There's nowhere to go, or we're there
+    Clear,
+    /// Planning failed, the next change may well succeed
+    Keep,
+}
+
+/// Replans after a change. A failed plan is only worth a warning.
+fn replan(pipeline: &mut Pipeline) -> PathUpdate {
+    let _plan = debug_span!("plan").entered();
+
+    match pipeline.replan() {
+        Ok(Some(trajectory)) => PathUpdate::Publish(trajectory.clone()),
+        Ok(None) => PathUpdate::Clear,
+        Err(err) => {
+            warn!("Keeping the old path\n{err}");
+            PathUpdate::Keep
+        }
+    }
+}
+
+async fn publish(path: &PathPublisher, update: PathUpdate, metrics: &Metrics) {
+    let _timer = metrics.start(Stage::Publish);
+    let span = debug_span!("publish");
+
+    match update {
+        PathUpdate::Publish(trajectory) => path.set(&trajectory).instrument(span).await,
+        PathUpdate::Clear => path.invalidate().instrument(span).await,
+        PathUpdate::Keep => {}
+    }
 }
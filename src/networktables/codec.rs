@@ -0,0 +1,122 @@
+use crate::prelude::*;
+use nt_client::publish::Publisher;
+use std::{sync::Arc, time::Instant};
+use tokio::sync::Mutex;
+
+use super::{error::PhotonWorkerError, watchdog::Watchdog, TypedPublisher};
+use planner::trajectory::Trajectory;
+
+/// How a topic's bytes are laid out, picked from the type string it was
+/// announced with
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Codec {
+    /// photon_serde's layout, for `raw` and anything we don't recognise
+    #[default]
+    Photon,
+    /// `struct:...`
+    Struct,
+    /// `proto:...`
+    Proto,
+}
+
+impl Codec {
+    pub fn from_type(type_string: &str) -> Self {
+        if type_string.starts_with("struct:") {
+            Self::Struct
+        } else if type_string.starts_with("proto:") {
+            Self::Proto
+        } else {
+            Self::Photon
+        }
+    }
+
+    #[track_caller]
+    pub fn decode<T>(&self, bytes: &[u8]) -> Result<T, PhotonWorkerError>
+    where
+        T: Deserialize + WpiStruct + WpiProto,
+    {
+        Ok(match self {
+            Self::Photon => deserialize(bytes)?,
+            Self::Struct => T::decode(bytes)?,
+            Self::Proto => T::from_proto(bytes)?,
+        })
+    }
+
+    pub fn encode<T>(&self, value: &T) -> Vec<u8>
+    where
+        T: WpiStruct + WpiProto,
+    {
+        match self {
+            // photon's layouts match WPILib's structs for everything we send
+            Self::Photon | Self::Struct => value.encode(),
+            Self::Proto => value.to_proto(),
+        }
+    }
+}
+
+/// `/pathforger/path`, in whichever representation the robot code reads, with
+/// its validity and expiry. Clones publish to the same topics.
+#[derive(Clone)]
+pub struct PathPublisher {
+    pub publisher: Arc<TypedPublisher>,
+    pub codec: Codec,
+    pub valid: Arc<Publisher<bool>>,
+    pub expiry: Arc<Publisher<i64>>,
+    pub watchdog: Arc<Watchdog>,
+    /// Held across a whole [`Self::set`] or [`Self::invalidate`], so the
+    /// watchdog's invalidation can't land between a path and its validity
+    pub writing: Arc<Mutex<()>>,
+}
+
+impl PathPublisher {
+    /// What the path is announced as with `codec`, which is what WPILib's
+    /// subscribers match on
+    pub fn type_string(codec: Codec) -> String {
+        match codec {
+            Codec::Proto => Trajectory::proto_type_string(),
+            Codec::Photon | Codec::Struct => format!("struct:{}[]", Pose2d::NAME),
+        }
+    }
+
+    /// A `proto:Trajectory` with the proto codec. Otherwise the states' poses as
+    /// a `struct:Pose2d[]`, since WPILib has no struct form of a trajectory.
+    pub fn encode(&self, trajectory: &Trajectory) -> Vec<u8> {
+        match self.codec {
+            Codec::Proto => trajectory.to_proto(),
+            Codec::Photon | Codec::Struct => {
+                let poses = trajectory
+                    .states
+                    .iter()
+                    .map(|state| state.pose)
+                    .collect_vec();
+                Pose2d::encode_array(&poses)
+            }
+        }
+    }
+
+    /// Publishes `trajectory` as valid until [`Watchdog::expiry`]. Does nothing
+    /// while the watchdog finds the worker stale, since a path that finished
+    /// after the deadline mustn't undo the watchdog's invalidation.
+    pub async fn set(&self, trajectory: &Trajectory) {
+        let _writing = self.writing.lock().await;
+
+        if let Some(stale) = self.watchdog.check(Instant::now()) {
+            tracing::debug!("Dropping a path planned while stale: {}", stale.reason());
+            return;
+        }
+
+        self.publisher.set(self.encode(trajectory)).await;
+        self.expiry.set(self.watchdog.expiry()).await;
+        self.valid.set(true).await;
+    }
+
+    /// Replaces the path with an empty one, marked invalid, so nothing is
+    /// followed until the next [`Self::set`]
+    pub async fn invalidate(&self) {
+        let _writing = self.writing.lock().await;
+        self.valid.set(false).await;
+        self.publisher
+            .set(self.encode(&Trajectory::default()))
+            .await;
+    }
+}
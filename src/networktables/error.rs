@@ -1,14 +1,28 @@
-use nt_client::publish::NewPublisherError;
+use nt_client::{publish::NewPublisherError, ConnectError};
 use std::io;
 use std::{backtrace::Backtrace, panic::Location};
 use tokio::sync::broadcast::error::RecvError;
 
 use thiserror::Error;
 
-use super::DeserializeError;
+use super::{DeserializeError, ProtoError, SchemaError, StructError};
+use crate::util::metrics::ErrorClass;
 
 #[derive(Error, Debug)]
 pub enum PhotonWorkerError {
+    #[error("At {location}: Networktables failed to connect:\n{source}")]
+    ConnectError {
+        #[from]
+        source: ConnectError,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Networktables disconnected")]
+    Disconnected {
+        location: &'static Location<'static>,
+    },
+
     #[error("At {location}: Networktables failed to recieve:\n{source}")]
     NTError {
         #[from]
@@ -40,4 +54,72 @@ pub enum PhotonWorkerError {
         location: &'static Location<'static>,
         backtrace: Backtrace,
     },
+
+    #[error("At {location}: WPILib struct error:\n{source}")]
+    StructError {
+        #[from]
+        source: StructError,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Protobuf error:\n{source}")]
+    ProtoError {
+        #[from]
+        source: ProtoError,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Schema error:\n{source}")]
+    SchemaError {
+        #[from]
+        source: SchemaError,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+}
+
+impl PhotonWorkerError {
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            // the robot rebooting, or a busy server
+            PhotonWorkerError::ConnectError { .. }
+            | PhotonWorkerError::Disconnected { .. }
+            | PhotonWorkerError::NTPublishError { .. } => ErrorClass::Transient,
+            // fell behind and missed some messages, the next ones are fine
+            PhotonWorkerError::NTError {
+                source: RecvError::Lagged(_),
+                ..
+            } => ErrorClass::Transient,
+            PhotonWorkerError::DeserializationError { .. }
+            | PhotonWorkerError::StructError { .. }
+            | PhotonWorkerError::ProtoError { .. } => ErrorClass::Frame,
+            // a result that doesn't fit its published layout
+            PhotonWorkerError::SchemaError {
+                source: SchemaError::Truncated { .. } | SchemaError::MissingField { .. },
+                ..
+            } => ErrorClass::Frame,
+            // every later result has the same layout, so refusing one refuses
+            // them all
+            PhotonWorkerError::SchemaError { .. }
+            | PhotonWorkerError::NTError { .. }
+            | PhotonWorkerError::IOError { .. } => ErrorClass::Fatal,
+        }
+    }
+
+    /// The variant's name, for telling apart errors that are logged together
+    pub fn kind(&self) -> &'static str {
+        match self {
+            PhotonWorkerError::ConnectError { .. } => "connect",
+            PhotonWorkerError::Disconnected { .. } => "disconnected",
+            PhotonWorkerError::NTError { .. } => "receive",
+            PhotonWorkerError::NTPublishError { .. } => "publish",
+            PhotonWorkerError::IOError { .. } => "io",
+            PhotonWorkerError::DeserializationError { .. } => "deserialize",
+            PhotonWorkerError::StructError { .. } => "struct",
+            PhotonWorkerError::ProtoError { .. } => "proto",
+            PhotonWorkerError::SchemaError { .. } => "schema",
+        }
+    }
 }
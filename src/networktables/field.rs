@@ -0,0 +1,125 @@
+use crate::prelude::*;
+use std::time::Duration;
+
+use game::{enemy::Enemy, tracker::Tracker};
+use nt_client::{
+    data::Properties,
+    publish::{NewPublisherError, Publisher},
+    topic::Topic,
+    Client,
+};
+
+/// Where our field widget lives. Glass and AdvantageScope pick it up from the
+/// `.type` entry.
+pub const FIELD_TABLE: &str = "/pathforger/field";
+
+/// Everything shown on the dashboard's field widget
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Field {
+    /// Our own pose, as the widget's main robot
+    pub robot: Option<Pose2d>,
+    pub enemies: Vec<Pose2d>,
+    /// Where the enemies should be a little into the future
+    pub predicted: Vec<Pose2d>,
+    pub path: Vec<Pose2d>,
+}
+
+impl Field {
+    /// Enemy poses from the tracker, with predictions `lookahead` after each
+    /// enemy was last seen
+    pub fn new(robot: Pose2d, enemies: &[Enemy], lookahead: Duration, path: Vec<Pose2d>) -> Self {
+        Self {
+            robot: Some(robot),
+            enemies: enemies.iter().map(Enemy::pose).collect(),
+            predicted: enemies
+                .iter()
+                .map(|enemy| Pose2d {
+                    translate: Tracker::predict(enemy, enemy.last_update() + lookahead),
+                    rotate: enemy.pose().rotate,
+                })
+                .collect(),
+            path,
+        }
+    }
+
+    /// Field2d's `double[]` layout: `[x, y, degrees]` per pose, back to back
+    pub fn to_doubles(poses: &[Pose2d]) -> Vec<f64> {
+        poses
+            .iter()
+            .flat_map(|pose| {
+                [
+                    pose.translate.x.get::<meter>(),
+                    pose.translate.y.get::<meter>(),
+                    pose.rotate.angle.get::<degree>(),
+                ]
+            })
+            .collect()
+    }
+}
+
+/// The topics of a `Field2d` under [`FIELD_TABLE`], made before connecting
+pub struct FieldTopics {
+    kind: Topic,
+    robot: Topic,
+    enemies: Topic,
+    predicted: Topic,
+    path: Topic,
+}
+
+impl FieldTopics {
+    pub fn new(nt: &Client) -> Self {
+        let topic = |name: &str| nt.topic(format!("{FIELD_TABLE}/{name}"));
+
+        Self {
+            kind: topic(".type"),
+            robot: topic("Robot"),
+            enemies: topic("Enemies"),
+            predicted: topic("Predicted"),
+            path: topic("Path"),
+        }
+    }
+}
+
+/// Publishes a [`Field`] as a `Field2d` under [`FIELD_TABLE`]
+pub struct FieldPublisher {
+    /// Never updated, but the topic goes away if it's dropped
+    _kind: Publisher<String>,
+    robot: Publisher<Vec<f64>>,
+    enemies: Publisher<Vec<f64>>,
+    predicted: Publisher<Vec<f64>>,
+    path: Publisher<Vec<f64>>,
+}
+
+impl FieldPublisher {
+    pub async fn new(topics: &FieldTopics) -> Result<Self, NewPublisherError> {
+        let properties = || Properties {
+            persistent: Some(false),
+            retained: Some(true),
+            cached: Some(true),
+            ..Default::default()
+        };
+
+        let kind = topics.kind.publish::<String>(properties()).await?;
+        kind.set("Field2d".to_string()).await;
+
+        Ok(Self {
+            _kind: kind,
+            robot: topics.robot.publish(properties()).await?,
+            enemies: topics.enemies.publish(properties()).await?,
+            predicted: topics.predicted.publish(properties()).await?,
+            path: topics.path.publish(properties()).await?,
+        })
+    }
+
+    pub async fn publish(&self, field: &Field) {
+        if let Some(robot) = field.robot {
+            self.robot.set(Field::to_doubles(&[robot])).await;
+        }
+
+        self.enemies.set(Field::to_doubles(&field.enemies)).await;
+        self.predicted
+            .set(Field::to_doubles(&field.predicted))
+            .await;
+        self.path.set(Field::to_doubles(&field.path)).await;
+    }
+}
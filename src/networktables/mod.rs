@@ -1,103 +1,621 @@
+pub mod codec;
 pub mod error;
+pub mod field;
+pub mod recovery;
+pub mod status;
+pub mod watchdog;
+
+#[cfg(test)]
+mod test;
 
 use crate::prelude::*;
-use std::time::Duration;
+use std::{
+    collections::HashMap,
+    panic::Location,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use codec::{Codec, PathPublisher};
 use error::*;
+use field::{Field, FieldPublisher, FieldTopics};
 use futures::future::BoxFuture;
+use metrics::{Metrics, Stage};
 use nt_client::{
-    data::{r#type::RawData, Properties, SubscriptionOptions},
-    publish::Publisher,
-    subscribe::ReceivedMessage,
-    Client,
+    data::{Properties, SubscriptionOptions},
+    publish::{GenericPublisher, NewPublisherError},
+    subscribe::{ReceivedMessage, Subscriber},
+    topic::Topic,
+    Client, NTAddr, NewClientOptions,
 };
-use tokio::runtime;
+use recovery::{Backoff, Recovery, RecoveryPolicy};
+use status::{StatusPublisher, StatusTopics};
+use tokio::time::{interval, sleep, timeout_at};
+use tracing::{debug_span, Instrument};
+use watchdog::{Watchdog, WatchdogOptions, WatchdogTopics, PATH_EXPIRY_TOPIC, PATH_VALID_TOPIC};
 
 pub trait ThreadSafe = Send + Sync + 'static;
 
-pub async fn worker<'a, C0, C1, C2>(
-    camera: String,
+/// What to do when photon's published result layout doesn't match ours
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SchemaPolicy {
+    /// Stop the worker rather than act on garbled targets
+    #[default]
+    Refuse,
+    /// Print a warning and decode by the published layout instead, which works
+    /// as long as the fields we read are still there
+    Warn,
+}
+
+/// How a camera's results are decoded, once their type string is checked
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PhotonLayout {
+    /// With [`PhotonVersion::decode`]
+    Compiled,
+    /// By the published schema of this name, with [`SchemaRegistry::decode_photon`]
+    Published(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct WorkerOptions {
+    /// Where the NT server runs, usually the roboRIO
+    pub addr: NTAddr,
+    pub path_codec: Codec,
+    /// The layout photon's results are decoded with, or `None` to detect it per
+    /// camera from the first result that fits one, and again after a result
+    /// fails to decode
+    pub photon_version: Option<PhotonVersion>,
+    pub schema_policy: SchemaPolicy,
+    /// How long to collect `/.schema` topics at startup, before any results are
+    /// decoded. Ones published later are picked up as they arrive.
+    pub schema_timeout: Duration,
+    /// Filled in as the worker runs, and published under
+    /// [`STATUS_TABLE`](status::STATUS_TABLE) every `status_period`. Share it to
+    /// time stages the callbacks run, or to report it elsewhere.
+    pub metrics: Arc<Metrics>,
+    pub status_period: Duration,
+    pub watchdog: WatchdogOptions,
+    pub recovery: RecoveryPolicy,
+}
+
+impl Default for WorkerOptions {
+    fn default() -> Self {
+        Self {
+            addr: NTAddr::Local,
+            path_codec: Codec::default(),
+            photon_version: None,
+            schema_policy: SchemaPolicy::default(),
+            schema_timeout: Duration::from_secs(2),
+            metrics: Arc::default(),
+            status_period: Duration::from_millis(500),
+            watchdog: WatchdogOptions::default(),
+            recovery: RecoveryPolicy::default(),
+        }
+    }
+}
+
+/// The camera a `/photonvision/<camera>/rawData` topic belongs to
+pub fn photon_camera(topic: &str) -> Option<&str> {
+    topic
+        .strip_prefix("/photonvision/")?
+        .strip_suffix("/rawData")
+        .filter(|camera| !camera.contains('/'))
+}
+
+/// Checks the layout behind a photon result's type string against
+/// [`PhotonResult`]. Photon before 2025 announces results as plain raw bytes,
+/// which have nothing to check.
+#[track_caller]
+pub fn check_photon_schema(
+    registry: &SchemaRegistry,
+    type_string: &str,
+    policy: SchemaPolicy,
+) -> Result<PhotonLayout, PhotonWorkerError> {
+    let Some(name) = type_string.strip_prefix("photonstruct:") else {
+        return Ok(PhotonLayout::Compiled);
+    };
+
+    match (registry.validate::<PhotonResult>(name), policy) {
+        (Ok(()), _) => Ok(PhotonLayout::Compiled),
+        (Err(err @ SchemaError::Mismatch { .. }), SchemaPolicy::Warn) => {
+            tracing::warn!("Decoding photon results by their published layout\n{err}");
+            Ok(PhotonLayout::Published(name.to_string()))
+        }
+        (Err(err), SchemaPolicy::Warn) => {
+            tracing::warn!("Decoding photon results anyway, expect garbage\n{err}");
+            Ok(PhotonLayout::Compiled)
+        }
+        (Err(err), SchemaPolicy::Refuse) => Err(err.into()),
+    }
+}
+
+/// Adds a schema from `/.schema/` to `registry`. Schemas we can't parse are
+/// skipped, they only matter if something we decode uses them.
+fn add_schema(registry: &mut SchemaRegistry, topic: &str, bytes: &[u8]) {
+    let schema = String::from_utf8_lossy(bytes);
+    if let Err(err) = registry.add(topic, &schema) {
+        tracing::warn!("Skipping schema {topic}:\n{err}");
+    }
+}
+
+/// Reads what `sub` (`/.schema/`) gets within `timeout`, so the schemas
+/// published before we connected are in `registry` before the first result.
+/// Later ones are added as they arrive.
+async fn collect_schemas(
+    sub: &mut Subscriber,
+    registry: &mut SchemaRegistry,
+    timeout: Duration,
+) -> Result<(), PhotonWorkerError> {
+    let deadline = tokio::time::Instant::now() + timeout;
+    while let Ok(message) = timeout_at(deadline, sub.recv()).await {
+        let ReceivedMessage::Updated((announced, value)) = message? else {
+            continue;
+        };
+
+        if let Some(bytes) = value.as_slice() {
+            add_schema(registry, &announced.name, bytes);
+        }
+    }
+
+    Ok(())
+}
+
+/// Topics for the schema of `T`, and of every struct it nests, under
+/// `/.schema`
+pub fn schema_topics<T: WpiStruct>(nt: &Client) -> Vec<(Topic, &'static str)> {
+    [(T::NAME, T::SCHEMA)]
+        .iter()
+        .chain(T::DEPENDENCIES)
+        .map(|(name, schema)| (nt.topic(format!("/.schema/struct:{name}")), *schema))
+        .collect()
+}
+
+/// Topics for the `.proto` files of the path's messages, under `/.schema`
+pub fn proto_schema_topics(nt: &Client) -> Vec<(Topic, Vec<u8>)> {
+    photon_serde::protobuf::file_descriptors()
+        .into_iter()
+        .map(|(file, descriptor)| (nt.topic(format!("/.schema/proto:{file}")), descriptor))
+        .collect()
+}
+
+/// Raw bytes announced under a type string of our choosing. WPILib matches
+/// struct, protobuf and schema topics by their type string, and ignores them
+/// when they're announced as plain `raw`.
+pub struct TypedPublisher {
+    publisher: GenericPublisher,
+}
+
+impl TypedPublisher {
+    pub async fn new(
+        topic: &Topic,
+        type_string: impl Into<String>,
+        properties: Properties,
+    ) -> Result<Self, NewPublisherError> {
+        Ok(Self {
+            publisher: topic
+                .generic_publish(type_string.into(), properties)
+                .await?,
+        })
+    }
+
+    pub async fn set(&self, bytes: Vec<u8>) {
+        self.publisher.set(bytes).await;
+    }
+}
+
+/// Publishes schemas from [`schema_topics`] as `structschema`, or from
+/// [`proto_schema_topics`] as `proto:FileDescriptorProto`, so dashboards and
+/// robot code can decode what we send. The topics go away when the publishers
+/// drop.
+pub async fn publish_schemas(
+    type_string: &str,
+    topics: &[(Topic, impl AsRef<[u8]>)],
+) -> Result<Vec<TypedPublisher>, NewPublisherError> {
+    let mut publishers = vec![];
+
+    for (topic, schema) in topics {
+        let publisher = TypedPublisher::new(
+            topic,
+            type_string,
+            Properties {
+                persistent: Some(false),
+                retained: Some(true),
+                cached: Some(true),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        publisher.set(schema.as_ref().to_vec()).await;
+        publishers.push(publisher);
+    }
+
+    Ok(publishers)
+}
+
+/// Every topic the worker uses. They're all made before connecting, since
+/// [`Client::connect`] takes the client for as long as the connection lasts.
+struct WorkerTopics {
+    photon: Topic,
+    pose: Topic,
+    dest: Topic,
+    path: Topic,
+    path_valid: Topic,
+    path_expiry: Topic,
+    schemas: Topic,
+    pose_schemas: Vec<(Topic, &'static str)>,
+    path_schemas: Vec<(Topic, Vec<u8>)>,
+    field: FieldTopics,
+    status: StatusTopics,
+    watchdog: WatchdogTopics,
+}
+
+impl WorkerTopics {
+    fn new(nt: &Client) -> Self {
+        Self {
+            photon: nt.topic("/photonvision/"),
+            pose: nt.topic("/robot/pose"),
+            dest: nt.topic("/robot/dest"),
+            path: nt.topic("/pathforger/path"),
+            path_valid: nt.topic(PATH_VALID_TOPIC),
+            path_expiry: nt.topic(PATH_EXPIRY_TOPIC),
+            schemas: nt.topic("/.schema/"),
+            pose_schemas: schema_topics::<Pose2d>(nt),
+            path_schemas: proto_schema_topics(nt),
+            field: FieldTopics::new(nt),
+            status: StatusTopics::new(nt),
+            watchdog: WatchdogTopics::new(nt),
+        }
+    }
+}
+
+/// Connects to NT at `options.addr`, and reconnects whenever the connection
+/// drops, until a fatal error (see [`metrics::ErrorClass`]). Messages that
+/// fail to decode are skipped, as `options.recovery` describes.
+///
+/// `/robot/pose` and `/robot/dest` are decoded according to the type they're
+/// announced with, so robot code can send photon's layout, a `struct:Pose2d` or
+/// a `proto:Pose2d`. The path is published with `options.path_codec`.
+///
+/// Results are read from each of `cameras`, or from every camera if it's empty,
+/// and decoded with the layout of `options.photon_version`. A published layout
+/// is checked against ours before a camera's first result is decoded, and again
+/// whenever its type string changes.
+///
+/// `on_tick` runs after each update, filling in what the dashboard's field
+/// widget should show. It's published right after.
+#[track_caller]
+pub async fn worker<C0, C1, C2, C3>(
+    cameras: Vec<String>,
     on_robot_pose_update: C0,
     on_photon_update: C1,
     on_dest_update: C2,
+    on_tick: C3,
+    options: WorkerOptions,
 ) -> Result<!, PhotonWorkerError>
 where
-    C0: for<'f> Fn(&'f mut Publisher<RawData>, Pose2d) -> BoxFuture<'f, ()> + ThreadSafe,
-    C1: for<'f> Fn(&'f mut Publisher<RawData>, PhotonResult) -> BoxFuture<'f, ()> + ThreadSafe,
-    C2: for<'f> Fn(&'f mut Publisher<RawData>, Pose2d) -> BoxFuture<'f, ()> + ThreadSafe,
+    C0: for<'f> Fn(&'f mut PathPublisher, Pose2d) -> BoxFuture<'f, ()> + ThreadSafe,
+    C1: for<'f> Fn(&'f mut PathPublisher, &'f str, PhotonResult) -> BoxFuture<'f, ()> + ThreadSafe,
+    C2: for<'f> Fn(&'f mut PathPublisher, Pose2d) -> BoxFuture<'f, ()> + ThreadSafe,
+    C3: for<'f> Fn(&'f mut Field) -> BoxFuture<'f, ()> + ThreadSafe,
 {
-    let nt = Client::new(Default::default());
+    let location = Location::caller();
+    let metrics = options.metrics.clone();
+    let mut recovery = Recovery::new(options.recovery, metrics.clone());
+    let mut backoff = Backoff::new(options.recovery);
+
+    loop {
+        let nt = Client::new(NewClientOptions {
+            addr: options.addr,
+            ..Default::default()
+        });
+        let topics = WorkerTopics::new(&nt);
+        let connected_at = Instant::now();
+
+        let result = tokio::select! {
+            connected = nt.connect() => match connected {
+                Ok(()) => Err(PhotonWorkerError::Disconnected { location }),
+                Err(err) => Err(err.into()),
+            },
+            result = run(
+                topics,
+                &cameras,
+                &on_robot_pose_update,
+                &on_photon_update,
+                &on_dest_update,
+                &on_tick,
+                &options,
+                &mut recovery,
+            ) => result,
+        };
+        metrics.set_connected(false);
+
+        // everything run ends with is counted here, including what came
+        // straight out of a `?` rather than through recovery
+        let Err(err) = result;
+        recovery.disconnected(err)?;
+
+        // a connection that held for a while isn't part of the same outage
+        if connected_at.elapsed() > options.recovery.max_backoff {
+            backoff.reset();
+        }
+        let wait = backoff.wait();
+        tracing::info!("Reconnecting in {wait:?}");
+        sleep(wait).await;
+    }
+}
 
-    let photon = nt.topic(format!("/photonvision/{camera}/rawData"));
-    let pose = nt.topic("/robot/pose");
-    let dest = nt.topic("/robot/dest");
-    let path = nt.topic("/pathforger/path");
+/// One connection's worth of [`worker`]
+#[allow(clippy::too_many_arguments)]
+async fn run<C0, C1, C2, C3>(
+    topics: WorkerTopics,
+    cameras: &[String],
+    on_robot_pose_update: &C0,
+    on_photon_update: &C1,
+    on_dest_update: &C2,
+    on_tick: &C3,
+    options: &WorkerOptions,
+    recovery: &mut Recovery,
+) -> Result<!, PhotonWorkerError>
+where
+    C0: for<'f> Fn(&'f mut PathPublisher, Pose2d) -> BoxFuture<'f, ()> + ThreadSafe,
+    C1: for<'f> Fn(&'f mut PathPublisher, &'f str, PhotonResult) -> BoxFuture<'f, ()> + ThreadSafe,
+    C2: for<'f> Fn(&'f mut PathPublisher, Pose2d) -> BoxFuture<'f, ()> + ThreadSafe,
+    C3: for<'f> Fn(&'f mut Field) -> BoxFuture<'f, ()> + ThreadSafe,
+{
+    let periodic = || SubscriptionOptions {
+        periodic: Some(Duration::from_millis(20)),
+        ..Default::default()
+    };
 
-    let mut photon_sub = photon
+    let mut photon_sub = topics
+        .photon
         .subscribe(SubscriptionOptions {
-            periodic: Some(Duration::from_millis(20)),
-            ..Default::default()
+            prefix: Some(true),
+            ..periodic()
         })
         .await;
+    let mut pose_sub = topics.pose.subscribe(periodic()).await;
+    let mut dest_sub = topics.dest.subscribe(periodic()).await;
 
-    let mut pose_sub = pose
-        .subscribe(SubscriptionOptions {
-            periodic: Some(Duration::from_millis(20)),
-            ..Default::default()
+    let properties = |retained| Properties {
+        persistent: Some(false),
+        retained: Some(retained),
+        cached: Some(true),
+        ..Default::default()
+    };
+
+    let watchdog = Arc::new(Watchdog::new(options.watchdog));
+    let mut path_pub = {
+        let (topics, watchdog) = (&topics, &watchdog);
+        recovery
+            .retry("path", move || async move {
+                Ok(PathPublisher {
+                    publisher: Arc::new(
+                        TypedPublisher::new(
+                            &topics.path,
+                            PathPublisher::type_string(options.path_codec),
+                            properties(true),
+                        )
+                        .await?,
+                    ),
+                    codec: options.path_codec,
+                    // not retained, so it's gone along with our connection
+                    valid: Arc::new(topics.path_valid.publish(properties(false)).await?),
+                    expiry: Arc::new(topics.path_expiry.publish(properties(true)).await?),
+                    watchdog: watchdog.clone(),
+                    writing: Default::default(),
+                })
+            })
+            .await?
+    };
+
+    // whatever the server retained is from before this connection, and nothing
+    // should follow it
+    path_pub.invalidate().await;
+    let _watch = recovery
+        .retry("watchdog", || {
+            watchdog::watch(watchdog.clone(), &topics.watchdog, path_pub.clone())
         })
-        .await;
+        .await?;
+
+    let _schemas = recovery
+        .retry("schema", || {
+            publish_schemas("structschema", &topics.pose_schemas)
+        })
+        .await?;
+    // the path's .proto files, which robot code needs before it can read a
+    // proto path
+    let _path_schemas = match options.path_codec {
+        Codec::Proto => {
+            recovery
+                .retry("schema", || {
+                    publish_schemas("proto:FileDescriptorProto", &topics.path_schemas)
+                })
+                .await?
+        }
+        Codec::Photon | Codec::Struct => vec![],
+    };
+    let field_pub = recovery
+        .retry("field", || FieldPublisher::new(&topics.field))
+        .await?;
+    let mut field = Field::default();
+    let status_pub = recovery
+        .retry("status", || StatusPublisher::new(&topics.status))
+        .await?;
+    let mut status_interval = interval(options.status_period);
+    let metrics = &options.metrics;
 
-    let mut dest_sub = dest
+    let mut schema_sub = topics
+        .schemas
         .subscribe(SubscriptionOptions {
-            periodic: Some(Duration::from_millis(20)),
+            prefix: Some(true),
             ..Default::default()
         })
         .await;
+    let mut registry = SchemaRegistry::default();
+    collect_schemas(&mut schema_sub, &mut registry, options.schema_timeout).await?;
+    // per camera, since each coprocessor can run a different release
+    let mut checked_types = HashMap::<String, (String, PhotonLayout)>::new();
+    let mut photon_versions = HashMap::<String, PhotonVersion>::new();
+    metrics.set_connected(true);
 
-    let mut path_pub = path
-        .publish::<RawData>(Properties {
-            persistent: Some(false),
-            retained: Some(true),
-            cached: Some(true),
-            ..Default::default()
-        })
-        .await?;
+    loop {
+        // each branch is whether anything was updated, so the field needs
+        // another tick
+        let (source, updated) = tokio::select! {
+            message = photon_sub.recv() => ("photon", async {
+                let ReceivedMessage::Updated((announced, value)) = message? else {
+                    return Ok(false);
+                };
+                let Some(camera) = photon_camera(&announced.name) else {
+                    return Ok(false);
+                };
+                if !cameras.is_empty() && !cameras.iter().any(|name| name == camera) {
+                    return Ok(false);
+                }
 
-    let rt = runtime::Builder::new_multi_thread().enable_all().build()?;
+                let Some(bytes) = value.as_slice() else {
+                    return Ok(false);
+                };
 
-    loop {
-        let photon_res = photon_sub.recv().await?;
-        let pose_res = pose_sub.recv().await?;
-        let dest_res = dest_sub.recv().await?;
+                watchdog.busy();
 
-        match photon_res {
-            ReceivedMessage::Updated((_, value)) => {
-                if let Some(bytes) = value.as_slice() {
-                    let result = deserialize(bytes)?;
-                    rt.block_on(on_photon_update(&mut path_pub, result));
+                // everything done for one result, filled in with its seqid once
+                // it's decoded
+                let frame = debug_span!("frame", camera, seqid = tracing::field::Empty);
+
+                let (layout, version) = {
+                    // which layout and version the bytes are in, once they've
+                    // arrived
+                    let _identify =
+                        debug_span!(parent: &frame, "identify", len = bytes.len()).entered();
+                    let _timer = metrics.start(Stage::Identify);
+
+                    let layout = match checked_types.get(camera) {
+                        Some((checked, layout)) if *checked == announced.r#type => layout.clone(),
+                        _ => {
+                            let layout = check_photon_schema(
+                                &registry,
+                                &announced.r#type,
+                                options.schema_policy,
+                            )?;
+                            checked_types.insert(
+                                camera.to_string(),
+                                (announced.r#type.clone(), layout.clone()),
+                            );
+                            layout
+                        }
+                    };
+
+                    let version = options
+                        .photon_version
+                        .or_else(|| photon_versions.get(camera).copied())
+                        .or_else(|| {
+                            let version = PhotonVersion::detect(&announced.r#type, bytes)?;
+                            photon_versions.insert(camera.to_string(), version);
+                            Some(version)
+                        })
+                        .unwrap_or_default();
+                    (layout, version)
                 };
-            }
-            _ => {}
-        }
 
-        match pose_res {
-            ReceivedMessage::Updated((_, value)) => {
-                if let Some(bytes) = value.as_slice() {
-                    let pose = deserialize(bytes)?;
-                    rt.block_on(on_robot_pose_update(&mut path_pub, pose));
+                let result = {
+                    let _deserialize = debug_span!(parent: &frame, "deserialize", ?version).entered();
+                    let _timer = metrics.start(Stage::Deserialize);
+                    let received = time::duration_of(Instant::now());
+                    match &layout {
+                        PhotonLayout::Compiled => {
+                            version.decode(bytes, received).map_err(PhotonWorkerError::from)
+                        }
+                        PhotonLayout::Published(name) => {
+                            registry.decode_photon(name, bytes).map_err(PhotonWorkerError::from)
+                        }
+                    }
+                    .inspect_err(|_| {
+                        metrics.deserialize_error();
+                        // the coprocessor may have been updated to another
+                        // release, so the next result is detected again
+                        photon_versions.remove(camera);
+                    })?
                 };
-            }
-            _ => {}
-        }
 
-        match dest_res {
-            ReceivedMessage::Updated((_, value)) => {
-                if let Some(bytes) = value.as_slice() {
-                    let dest = deserialize(bytes)?;
-                    rt.block_on(on_dest_update(&mut path_pub, dest));
+                metrics.frame(camera, result.metadata.seqid);
+                watchdog.input();
+
+                frame.record("seqid", result.metadata.seqid);
+                on_photon_update(&mut path_pub, camera, result)
+                    .instrument(frame)
+                    .await;
+                Ok(true)
+            }.await),
+            message = pose_sub.recv() => ("pose", async {
+                let ReceivedMessage::Updated((announced, value)) = message? else {
+                    return Ok(false);
                 };
+                let Some(bytes) = value.as_slice() else {
+                    return Ok(false);
+                };
+
+                watchdog.busy();
+                let span = debug_span!("pose");
+                let pose = {
+                    let _decode = span.enter();
+                    Codec::from_type(&announced.r#type).decode(bytes)?
+                };
+                watchdog.input();
+                on_robot_pose_update(&mut path_pub, pose).instrument(span).await;
+                Ok(true)
+            }.await),
+            message = dest_sub.recv() => ("dest", async {
+                let ReceivedMessage::Updated((announced, value)) = message? else {
+                    return Ok(false);
+                };
+                let Some(bytes) = value.as_slice() else {
+                    return Ok(false);
+                };
+
+                watchdog.busy();
+                let span = debug_span!("dest");
+                let dest = {
+                    let _decode = span.enter();
+                    Codec::from_type(&announced.r#type).decode(bytes)?
+                };
+                on_dest_update(&mut path_pub, dest).instrument(span).await;
+                Ok(true)
+            }.await),
+            message = schema_sub.recv() => ("schema", async {
+                let ReceivedMessage::Updated((announced, value)) = message? else {
+                    return Ok(false);
+                };
+
+                if let Some(bytes) = value.as_slice() {
+                    add_schema(&mut registry, &announced.name, bytes);
+                    // a coprocessor that restarted on another release publishes
+                    // its layout again, so everything is checked against it again
+                    checked_types.clear();
+                }
+                Ok(false)
+            }.await),
+            _ = status_interval.tick() => {
+                status_pub.publish(&metrics.status()).await;
+                ("status", Ok(false))
             }
-            _ => {}
+        };
+
+        // a skipped message still ends the update the watchdog is timing
+        if recovery.handle(source, updated)? != Some(true) {
+            watchdog.idle();
+            continue;
         }
+
+        async {
+            on_tick(&mut field).await;
+            field_pub.publish(&field).await;
+        }
+        .instrument(debug_span!("tick"))
+        .await;
+        metrics.tick();
+        watchdog.idle();
     }
 }
@@ -0,0 +1,244 @@
+//! What the worker does about an error, by its [`ErrorClass`]. One bad frame
+//! shouldn't cost the robot its path, so only fatal errors stop the worker:
+//! - Frame errors skip the message they came with, unless a source's messages
+//!   keep failing, which is a layout mismatch rather than a bad frame.
+//! - Transient errors skip the message, and the worker reconnects when they
+//!   take the connection with them. Publishers are retried before giving up on
+//!   the connection.
+//!
+//! Each error is counted in [`Metrics`] by class exactly once, by whichever of
+//! these decides what happens to it. Skipped ones are logged at most once per
+//! [`RecoveryPolicy::log_interval`] per kind, so a camera sending garbage
+//! doesn't drown out everything else.
+
+use std::{
+    backtrace::Backtrace,
+    collections::HashMap,
+    future::Future,
+    panic::Location,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use super::error::PhotonWorkerError;
+use crate::util::metrics::{ErrorClass, Metrics};
+use nt_client::publish::NewPublisherError;
+use tokio::time::sleep;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RecoveryPolicy {
+    /// Shortest time between two logs of the same kind of error
+    pub log_interval: Duration,
+    /// Frame errors in a row from one source before they stop the worker
+    pub frame_error_limit: u32,
+    /// Tries at making a publisher before reconnecting
+    pub publisher_attempts: u32,
+    /// Wait after the first failed try or dropped connection, doubled after
+    /// each one up to `max_backoff`
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RecoveryPolicy {
+    fn default() -> Self {
+        Self {
+            log_interval: Duration::from_secs(5),
+            frame_error_limit: 50,
+            publisher_attempts: 5,
+            backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Exponential backoff between [`RecoveryPolicy::backoff`] and
+/// [`RecoveryPolicy::max_backoff`]
+#[derive(Clone, Copy, Debug)]
+pub struct Backoff {
+    next: Duration,
+    policy: RecoveryPolicy,
+}
+
+impl Backoff {
+    pub fn new(policy: RecoveryPolicy) -> Self {
+        Self {
+            next: policy.backoff,
+            policy,
+        }
+    }
+
+    /// How long to wait this time
+    pub fn wait(&mut self) -> Duration {
+        let wait = self.next;
+        self.next = (self.next * 2).min(self.policy.max_backoff);
+        wait
+    }
+
+    pub fn reset(&mut self) {
+        self.next = self.policy.backoff;
+    }
+}
+
+/// Lets a message through at most once per interval per key
+#[derive(Clone, Debug)]
+pub struct RateLimit {
+    interval: Duration,
+    /// When each key was last let through, and how many were held back since
+    logged: HashMap<&'static str, (Instant, u64)>,
+}
+
+impl RateLimit {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            logged: HashMap::new(),
+        }
+    }
+
+    /// `Some` with the number held back since the last one if `key` should be
+    /// logged at `now`
+    pub fn allow(&mut self, key: &'static str, now: Instant) -> Option<u64> {
+        match self.logged.get_mut(key) {
+            Some((last, suppressed)) if now.saturating_duration_since(*last) < self.interval => {
+                *suppressed += 1;
+                None
+            }
+            Some((last, suppressed)) => {
+                *last = now;
+                Some(std::mem::take(suppressed))
+            }
+            None => {
+                self.logged.insert(key, (now, 0));
+                Some(0)
+            }
+        }
+    }
+}
+
+/// Applies a [`RecoveryPolicy`] for the lifetime of a worker, across
+/// reconnects
+#[derive(Debug)]
+pub struct Recovery {
+    pub policy: RecoveryPolicy,
+    metrics: Arc<Metrics>,
+    limit: RateLimit,
+    /// Frame errors in a row, per source
+    streaks: HashMap<&'static str, u32>,
+}
+
+impl Recovery {
+    pub fn new(policy: RecoveryPolicy, metrics: Arc<Metrics>) -> Self {
+        Self {
+            policy,
+            metrics,
+            limit: RateLimit::new(policy.log_interval),
+            streaks: HashMap::new(),
+        }
+    }
+
+    /// Counts a skipped `err`, and logs it unless its kind was logged recently
+    fn note(&mut self, err: &PhotonWorkerError) {
+        let class = err.class();
+        self.metrics.error(class);
+
+        match self.limit.allow(err.kind(), Instant::now()) {
+            Some(0) => tracing::warn!("Skipping a {} error:\n{err}", class.name()),
+            Some(suppressed) => tracing::warn!(
+                "Skipping a {} error, and {suppressed} more since the last one logged:\n{err}",
+                class.name()
+            ),
+            None => {}
+        }
+    }
+
+    /// The outcome of one message from `source`: `Some` if it went through,
+    /// `None` if it was skipped, and the error if the connection should end.
+    /// Only skipped errors are counted here, the rest by [`Self::disconnected`].
+    pub fn handle<T>(
+        &mut self,
+        source: &'static str,
+        result: Result<T, PhotonWorkerError>,
+    ) -> Result<Option<T>, PhotonWorkerError> {
+        let err = match result {
+            Ok(value) => {
+                self.streaks.remove(source);
+                return Ok(Some(value));
+            }
+            Err(err) => err,
+        };
+
+        match err.class() {
+            ErrorClass::Transient => {}
+            ErrorClass::Frame => {
+                let streak = self.streaks.entry(source).or_default();
+                *streak += 1;
+
+                if *streak >= self.policy.frame_error_limit {
+                    tracing::error!(
+                        "The last {streak} messages from {source} failed to decode, giving up"
+                    );
+                    return Err(err);
+                }
+            }
+            ErrorClass::Fatal => return Err(err),
+        }
+
+        self.note(&err);
+        Ok(None)
+    }
+
+    /// Counts the error a connection ended with, however it got out. `Ok` if
+    /// it's worth reconnecting, or the error back if the worker should stop.
+    /// Stopping errors are left for whoever gets them to log.
+    pub fn disconnected(&mut self, err: PhotonWorkerError) -> Result<(), PhotonWorkerError> {
+        if err.class() != ErrorClass::Transient {
+            self.metrics.error(err.class());
+            return Err(err);
+        }
+
+        self.note(&err);
+        Ok(())
+    }
+
+    /// Makes a publisher with `attempt`, backing off between failed tries.
+    /// Fails once [`RecoveryPolicy::publisher_attempts`] are used up, with the
+    /// last try's error left for [`Self::disconnected`] to count.
+    #[track_caller]
+    pub fn retry<'a, T, F, Fut>(
+        &'a mut self,
+        what: &'a str,
+        mut attempt: F,
+    ) -> impl Future<Output = Result<T, PhotonWorkerError>> + 'a
+    where
+        F: FnMut() -> Fut + 'a,
+        Fut: Future<Output = Result<T, NewPublisherError>> + 'a,
+    {
+        let location = Location::caller();
+
+        async move {
+            let mut backoff = Backoff::new(self.policy);
+            let mut tries = 0;
+
+            loop {
+                tries += 1;
+                let err = match attempt().await {
+                    Ok(publisher) => return Ok(publisher),
+                    Err(source) => PhotonWorkerError::NTPublishError {
+                        source,
+                        location,
+                        backtrace: Backtrace::capture(),
+                    },
+                };
+
+                if tries >= self.policy.publisher_attempts {
+                    return Err(err);
+                }
+                self.note(&err);
+
+                let wait = backoff.wait();
+                tracing::debug!("Retrying the {what} publisher in {wait:?}");
+                sleep(wait).await;
+            }
+        }
+    }
+}
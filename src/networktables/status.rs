@@ -0,0 +1,137 @@
+use crate::prelude::*;
+
+use metrics::{ErrorClass, Stage, Status};
+use nt_client::{
+    data::Properties,
+    publish::{NewPublisherError, Publisher},
+    topic::Topic,
+    Client,
+};
+
+/// Where pathforger reports its own health, for the driver station and pit
+pub const STATUS_TABLE: &str = "/pathforger/status";
+
+/// The topics under [`STATUS_TABLE`], made before connecting
+pub struct StatusTopics {
+    loop_rate: Topic,
+    latency: Vec<[Topic; 3]>,
+    frames: Topic,
+    frames_dropped: Topic,
+    deserialize_errors: Topic,
+    errors: [Topic; 3],
+    tracks: Topic,
+    last_plan_age: Topic,
+    connected: Topic,
+    uptime: Topic,
+}
+
+impl StatusTopics {
+    pub fn new(nt: &Client) -> Self {
+        let topic = |name: &str| nt.topic(format!("{STATUS_TABLE}/{name}"));
+
+        Self {
+            loop_rate: topic("loop_rate"),
+            latency: Stage::ALL
+                .iter()
+                .map(|stage| ["p50", "p90", "p99"].map(|p| topic(&format!("latency/{stage}/{p}"))))
+                .collect(),
+            frames: topic("frames"),
+            frames_dropped: topic("frames_dropped"),
+            deserialize_errors: topic("deserialize_errors"),
+            errors: ErrorClass::ALL.map(|class| topic(&format!("errors/{}", class.name()))),
+            tracks: topic("tracks"),
+            last_plan_age: topic("last_plan_age"),
+            connected: topic("connected"),
+            uptime: topic("uptime"),
+        }
+    }
+}
+
+/// Publishes a [`Status`] under [`STATUS_TABLE`]. Latencies are in
+/// milliseconds, ages and uptime in seconds.
+pub struct StatusPublisher {
+    loop_rate: Publisher<f64>,
+    /// p50, p90 and p99 of each of [`Stage::ALL`]
+    latency: Vec<[Publisher<f64>; 3]>,
+    frames: Publisher<i64>,
+    frames_dropped: Publisher<i64>,
+    deserialize_errors: Publisher<i64>,
+    /// Worker errors of each of [`ErrorClass::ALL`]
+    errors: [Publisher<i64>; 3],
+    tracks: Publisher<i64>,
+    /// -1 before anything has been planned
+    last_plan_age: Publisher<f64>,
+    connected: Publisher<bool>,
+    uptime: Publisher<f64>,
+}
+
+impl StatusPublisher {
+    pub async fn new(topics: &StatusTopics) -> Result<Self, NewPublisherError> {
+        let properties = || Properties {
+            persistent: Some(false),
+            retained: Some(false),
+            cached: Some(true),
+            ..Default::default()
+        };
+
+        let mut latency = vec![];
+        for [p50, p90, p99] in &topics.latency {
+            latency.push([
+                p50.publish(properties()).await?,
+                p90.publish(properties()).await?,
+                p99.publish(properties()).await?,
+            ]);
+        }
+
+        let [transient, frame, fatal] = &topics.errors;
+        let errors = [
+            transient.publish(properties()).await?,
+            frame.publish(properties()).await?,
+            fatal.publish(properties()).await?,
+        ];
+
+        Ok(Self {
+            loop_rate: topics.loop_rate.publish(properties()).await?,
+            latency,
+            frames: topics.frames.publish(properties()).await?,
+            frames_dropped: topics.frames_dropped.publish(properties()).await?,
+            deserialize_errors: topics.deserialize_errors.publish(properties()).await?,
+            errors,
+            tracks: topics.tracks.publish(properties()).await?,
+            last_plan_age: topics.last_plan_age.publish(properties()).await?,
+            connected: topics.connected.publish(properties()).await?,
+            uptime: topics.uptime.publish(properties()).await?,
+        })
+    }
+
+    pub async fn publish(&self, status: &Status) {
+        let millis = |latency: std::time::Duration| latency.as_secs_f64() * 1000.0;
+
+        self.loop_rate.set(status.loop_rate).await;
+
+        for (stage, publishers) in Stage::ALL.iter().zip(&self.latency) {
+            let latency = status.latency.get(stage).copied().unwrap_or_default();
+            let [p50, p90, p99] = publishers;
+
+            p50.set(millis(latency.p50)).await;
+            p90.set(millis(latency.p90)).await;
+            p99.set(millis(latency.p99)).await;
+        }
+
+        self.frames.set(status.frames as i64).await;
+        self.frames_dropped.set(status.frames_dropped as i64).await;
+        self.deserialize_errors
+            .set(status.deserialize_errors as i64)
+            .await;
+        for (class, publisher) in ErrorClass::ALL.iter().zip(&self.errors) {
+            let count = status.errors.get(class).copied().unwrap_or_default();
+            publisher.set(count as i64).await;
+        }
+        self.tracks.set(status.tracks as i64).await;
+        self.last_plan_age
+            .set(status.last_plan_age.map_or(-1.0, |age| age.as_secs_f64()))
+            .await;
+        self.connected.set(status.connected).await;
+        self.uptime.set(status.uptime.as_secs_f64()).await;
+    }
+}
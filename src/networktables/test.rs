@@ -0,0 +1,270 @@
+use super::{
+    check_photon_schema,
+    codec::{Codec, PathPublisher},
+    error::PhotonWorkerError,
+    field::Field,
+    recovery::{Backoff, RateLimit, Recovery, RecoveryPolicy},
+    watchdog::{Stale, Watchdog, WatchdogOptions},
+    PhotonLayout, SchemaPolicy,
+};
+use crate::prelude::*;
+use crate::util::fixtures::pose;
+use std::{
+    panic::Location,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use game::enemy::{DataPoint, Enemy};
+use metrics::{ErrorClass, Metrics};
+use tokio::sync::broadcast::error::RecvError;
+
+#[test]
+fn field2d_doubles() {
+    let doubles = Field::to_doubles(&[pose(1.0, 2.0, 90.0), pose(3.0, 4.0, -45.0)]);
+
+    assert_eq!(doubles.len(), 6);
+    assert_eq!(&doubles[..2], &[1.0, 2.0]);
+    assert!((doubles[2] - 90.0).abs() < 1e-9);
+    assert!((doubles[5] + 45.0).abs() < 1e-9);
+    assert!(Field::to_doubles(&[]).is_empty());
+}
+
+#[test]
+fn field_predicts_enemies() {
+    let start = Instant::now();
+    // driving +x at 2m/s
+    let enemy = Enemy {
+        id: 0,
+        history: (0..3)
+            .map(|i| DataPoint {
+                time: start + Duration::from_millis(100 * i),
+                pose: pose(1.0 + 0.2 * i as f64, 1.0, 0.0),
+                size: Default::default(),
+                confidence: 1.0,
+            })
+            .collect(),
+    };
+
+    let field = Field::new(
+        pose(0.0, 0.0, 0.0),
+        &[enemy],
+        Duration::from_millis(500),
+        vec![],
+    );
+
+    assert_eq!(field.enemies.len(), 1);
+    assert_eq!(field.robot, Some(pose(0.0, 0.0, 0.0)));
+
+    let predicted = field.predicted[0].translate;
+    assert!((predicted.x.get::<meter>() - 2.4).abs() < 1e-6);
+    assert!((predicted.y.get::<meter>() - 1.0).abs() < 1e-6);
+}
+
+#[test]
+fn codec_from_type() {
+    let pose = pose(1.0, 2.0, 30.0);
+
+    for (type_string, bytes) in [
+        ("raw", pose.encode()),
+        ("struct:Pose2d", pose.encode()),
+        ("proto:wpi.proto.ProtobufPose2d", pose.to_proto()),
+    ] {
+        let codec = Codec::from_type(type_string);
+        let decoded = codec.decode::<Pose2d>(&bytes);
+
+        assert!(decoded.is_ok(), "{type_string}: {:?}", decoded.err());
+        assert_eq!(decoded.unwrap(), pose);
+        assert_eq!(codec.encode(&pose), bytes);
+    }
+
+    assert_eq!(Codec::from_type("proto:Pose2d"), Codec::Proto);
+    assert!(Codec::Struct.decode::<Pose2d>(&pose.to_proto()).is_err());
+}
+
+#[test]
+fn path_type_string() {
+    assert_eq!(
+        PathPublisher::type_string(Codec::Proto),
+        "proto:wpi.proto.ProtobufTrajectory"
+    );
+    for codec in [Codec::Photon, Codec::Struct] {
+        assert_eq!(PathPublisher::type_string(codec), "struct:Pose2d[]");
+    }
+}
+
+#[test]
+fn schema_policy() {
+    let mut registry = SchemaRegistry::default();
+    registry
+        .add(
+            "/.schema/photonstruct:PhotonPipelineResult:abc",
+            "int8 nope",
+        )
+        .unwrap();
+
+    assert_eq!(
+        check_photon_schema(&registry, "rawBytes", SchemaPolicy::Refuse).unwrap(),
+        PhotonLayout::Compiled
+    );
+    assert_eq!(
+        check_photon_schema(
+            &registry,
+            "photonstruct:PhotonPipelineResult:abc",
+            SchemaPolicy::Warn
+        )
+        .unwrap(),
+        PhotonLayout::Published("PhotonPipelineResult:abc".into())
+    );
+    assert!(matches!(
+        check_photon_schema(
+            &registry,
+            "photonstruct:PhotonPipelineResult:abc",
+            SchemaPolicy::Refuse
+        ),
+        Err(PhotonWorkerError::SchemaError { .. })
+    ));
+}
+
+#[test]
+fn watchdog_staleness() {
+    let options = WatchdogOptions::default();
+    let watchdog = Watchdog::new(options);
+    let now = Instant::now();
+
+    // the input timeout runs from startup until something arrives
+    assert_eq!(watchdog.check(now), None);
+    assert_eq!(
+        watchdog.check(now + options.input_timeout * 2),
+        Some(Stale::NoInput)
+    );
+
+    watchdog.input();
+    watchdog.busy();
+    let later = Instant::now() + options.deadline * 2;
+    assert_eq!(watchdog.check(later), Some(Stale::Deadline));
+
+    // a finished update only leaves the input timeout
+    watchdog.idle();
+    assert_eq!(watchdog.check(Instant::now()), None);
+
+    // a second at 100ms a beat
+    assert_eq!(options.lifetime_beats(), 10);
+    assert_eq!(watchdog.expiry(), watchdog.heartbeat() + 10);
+}
+
+fn truncated() -> PhotonWorkerError {
+    DeserializeError::Truncated {
+        needed: 4,
+        location: Location::caller(),
+    }
+    .into()
+}
+
+#[test]
+fn error_classes() {
+    let lagged: PhotonWorkerError = RecvError::Lagged(3).into();
+    let closed: PhotonWorkerError = RecvError::Closed.into();
+    let disconnected = PhotonWorkerError::Disconnected {
+        location: Location::caller(),
+    };
+
+    assert_eq!(lagged.class(), ErrorClass::Transient);
+    assert_eq!(disconnected.class(), ErrorClass::Transient);
+    assert_eq!(truncated().class(), ErrorClass::Frame);
+    assert_eq!(closed.class(), ErrorClass::Fatal);
+    assert_eq!(closed.kind(), lagged.kind());
+}
+
+#[test]
+fn rate_limit() {
+    let mut limit = RateLimit::new(Duration::from_secs(5));
+    let now = Instant::now();
+
+    assert_eq!(limit.allow("deserialize", now), Some(0));
+    assert_eq!(
+        limit.allow("deserialize", now + Duration::from_secs(1)),
+        None
+    );
+    assert_eq!(
+        limit.allow("deserialize", now + Duration::from_secs(2)),
+        None
+    );
+    // kinds are limited apart
+    assert_eq!(limit.allow("struct", now), Some(0));
+    assert_eq!(
+        limit.allow("deserialize", now + Duration::from_secs(5)),
+        Some(2)
+    );
+
+    let mut backoff = Backoff::new(RecoveryPolicy::default());
+    let waits = [(); 7].map(|_| backoff.wait().as_millis());
+    assert_eq!(waits, [250, 500, 1000, 2000, 4000, 5000, 5000]);
+    backoff.reset();
+    assert_eq!(backoff.wait().as_millis(), 250);
+}
+
+#[test]
+fn recovery_escalates_fatal_and_repeated() {
+    let metrics = Arc::new(Metrics::default());
+    let policy = RecoveryPolicy {
+        frame_error_limit: 3,
+        ..Default::default()
+    };
+    let mut recovery = Recovery::new(policy, metrics.clone());
+
+    assert!(matches!(
+        recovery.handle("photon", Ok(true)),
+        Ok(Some(true))
+    ));
+
+    // a good message between bad ones starts the count over
+    for _ in 0..2 {
+        assert!(matches!(
+            recovery.handle("photon", Err::<(), _>(truncated())),
+            Ok(None)
+        ));
+    }
+    assert!(matches!(recovery.handle("photon", Ok(())), Ok(Some(()))));
+    for _ in 0..2 {
+        assert!(matches!(
+            recovery.handle("photon", Err::<(), _>(truncated())),
+            Ok(None)
+        ));
+    }
+    // and other sources keep their own
+    assert!(matches!(
+        recovery.handle("pose", Err::<(), _>(truncated())),
+        Ok(None)
+    ));
+    let repeated = recovery
+        .handle("photon", Err::<(), _>(truncated()))
+        .unwrap_err();
+
+    let lagged: PhotonWorkerError = RecvError::Lagged(3).into();
+    assert!(matches!(
+        recovery.handle("pose", Err::<(), _>(lagged)),
+        Ok(None)
+    ));
+    let closed: PhotonWorkerError = RecvError::Closed.into();
+    let closed = recovery.handle("pose", Err::<(), _>(closed)).unwrap_err();
+
+    // what ends the connection isn't counted until it has
+    let errors = metrics.status().errors;
+    assert_eq!(errors[&ErrorClass::Frame], 5);
+    assert_eq!(errors[&ErrorClass::Transient], 1);
+    assert_eq!(errors.get(&ErrorClass::Fatal), None);
+
+    assert!(recovery.disconnected(repeated).is_err());
+    assert!(recovery.disconnected(closed).is_err());
+    // anything else that ends a connection is reconnected after, and counted once
+    let disconnected = PhotonWorkerError::Disconnected {
+        location: Location::caller(),
+    };
+    assert!(recovery.disconnected(disconnected).is_ok());
+
+    let errors = metrics.status().errors;
+    assert_eq!(errors[&ErrorClass::Frame], 6);
+    assert_eq!(errors[&ErrorClass::Transient], 2);
+    assert_eq!(errors[&ErrorClass::Fatal], 1);
+}
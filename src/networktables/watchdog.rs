@@ -0,0 +1,245 @@
+//! Lets the robot tell a path worth following from one pathforger has stopped
+//! looking after. The path is retained, so without this a hung pathforger
+//! leaves the robot following whatever it published last.
+//!
+//! The robot should treat the path as stale when any of these hold:
+//! - [`PATH_VALID_TOPIC`] is false. The watchdog sets it, and publishes an
+//!   empty path, when the worker misses its deadline or its inputs stop.
+//! - [`PATH_VALID_TOPIC`] is missing. Unlike the path it isn't retained, so the
+//!   server drops it along with pathforger's connection.
+//! - [`HEARTBEAT_TOPIC`] has passed [`PATH_EXPIRY_TOPIC`], meaning the path
+//!   hasn't been replanned in a while, usually because planning keeps failing.
+//! - [`HEARTBEAT_TOPIC`] hasn't changed for a few periods. A dropped connection
+//!   or a hung process can't be announced over NT, so this is how they show.
+//!
+//! Each new connection starts with an invalid path, since whatever the server
+//! retained is from before the last one dropped. Paths that finish while the
+//! watchdog finds the worker stale aren't published, so the first valid one
+//! after an invalidation is planned once the worker has caught up.
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::codec::PathPublisher;
+use nt_client::{
+    data::Properties,
+    publish::{NewPublisherError, Publisher},
+    topic::Topic,
+    Client,
+};
+use tokio::{task::JoinHandle, time::interval};
+
+/// Counts up every [`WatchdogOptions::period`] while the worker is healthy
+pub const HEARTBEAT_TOPIC: &str = "/pathforger/heartbeat";
+
+/// The heartbeat after which the current path is stale
+pub const PATH_EXPIRY_TOPIC: &str = "/pathforger/path_expiry";
+
+/// Whether the current path should be followed at all
+pub const PATH_VALID_TOPIC: &str = "/pathforger/path_valid";
+
+/// Why the path was invalidated, or empty while it's healthy
+pub const STALE_REASON_TOPIC: &str = "/pathforger/stale_reason";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchdogOptions {
+    /// Time between checks, and between heartbeats
+    pub period: Duration,
+    /// Longest one update may take, from receiving a message to the end of
+    /// the tick after it
+    pub deadline: Duration,
+    /// Longest the robot's pose or a photon result may take to arrive
+    pub input_timeout: Duration,
+    /// How long a path is good for if nothing replaces it
+    pub path_lifetime: Duration,
+}
+
+impl Default for WatchdogOptions {
+    fn default() -> Self {
+        Self {
+            period: Duration::from_millis(100),
+            deadline: Duration::from_millis(250),
+            input_timeout: Duration::from_millis(500),
+            path_lifetime: Duration::from_secs(1),
+        }
+    }
+}
+
+impl WatchdogOptions {
+    /// [`Self::path_lifetime`] in heartbeats, at least one
+    pub fn lifetime_beats(&self) -> i64 {
+        (self.path_lifetime.as_secs_f64() / self.period.as_secs_f64())
+            .ceil()
+            .max(1.0) as i64
+    }
+}
+
+/// Why the watchdog invalidated the path
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stale {
+    /// An update has been running for longer than the deadline
+    Deadline,
+    /// Neither the robot's pose nor any photon result has arrived in a while
+    NoInput,
+}
+
+impl Stale {
+    pub fn reason(self) -> &'static str {
+        match self {
+            Stale::Deadline => "deadline",
+            Stale::NoInput => "no input",
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    busy_since: Option<Instant>,
+    last_input: Option<Instant>,
+    heartbeat: i64,
+}
+
+/// What the worker has been up to, fed as it goes and checked from a task of
+/// its own, so a worker stuck in a callback still gets noticed
+#[derive(Debug)]
+pub struct Watchdog {
+    pub options: WatchdogOptions,
+    started: Instant,
+    state: Mutex<State>,
+}
+
+impl Watchdog {
+    pub fn new(options: WatchdogOptions) -> Self {
+        Self {
+            options,
+            started: Instant::now(),
+            state: Mutex::default(),
+        }
+    }
+
+    /// An update started
+    pub fn busy(&self) {
+        self.state.lock().unwrap().busy_since = Some(Instant::now());
+    }
+
+    /// The update finished, tick and all
+    pub fn idle(&self) {
+        self.state.lock().unwrap().busy_since = None;
+    }
+
+    /// The robot's pose or a photon result arrived
+    pub fn input(&self) {
+        self.state.lock().unwrap().last_input = Some(Instant::now());
+    }
+
+    pub fn heartbeat(&self) -> i64 {
+        self.state.lock().unwrap().heartbeat
+    }
+
+    /// The heartbeat the next path expires at
+    pub fn expiry(&self) -> i64 {
+        self.heartbeat() + self.options.lifetime_beats()
+    }
+
+    /// Whether the worker is keeping up at `now`. Before the first input, the
+    /// timeout counts from when the watchdog was made.
+    pub fn check(&self, now: Instant) -> Option<Stale> {
+        let state = self.state.lock().unwrap();
+        let since = |then: Instant| now.saturating_duration_since(then);
+
+        if state
+            .busy_since
+            .is_some_and(|busy| since(busy) > self.options.deadline)
+        {
+            return Some(Stale::Deadline);
+        }
+
+        if since(state.last_input.unwrap_or(self.started)) > self.options.input_timeout {
+            return Some(Stale::NoInput);
+        }
+
+        None
+    }
+
+    fn beat(&self) -> i64 {
+        let mut state = self.state.lock().unwrap();
+        state.heartbeat += 1;
+        state.heartbeat
+    }
+}
+
+/// The watchdog's own topics, made before connecting. The path's validity and
+/// expiry belong to the [`PathPublisher`].
+pub struct WatchdogTopics {
+    heartbeat: Topic,
+    stale_reason: Topic,
+}
+
+impl WatchdogTopics {
+    pub fn new(nt: &Client) -> Self {
+        Self {
+            heartbeat: nt.topic(HEARTBEAT_TOPIC),
+            stale_reason: nt.topic(STALE_REASON_TOPIC),
+        }
+    }
+}
+
+/// Checks `watchdog` every period, beating while the worker is healthy and
+/// invalidating `path` when it stops being
+pub async fn watch(
+    watchdog: Arc<Watchdog>,
+    topics: &WatchdogTopics,
+    path: PathPublisher,
+) -> Result<WatchHandle, NewPublisherError> {
+    let properties = || Properties {
+        persistent: Some(false),
+        retained: Some(false),
+        cached: Some(true),
+        ..Default::default()
+    };
+
+    let heartbeat: Publisher<i64> = topics.heartbeat.publish(properties()).await?;
+    let stale_reason: Publisher<String> = topics.stale_reason.publish(properties()).await?;
+
+    let task = tokio::spawn(async move {
+        let mut ticks = interval(watchdog.options.period);
+        let mut reported = None;
+
+        loop {
+            ticks.tick().await;
+            let stale = watchdog.check(Instant::now());
+
+            if stale != reported {
+                match stale {
+                    Some(stale) => {
+                        tracing::warn!("Invalidating the path: {}", stale.reason());
+                        path.invalidate().await;
+                    }
+                    None => tracing::info!("Caught up, the next path will be valid"),
+                }
+
+                stale_reason
+                    .set(stale.map_or("", Stale::reason).to_string())
+                    .await;
+                reported = stale;
+            }
+
+            if stale.is_none() {
+                heartbeat.set(watchdog.beat()).await;
+            }
+        }
+    });
+
+    Ok(WatchHandle(task))
+}
+
+/// Stops the watchdog's task when dropped, along with the worker it watches
+pub struct WatchHandle(JoinHandle<()>);
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
@@ -1,4 +1,5 @@
 use std::io::Read;
+use std::panic::Location;
 use std::u8;
 
 use crate::prelude::*;
@@ -7,7 +8,11 @@ use photon_serde::*;
 macro_rules! read_fixed {
     ($buf:expr, $len:expr) => {{
         let mut arr = [Default::default(); $len];
-        $buf.read(&mut arr).unwrap();
+        $buf.read_exact(&mut arr)
+            .map_err(|_| DeserializeError::Truncated {
+                needed: $len,
+                location: Location::caller(),
+            })?;
         arr
     }};
 }
@@ -72,17 +77,57 @@ impl Deserialize for Duration {
     }
 }
 
+// Containers pass the version on to what they contain
 impl<T> Deserialize for Option<T>
 where
     T: Deserialize,
 {
     fn deserialize(data: &mut Cursor<&[u8]>) -> Result<Self, DeserializeError> {
+        Self::deserialize_version(data, LATEST)
+    }
+
+    fn deserialize_version(
+        data: &mut Cursor<&[u8]>,
+        version: u32,
+    ) -> Result<Self, DeserializeError> {
         if !bool::deserialize(data)? {
             Ok(None)
         } else {
-            Ok(Some(T::deserialize(data)?))
+            Ok(Some(T::deserialize_version(data, version)?))
         }
     }
+
+    fn deserialize_in_place(
+        data: &mut Cursor<&[u8]>,
+        out: &mut Self,
+    ) -> Result<(), DeserializeError> {
+        match (bool::deserialize(data)?, out.as_mut()) {
+            (false, _) => *out = None,
+            (true, Some(inner)) => T::deserialize_in_place(data, inner)?,
+            (true, None) => *out = Some(T::deserialize(data)?),
+        }
+
+        Ok(())
+    }
+}
+
+/// A list of `T` with an `L` length prefix. Photon's are `u8`.
+pub fn deserialize_list<L, T>(
+    data: &mut Cursor<&[u8]>,
+    version: u32,
+) -> Result<Vec<T>, DeserializeError>
+where
+    L: Deserialize + Into<u64>,
+    T: Deserialize,
+{
+    let len = L::deserialize(data)?.into();
+    let mut vec = vec![];
+
+    for _ in 0..len {
+        vec.push(T::deserialize_version(data, version)?);
+    }
+
+    Ok(vec)
 }
 
 impl<T> Deserialize for Vec<T>
@@ -90,13 +135,58 @@ where
     T: Deserialize,
 {
     fn deserialize(data: &mut Cursor<&[u8]>) -> Result<Self, DeserializeError> {
-        let len = u8::deserialize(data)?;
-        let mut vec = vec![];
+        Self::deserialize_version(data, LATEST)
+    }
+
+    fn deserialize_version(
+        data: &mut Cursor<&[u8]>,
+        version: u32,
+    ) -> Result<Self, DeserializeError> {
+        deserialize_list::<u8, T>(data, version)
+    }
+
+    fn deserialize_in_place(
+        data: &mut Cursor<&[u8]>,
+        out: &mut Self,
+    ) -> Result<(), DeserializeError> {
+        let len = u8::deserialize(data)? as usize;
+        out.truncate(len);
+
+        for item in out.iter_mut() {
+            T::deserialize_in_place(data, item)?;
+        }
+
+        while out.len() < len {
+            out.push(T::deserialize(data)?);
+        }
+
+        Ok(())
+    }
+}
+
+impl<T, const N: usize> Deserialize for [T; N]
+where
+    T: Deserialize,
+{
+    fn deserialize(data: &mut Cursor<&[u8]>) -> Result<Self, DeserializeError> {
+        Self::deserialize_version(data, LATEST)
+    }
+
+    fn deserialize_version(
+        data: &mut Cursor<&[u8]>,
+        version: u32,
+    ) -> Result<Self, DeserializeError> {
+        std::array::try_from_fn(|_| T::deserialize_version(data, version))
+    }
 
-        for _ in 0..len {
-            vec.push(T::deserialize(data)?);
+    fn deserialize_in_place(
+        data: &mut Cursor<&[u8]>,
+        out: &mut Self,
+    ) -> Result<(), DeserializeError> {
+        for item in out {
+            T::deserialize_in_place(data, item)?;
         }
 
-        Ok(vec)
+        Ok(())
     }
 }
@@ -0,0 +1,155 @@
+//! Human-readable summaries of [`PhotonResult`]s, for `photon-dump` to print
+//! when a coprocessor's results need checking without a debugger.
+
+use std::{fmt, panic::Location, time::Duration};
+
+use crate::prelude::*;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum HexError {
+    #[error("At {location}: {found:?} isn't a hex digit")]
+    BadDigit {
+        found: char,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: Odd number of hex digits, the last byte is cut off")]
+    OddLength {
+        location: &'static Location<'static>,
+    },
+}
+
+/// Parses hex like `0a1b` or `0a 1b`, ignoring whitespace
+#[track_caller]
+pub fn parse_hex(hex: &str) -> Result<Vec<u8>, HexError> {
+    let location = Location::caller();
+    let digits = hex
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| {
+            c.to_digit(16)
+                .map(|digit| digit as u8)
+                .ok_or(HexError::BadDigit { found: c, location })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if digits.len() % 2 != 0 {
+        return Err(HexError::OddLength { location });
+    }
+
+    Ok(digits
+        .as_chunks::<2>()
+        .0
+        .iter()
+        .map(|[high, low]| high << 4 | low)
+        .collect())
+}
+
+/// Which results get printed. Empty lists let everything through.
+#[derive(Clone, Debug, Default)]
+pub struct DumpFilter {
+    pub cameras: Vec<String>,
+    pub fiducials: Vec<u32>,
+}
+
+impl DumpFilter {
+    pub fn camera(&self, camera: &str) -> bool {
+        self.cameras.is_empty() || self.cameras.iter().any(|name| name == camera)
+    }
+
+    /// `result` with only the targets of the fiducials asked for, or `None` if
+    /// there aren't any. The multi-tag result is kept as is.
+    pub fn apply(&self, mut result: PhotonResult) -> Option<PhotonResult> {
+        if self.fiducials.is_empty() {
+            return Some(result);
+        }
+
+        result.targets.retain(|target| {
+            target
+                .fiducial_id
+                .0
+                .is_some_and(|id| self.fiducials.contains(&id))
+        });
+
+        (!result.targets.is_empty()).then_some(result)
+    }
+}
+
+/// One result, printed as a header line and a line per target
+pub struct Summary<'a> {
+    pub camera: Option<&'a str>,
+    pub version: PhotonVersion,
+    pub result: &'a PhotonResult,
+}
+
+impl Summary<'_> {
+    /// From capture to publish, which is all older layouts send
+    pub fn latency(&self) -> Duration {
+        let metadata = &self.result.metadata;
+        metadata.publish_time.saturating_sub(metadata.capture_time)
+    }
+}
+
+impl fmt::Display for Summary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let result = self.result;
+
+        if let Some(camera) = self.camera {
+            write!(f, "[{camera}] ")?;
+        }
+
+        // older layouts don't number their results
+        if self.version == PhotonVersion::V2025 {
+            write!(f, "seq {}  ", result.metadata.seqid)?;
+        }
+
+        let ids = result
+            .targets
+            .iter()
+            .filter_map(|target| target.fiducial_id.0)
+            .collect_vec();
+
+        write!(
+            f,
+            "latency {:.1}ms  targets {}  ids {ids:?}  ",
+            self.latency().as_secs_f64() * 1000.0,
+            result.targets.len(),
+        )?;
+
+        match &result.pnp {
+            Some(multi) => writeln!(
+                f,
+                "pnp error {:.4} from {} tags",
+                multi.pnp.error, multi.num_fiducials
+            )?,
+            None => writeln!(f, "no pnp")?,
+        }
+
+        for target in &result.targets {
+            match target.fiducial_id.0 {
+                Some(id) => write!(f, "  tag {id:<4}")?,
+                None => write!(f, "  no tag  ")?,
+            }
+
+            write!(
+                f,
+                "yaw {:>7.2}  pitch {:>7.2}  area {:>6.2}  ambiguity {:.3}",
+                target.yaw, target.pitch, target.area, target.ambiguity
+            )?;
+
+            if target.detected.id != NO_CLASS {
+                write!(
+                    f,
+                    "  class {} ({:.0}%)",
+                    target.detected.id,
+                    target.detected.confidence * 100.0
+                )?;
+            }
+
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,239 +1,279 @@
 mod builtin;
+pub mod dump;
+pub mod protobuf;
+pub mod schema;
+#[cfg(test)]
+pub mod serialize;
+pub mod version;
+pub mod view;
+pub mod wpistruct;
 
 #[cfg(test)]
 mod test;
 
 use crate::prelude::*;
-use std::{
-    hash::{Hash, Hasher},
-    io::Cursor,
-    time::Duration,
-};
+use std::{io::Cursor, panic::Location, time::Duration};
 use thiserror::Error;
 
+pub use builtin::deserialize_list;
+pub use photon_serde_derive::{FloatHash, PhotonSerde};
+
+pub use protobuf::{ProtoError, WpiProto};
+pub use schema::{SchemaError, SchemaRegistry, Shaped};
+#[cfg(test)]
+pub use serialize::{serialize, serialize_list, Serialize, SerializeError};
+pub use version::PhotonVersion;
+pub use view::{PhotonResultView, TargetView};
+pub use wpistruct::{StructError, WpiStruct};
+
 #[derive(Error, Debug)]
-pub enum DeserializeError {}
+pub enum DeserializeError {
+    #[error("At {location}: Ran out of data reading {needed} bytes")]
+    Truncated {
+        needed: usize,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: {value} isn't a {name}")]
+    BadDiscriminant {
+        name: &'static str,
+        value: i64,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: Fiducial id {value} is negative but isn't -1")]
+    NegativeFiducialId {
+        value: i32,
+        location: &'static Location<'static>,
+    },
+}
+
+/// The protocol version [`Deserialize::deserialize`] reads, newer than any
+/// `#[photon(version >= ...)]`
+pub const LATEST: u32 = u32::MAX;
 
 pub trait Deserialize: Sized {
     fn deserialize(data: &mut Cursor<&[u8]>) -> Result<Self, DeserializeError>;
+
+    /// Reads the layout of protocol `version`, for types with
+    /// `#[photon(version >= ...)]` fields. Others ignore it.
+    fn deserialize_version(
+        data: &mut Cursor<&[u8]>,
+        version: u32,
+    ) -> Result<Self, DeserializeError> {
+        let _ = version;
+        Self::deserialize(data)
+    }
+
+    /// Decodes over `out`, reusing the allocations of any `Vec`s in it. `out` is
+    /// left partly overwritten if this fails.
+    fn deserialize_in_place(
+        data: &mut Cursor<&[u8]>,
+        out: &mut Self,
+    ) -> Result<(), DeserializeError> {
+        *out = Self::deserialize(data)?;
+        Ok(())
+    }
 }
 
 pub fn deserialize<D: Deserialize>(data: &[u8]) -> Result<D, DeserializeError> {
     D::deserialize(&mut Cursor::new(data))
 }
 
-trait FpHash {
-    fn hash(&self, h: &mut impl Hasher);
+/// [`deserialize`] into an existing value, so decoding frame after frame into
+/// the same one stops allocating once its `Vec`s have grown big enough
+pub fn deserialize_into<D: Deserialize>(data: &[u8], out: &mut D) -> Result<(), DeserializeError> {
+    D::deserialize_in_place(&mut Cursor::new(data), out)
 }
 
-impl FpHash for f64 {
-    fn hash(&self, h: &mut impl Hasher) {
-        if self.is_nan() {
-            f64::NAN.to_le_bytes().hash(h);
-        } else {
-            self.to_le_bytes().hash(h);
-        }
-    }
+pub fn deserialize_version<D: Deserialize>(
+    data: &[u8],
+    version: u32,
+) -> Result<D, DeserializeError> {
+    D::deserialize_version(&mut Cursor::new(data), version)
 }
 
-impl FpHash for f32 {
-    fn hash(&self, h: &mut impl Hasher) {
-        if self.is_nan() {
-            f32::NAN.to_le_bytes().hash(h);
-        } else {
-            self.to_le_bytes().hash(h);
-        }
+/// The bits of a float, for hashing. NaNs all hash the same, and so do both
+/// zeroes, to stay consistent with `==`.
+pub fn float_bits(value: f64) -> u64 {
+    if value.is_nan() {
+        f64::NAN.to_bits()
+    } else if value == 0.0 {
+        0
+    } else {
+        value.to_bits()
     }
 }
 
-impl FpHash for Angle {
-    fn hash(&self, h: &mut impl Hasher) {
-        self.get::<radian>().hash(h);
-    }
-}
-
-impl FpHash for Length {
-    fn hash(&self, h: &mut impl Hasher) {
-        self.get::<meter>().hash(h);
-    }
-}
-
-macro_rules! define_types {
-    // main entrypoint
-    ($(
-        $([$manual:ident ( $($eq:ident)?, $($hash:ident)? )])?
-        $(#[$struct_attr:meta])*
-        pub struct $struct:ident {
-            $(pub $field:ident: $ty:ty),* $(,)?
-        }
-    )*) => {
-        $(
-            $(#[$struct_attr])*
-            pub struct $struct {
-                $(pub $field: $ty),*
-            }
-
-            impl Deserialize for $struct {
-                #[allow(unused_assignments)]
-                fn deserialize(data: &mut Cursor<&[u8]>) -> Result<Self, DeserializeError> {
-                    Ok(Self {
-                        $($field: <$ty as Deserialize>::deserialize(data)?),*
-                    })
-                }
-            }
-
-            define_types!(impl $( $manual $($eq)? )? $struct);
-            define_types!(impl $( $manual $($hash)? )? $struct $($field:$ty)*);
-        )*
-    };
-
-    // Eq+Hash manual impls
-    (impl manual Eq $struct:ident) => {
-        impl Eq for $struct {}
-    };
-    (impl manual Hash $struct:ident $($field:ident : $ty:ty)*) => {
-        impl Hash for $struct {
-            fn hash<H: Hasher>(&self, hasher: &mut H) {
-                $(self.$field.hash(hasher));*
-            }
-        }
-    };
-
-    // catch other internal uses
-    (impl $_a:ident $_b:ident $($_c:ident : $_d:ty)*) => {};
-    (impl $_a:ident $_b:ident) => {};
-    (impl $_a:ident $($_b:ident : $_c:ty)*) => {};
-    (impl $_a:ident) => {};
-}
+/// Photon sends -1 as the class of targets that didn't come from object detection
+pub const NO_CLASS: u64 = u64::MAX;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
 pub struct FiducialId(pub Option<u32>);
 
 impl Deserialize for FiducialId {
     fn deserialize(data: &mut Cursor<&[u8]>) -> Result<Self, DeserializeError> {
         let value = i32::deserialize(data)?;
 
-        if value == -1 {
-            return Ok(Self(None));
+        match value {
+            -1 => Ok(Self(None)),
+            0.. => Ok(Self(Some(value as u32))),
+            _ => Err(DeserializeError::NegativeFiducialId {
+                value,
+                location: Location::caller(),
+            }),
         }
-
-        Ok(Self(Some(value as u32)))
     }
 }
 
 // photon types
-define_types! {
-    [manual(Eq, Hash)]
-    #[derive(Clone, Copy, Debug, PartialEq)]
-    pub struct Quaternion {
-        pub w: f64,
-        pub x: f64,
-        pub y: f64,
-        pub z: f64,
-    }
+#[derive(Clone, Copy, Debug, PartialEq, PhotonSerde, FloatHash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
 
-    [manual(Eq, Hash)]
-    #[derive(Clone, Copy, Debug, PartialEq)]
-    pub struct Translate3d {
-        pub x: f64,
-        pub y: f64,
-        pub z: f64,
-    }
+#[derive(Clone, Copy, Debug, PartialEq, PhotonSerde, FloatHash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Translate3d {
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
 
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    pub struct Transform3d {
-        pub translation: Translate3d,
-        pub rotation: Quaternion,
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PhotonSerde)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Transform3d {
+    pub translation: Translate3d,
+    pub rotation: Quaternion,
+}
 
-    [manual(Eq, Hash)]
-    #[derive(Clone, Copy, Debug, PartialEq)]
-    pub struct DetectedObject {
-        pub id: u64,
-        pub confidence: f32,
-    }
+#[derive(Clone, Copy, Debug, PartialEq, PhotonSerde, FloatHash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DetectedObject {
+    pub id: u64,
+    pub confidence: f32,
+}
 
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    pub struct TargetTransforms {
-        pub best: Transform3d,
-        pub alt: Transform3d,
-    }
+impl DetectedObject {
+    /// What targets that didn't come from object detection have
+    pub const NONE: Self = Self {
+        id: NO_CLASS,
+        confidence: 0.0,
+    };
+}
 
-    [manual(Eq, Hash)]
-    #[derive(Clone, Copy, Debug, PartialEq)]
-    pub struct TargetCorner {
-        pub x: f64,
-        pub y: f64,
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PhotonSerde)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TargetTransforms {
+    pub best: Transform3d,
+    pub alt: Transform3d,
+}
 
-    [manual(Eq, Hash)]
-    #[derive(Clone, Copy, Debug, PartialEq)]
-    pub struct PNPResult {
-        pub best: Transform3d,
-        pub alt: Transform3d,
-        pub error: f64,
-        pub alt_error: f64,
-        pub ambiguity: f64,
-    }
+#[derive(Clone, Copy, Debug, PartialEq, PhotonSerde, FloatHash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct TargetCorner {
+    pub x: f64,
+    pub y: f64,
+}
 
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    pub struct PhotonPipelineMetadata {
-        pub seqid: u64,
-        pub capture_time: Duration,
-        pub publish_time: Duration,
-        pub last_handshake: Duration,
-    }
+#[derive(Clone, Copy, Debug, PartialEq, PhotonSerde, FloatHash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PNPResult {
+    pub best: Transform3d,
+    pub alt: Transform3d,
+    pub error: f64,
+    pub alt_error: f64,
+    pub ambiguity: f64,
+}
 
-    [manual(Eq, Hash)]
-    #[derive(Clone, Debug, PartialEq)]
-    pub struct PhotonTrackedTarget {
-        pub yaw: f64,
-        pub pitch: f64,
-        pub area: f64,
-        pub skew: f64,
-        pub fiducial_id: FiducialId,
-        pub detected: DetectedObject,
-        pub to_target: TargetTransforms,
-        pub ambiguity: f64,
-        pub area_rect_corners: Vec<TargetCorner>,
-        pub detected_corners: Vec<TargetCorner>,
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PhotonSerde)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PhotonPipelineMetadata {
+    pub seqid: u64,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "export::micros"))]
+    pub capture_time: Duration,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "export::micros"))]
+    pub publish_time: Duration,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "export::micros"))]
+    pub last_handshake: Duration,
+}
 
-    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
-    pub struct MultiTargetPNP {
-        pub pnp: PNPResult,
-        pub num_fiducials: u16,
-    }
+#[derive(Clone, Debug, PartialEq, PhotonSerde, FloatHash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PhotonTrackedTarget {
+    pub yaw: f64,
+    pub pitch: f64,
+    pub area: f64,
+    pub skew: f64,
+    pub fiducial_id: FiducialId,
+    #[photon(version >= 2025, default = DetectedObject::NONE)]
+    pub detected: DetectedObject,
+    pub to_target: TargetTransforms,
+    pub ambiguity: f64,
+    #[photon(version >= 2024, legacy = version::legacy::four_corners)]
+    pub area_rect_corners: Vec<TargetCorner>,
+    pub detected_corners: Vec<TargetCorner>,
+}
 
-    #[derive(Clone, Debug, PartialEq, Eq, Hash)]
-    pub struct PhotonResult {
-        pub metadata: PhotonPipelineMetadata,
-        pub targets: Vec<PhotonTrackedTarget>,
-        pub pnp: Option<MultiTargetPNP>,
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PhotonSerde)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct MultiTargetPNP {
+    pub pnp: PNPResult,
+    pub num_fiducials: u16,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PhotonSerde)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct PhotonResult {
+    #[photon(version >= 2025, legacy = version::legacy::latency)]
+    pub metadata: PhotonPipelineMetadata,
+    pub targets: Vec<PhotonTrackedTarget>,
+    #[photon(version >= 2025, legacy = version::legacy::multitag)]
+    pub pnp: Option<MultiTargetPNP>,
 }
 
 // robot types
-define_types! {
-    [manual(Eq, Hash)]
-    #[derive(Clone, Copy, Debug, Default, PartialEq)]
-    pub struct Rotate2d {
-        pub angle: Angle,
-    }
+#[derive(Clone, Copy, Debug, Default, PartialEq, PhotonSerde, FloatHash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Rotate2d {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "export::radians"))]
+    pub angle: Angle,
+}
 
-    [manual(Eq, Hash)]
-    #[derive(Clone, Copy, Debug, Default, PartialEq)]
-    pub struct Translate2d {
-        pub x: Length,
-        pub y: Length,
-    }
+#[derive(Clone, Copy, Debug, Default, PartialEq, PhotonSerde, FloatHash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Translate2d {
+    #[cfg_attr(feature = "serde", serde(serialize_with = "export::meters"))]
+    pub x: Length,
+    #[cfg_attr(feature = "serde", serde(serialize_with = "export::meters"))]
+    pub y: Length,
+}
 
-    [manual(Eq, Hash)]
-    #[derive(Clone, Copy, Debug, Default, PartialEq)]
-    pub struct Pose2d {
-        pub translate: Translate2d,
-        pub rotate: Rotate2d,
-    }
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PhotonSerde)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Pose2d {
+    pub translate: Translate2d,
+    pub rotate: Rotate2d,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PhotonSerde)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Transform2d {
+    pub translate: Translate2d,
+    pub rotate: Rotate2d,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PhotonSerde)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Pose3d {
+    pub translation: Translate3d,
+    pub rotation: Quaternion,
 }
 
 impl From<Translate2d> for (Length, Length) {
@@ -0,0 +1,687 @@
+//! Protobuf encoding of WPILib's geometry and trajectory messages
+//! (`wpi.proto.ProtobufPose2d` and friends). The messages are small and fixed, so
+//! this is hand-rolled rather than generated.
+
+use std::panic::Location;
+
+use crate::prelude::*;
+use planner::trajectory::{Trajectory, TrajectoryState};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ProtoError {
+    #[error("At {location}: {message} ended in the middle of a field")]
+    Truncated {
+        message: &'static str,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: {message} field {field} has wire type {found}, expected {expected}")]
+    WrongWireType {
+        message: &'static str,
+        field: u32,
+        found: u8,
+        expected: u8,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: {message} uses unknown wire type {wire_type}")]
+    UnknownWireType {
+        message: &'static str,
+        wire_type: u8,
+        location: &'static Location<'static>,
+    },
+}
+
+const VARINT: u8 = 0;
+const FIXED64: u8 = 1;
+const LEN: u8 = 2;
+const FIXED32: u8 = 5;
+
+/// A field's payload, still undecoded
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WireValue<'a> {
+    Varint(u64),
+    Fixed64([u8; 8]),
+    Len(&'a [u8]),
+    Fixed32([u8; 4]),
+}
+
+impl WireValue<'_> {
+    fn wire_type(&self) -> u8 {
+        match self {
+            Self::Varint(_) => VARINT,
+            Self::Fixed64(_) => FIXED64,
+            Self::Len(_) => LEN,
+            Self::Fixed32(_) => FIXED32,
+        }
+    }
+}
+
+/// Walks the fields of one message, in wire order
+pub struct Reader<'a> {
+    message: &'static str,
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(message: &'static str, data: &'a [u8]) -> Self {
+        Self { message, data }
+    }
+
+    #[track_caller]
+    fn take(&mut self, len: usize) -> Result<&'a [u8], ProtoError> {
+        if self.data.len() < len {
+            return Err(ProtoError::Truncated {
+                message: self.message,
+                location: Location::caller(),
+            });
+        }
+
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(taken)
+    }
+
+    #[track_caller]
+    fn varint(&mut self) -> Result<u64, ProtoError> {
+        let mut value = 0u64;
+
+        for shift in (0..64).step_by(7) {
+            let byte = self.take(1)?[0];
+            value |= ((byte & 0x7f) as u64) << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        // more than 10 bytes can't be a valid varint
+        Err(ProtoError::Truncated {
+            message: self.message,
+            location: Location::caller(),
+        })
+    }
+
+    /// The next (field number, value), or `None` at the end of the message
+    #[track_caller]
+    pub fn field(&mut self) -> Result<Option<(u32, WireValue<'a>)>, ProtoError> {
+        if self.data.is_empty() {
+            return Ok(None);
+        }
+
+        let key = self.varint()?;
+        let field = (key >> 3) as u32;
+
+        let value = match (key & 0x7) as u8 {
+            VARINT => WireValue::Varint(self.varint()?),
+            FIXED64 => WireValue::Fixed64(self.take(8)?.try_into().unwrap()),
+            LEN => {
+                let len = self.varint()? as usize;
+                WireValue::Len(self.take(len)?)
+            }
+            FIXED32 => WireValue::Fixed32(self.take(4)?.try_into().unwrap()),
+            wire_type => {
+                return Err(ProtoError::UnknownWireType {
+                    message: self.message,
+                    wire_type,
+                    location: Location::caller(),
+                })
+            }
+        };
+
+        Ok(Some((field, value)))
+    }
+
+    #[track_caller]
+    fn expect(&self, field: u32, value: &WireValue, expected: u8) -> Result<(), ProtoError> {
+        if value.wire_type() != expected {
+            return Err(ProtoError::WrongWireType {
+                message: self.message,
+                field,
+                found: value.wire_type(),
+                expected,
+                location: Location::caller(),
+            });
+        }
+
+        Ok(())
+    }
+
+    #[track_caller]
+    pub fn double(&self, field: u32, value: WireValue) -> Result<f64, ProtoError> {
+        self.expect(field, &value, FIXED64)?;
+        let WireValue::Fixed64(bytes) = value else {
+            unreachable!()
+        };
+
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    #[track_caller]
+    pub fn message<T: WpiProto>(&self, field: u32, value: WireValue) -> Result<T, ProtoError> {
+        self.expect(field, &value, LEN)?;
+        let WireValue::Len(bytes) = value else {
+            unreachable!()
+        };
+
+        T::from_proto(bytes)
+    }
+}
+
+fn put_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8 & 0x7f) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn put_key(out: &mut Vec<u8>, field: u32, wire_type: u8) {
+    put_varint(out, ((field as u64) << 3) | wire_type as u64);
+}
+
+/// proto3 leaves out fields at their default, so zeros cost nothing
+pub fn put_double(out: &mut Vec<u8>, field: u32, value: f64) {
+    if value.to_bits() == 0 {
+        return;
+    }
+
+    put_key(out, field, FIXED64);
+    out.extend(value.to_le_bytes());
+}
+
+pub fn put_message(out: &mut Vec<u8>, field: u32, value: &impl WpiProto) {
+    let bytes = value.to_proto();
+
+    put_key(out, field, LEN);
+    put_varint(out, bytes.len() as u64);
+    out.extend(bytes);
+}
+
+/// A type with a WPILib protobuf message. Missing fields decode as zero and
+/// unknown ones are skipped, like any protobuf reader.
+pub trait WpiProto: Sized {
+    /// Full message name, which is what goes after `proto:` in type strings
+    const MESSAGE: &'static str;
+
+    fn encode_fields(&self, out: &mut Vec<u8>);
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError>;
+
+    fn proto_type_string() -> String {
+        format!("proto:{}", Self::MESSAGE)
+    }
+
+    fn to_proto(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.encode_fields(&mut out);
+        out
+    }
+}
+
+impl WpiProto for Translate2d {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufTranslation2d";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        put_double(out, 1, self.x.get::<meter>());
+        put_double(out, 2, self.y.get::<meter>());
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let mut reader = Reader::new(Self::MESSAGE, data);
+        let mut value = Self::default();
+
+        while let Some((field, wire)) = reader.field()? {
+            match field {
+                1 => value.x = Length::new::<meter>(reader.double(field, wire)?),
+                2 => value.y = Length::new::<meter>(reader.double(field, wire)?),
+                _ => {}
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+impl WpiProto for Rotate2d {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufRotation2d";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        put_double(out, 1, self.angle.get::<radian>());
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let mut reader = Reader::new(Self::MESSAGE, data);
+        let mut value = Self::default();
+
+        while let Some((field, wire)) = reader.field()? {
+            if field == 1 {
+                value.angle = Angle::new::<radian>(reader.double(field, wire)?);
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+impl WpiProto for Pose2d {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufPose2d";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        put_message(out, 1, &self.translate);
+        put_message(out, 2, &self.rotate);
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let mut reader = Reader::new(Self::MESSAGE, data);
+        let mut value = Self::default();
+
+        while let Some((field, wire)) = reader.field()? {
+            match field {
+                1 => value.translate = reader.message(field, wire)?,
+                2 => value.rotate = reader.message(field, wire)?,
+                _ => {}
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+impl WpiProto for Transform2d {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufTransform2d";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        put_message(out, 1, &self.translate);
+        put_message(out, 2, &self.rotate);
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let mut reader = Reader::new(Self::MESSAGE, data);
+        let mut value = Self::default();
+
+        while let Some((field, wire)) = reader.field()? {
+            match field {
+                1 => value.translate = reader.message(field, wire)?,
+                2 => value.rotate = reader.message(field, wire)?,
+                _ => {}
+            }
+        }
+
+        Ok(value)
+    }
+}
+
+impl WpiProto for Translate3d {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufTranslation3d";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        put_double(out, 1, self.x);
+        put_double(out, 2, self.y);
+        put_double(out, 3, self.z);
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let mut reader = Reader::new(Self::MESSAGE, data);
+        let mut value = [0.0; 3];
+
+        while let Some((field, wire)) = reader.field()? {
+            if (1..=3).contains(&field) {
+                value[field as usize - 1] = reader.double(field, wire)?;
+            }
+        }
+
+        Ok(value.into())
+    }
+}
+
+impl WpiProto for Quaternion {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufQuaternion";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        put_double(out, 1, self.w);
+        put_double(out, 2, self.x);
+        put_double(out, 3, self.y);
+        put_double(out, 4, self.z);
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let mut reader = Reader::new(Self::MESSAGE, data);
+        let mut value = [0.0; 4];
+
+        while let Some((field, wire)) = reader.field()? {
+            if (1..=4).contains(&field) {
+                value[field as usize - 1] = reader.double(field, wire)?;
+            }
+        }
+
+        let [w, x, y, z] = value;
+        Ok(Self { w, x, y, z })
+    }
+}
+
+/// WPILib's `ProtobufRotation3d`, which wraps the quaternion in field 1
+struct Rotation3d(Quaternion);
+
+impl WpiProto for Rotation3d {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufRotation3d";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        put_message(out, 1, &self.0);
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let mut reader = Reader::new(Self::MESSAGE, data);
+        // an unset rotation is no rotation, not a zero quaternion
+        let mut value = Quaternion::IDENTITY;
+
+        while let Some((field, wire)) = reader.field()? {
+            if field == 1 {
+                value = reader.message(field, wire)?;
+            }
+        }
+
+        Ok(Self(value))
+    }
+}
+
+impl WpiProto for Pose3d {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufPose3d";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        put_message(out, 1, &self.translation);
+        put_message(out, 2, &Rotation3d(self.rotation));
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let Transform3d {
+            translation,
+            rotation,
+        } = decode_3d(Self::MESSAGE, data)?;
+
+        Ok(Self {
+            translation,
+            rotation,
+        })
+    }
+}
+
+impl WpiProto for Transform3d {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufTransform3d";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        put_message(out, 1, &self.translation);
+        put_message(out, 2, &Rotation3d(self.rotation));
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        decode_3d(Self::MESSAGE, data)
+    }
+}
+
+/// Pose3d and Transform3d share a layout
+fn decode_3d(message: &'static str, data: &[u8]) -> Result<Transform3d, ProtoError> {
+    let mut reader = Reader::new(message, data);
+    let mut value = Transform3d::IDENTITY;
+
+    while let Some((field, wire)) = reader.field()? {
+        match field {
+            1 => value.translation = reader.message(field, wire)?,
+            2 => value.rotation = reader.message::<Rotation3d>(field, wire)?.0,
+            _ => {}
+        }
+    }
+
+    Ok(value)
+}
+
+/// WPILib's states only carry a speed and a tangential acceleration, so the
+/// velocity is along the direction of travel when encoding and along the pose's
+/// heading when decoding
+impl WpiProto for TrajectoryState {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufTrajectoryState";
+
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        let speed = self.speed().get::<mps>();
+        let (vx, vy) = (self.velocity.0.get::<mps>(), self.velocity.1.get::<mps>());
+        let (ax, ay) = (
+            self.acceleration.0.get::<mps2>(),
+            self.acceleration.1.get::<mps2>(),
+        );
+        let tangential = if speed > 1e-9 {
+            (ax * vx + ay * vy) / speed
+        } else {
+            ax.hypot(ay)
+        };
+
+        put_double(out, 1, self.time.get::<second>());
+        put_double(out, 2, speed);
+        put_double(out, 3, tangential);
+        put_message(out, 4, &self.pose);
+        put_double(out, 5, self.curvature);
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let mut reader = Reader::new(Self::MESSAGE, data);
+        let (mut time, mut speed, mut accel, mut curvature) = (0.0, 0.0, 0.0, 0.0);
+        let mut pose = Pose2d::default();
+
+        while let Some((field, wire)) = reader.field()? {
+            match field {
+                1 => time = reader.double(field, wire)?,
+                2 => speed = reader.double(field, wire)?,
+                3 => accel = reader.double(field, wire)?,
+                4 => pose = reader.message(field, wire)?,
+                5 => curvature = reader.double(field, wire)?,
+                _ => {}
+            }
+        }
+
+        let (sin, cos) = pose.rotate.angle.get::<radian>().sin_cos();
+
+        Ok(Self {
+            time: Time::new::<second>(time),
+            pose,
+            velocity: (
+                Velocity::new::<mps>(speed * cos),
+                Velocity::new::<mps>(speed * sin),
+            ),
+            acceleration: (
+                Acceleration::new::<mps2>(accel * cos),
+                Acceleration::new::<mps2>(accel * sin),
+            ),
+            angular_velocity: AngularVelocity::new::<radps>(speed * curvature),
+            curvature,
+        })
+    }
+}
+
+impl WpiProto for Trajectory {
+    const MESSAGE: &'static str = "wpi.proto.ProtobufTrajectory";
+
+    // WPILib numbers the states field 2
+    fn encode_fields(&self, out: &mut Vec<u8>) {
+        for state in &self.states {
+            put_message(out, 2, state);
+        }
+    }
+
+    fn from_proto(data: &[u8]) -> Result<Self, ProtoError> {
+        let mut reader = Reader::new(Self::MESSAGE, data);
+        let mut states = vec![];
+
+        while let Some((field, wire)) = reader.field()? {
+            if field == 2 {
+                states.push(reader.message(field, wire)?);
+            }
+        }
+
+        Ok(Self { states })
+    }
+}
+
+/// One field of a message in a [`file_descriptors`] file: a double, or the
+/// named message if `message` is set
+struct FieldSpec {
+    name: &'static str,
+    number: u32,
+    message: Option<&'static str>,
+    repeated: bool,
+}
+
+const fn double(name: &'static str, number: u32) -> FieldSpec {
+    FieldSpec {
+        name,
+        number,
+        message: None,
+        repeated: false,
+    }
+}
+
+const fn message(name: &'static str, number: u32, message: &'static str) -> FieldSpec {
+    FieldSpec {
+        name,
+        number,
+        message: Some(message),
+        repeated: false,
+    }
+}
+
+const GEOMETRY2D: &[(&str, &[FieldSpec])] = &[
+    ("ProtobufTranslation2d", &[double("x", 1), double("y", 2)]),
+    ("ProtobufRotation2d", &[double("value", 1)]),
+    (
+        "ProtobufPose2d",
+        &[
+            message("translation", 1, "ProtobufTranslation2d"),
+            message("rotation", 2, "ProtobufRotation2d"),
+        ],
+    ),
+    (
+        "ProtobufTransform2d",
+        &[
+            message("translation", 1, "ProtobufTranslation2d"),
+            message("rotation", 2, "ProtobufRotation2d"),
+        ],
+    ),
+    (
+        "ProtobufTwist2d",
+        &[double("dx", 1), double("dy", 2), double("dtheta", 3)],
+    ),
+    (
+        "ProtobufRectangle2d",
+        &[
+            message("center", 1, "ProtobufPose2d"),
+            double("xWidth", 2),
+            double("yWidth", 3),
+        ],
+    ),
+    (
+        "ProtobufEllipse2d",
+        &[
+            message("center", 1, "ProtobufPose2d"),
+            double("xSemiAxis", 2),
+            double("ySemiAxis", 3),
+        ],
+    ),
+];
+
+const TRAJECTORY: &[(&str, &[FieldSpec])] = &[
+    (
+        "ProtobufTrajectoryState",
+        &[
+            double("time", 1),
+            double("velocity", 2),
+            double("acceleration", 3),
+            message("pose", 4, "ProtobufPose2d"),
+            double("curvature", 5),
+        ],
+    ),
+    (
+        "ProtobufTrajectory",
+        &[FieldSpec {
+            name: "states",
+            number: 2,
+            message: Some("ProtobufTrajectoryState"),
+            repeated: true,
+        }],
+    ),
+];
+
+fn put_bytes(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+    put_key(out, field, LEN);
+    put_varint(out, bytes.len() as u64);
+    out.extend(bytes);
+}
+
+fn put_uint(out: &mut Vec<u8>, field: u32, value: u64) {
+    put_key(out, field, VARINT);
+    put_varint(out, value);
+}
+
+/// A `google.protobuf.FileDescriptorProto` for WPILib's file `name`
+fn file_descriptor(
+    name: &str,
+    dependencies: &[&str],
+    messages: &[(&str, &[FieldSpec])],
+) -> Vec<u8> {
+    // FieldDescriptorProto's label and type enums
+    const OPTIONAL: u64 = 1;
+    const REPEATED: u64 = 3;
+    const TYPE_DOUBLE: u64 = 1;
+    const TYPE_MESSAGE: u64 = 11;
+
+    let mut out = vec![];
+    put_bytes(&mut out, 1, name.as_bytes());
+    put_bytes(&mut out, 2, b"wpi.proto");
+    for dependency in dependencies {
+        put_bytes(&mut out, 3, dependency.as_bytes());
+    }
+
+    for (message, fields) in messages {
+        let mut descriptor = vec![];
+        put_bytes(&mut descriptor, 1, message.as_bytes());
+
+        for field in *fields {
+            let mut spec = vec![];
+            put_bytes(&mut spec, 1, field.name.as_bytes());
+            put_uint(&mut spec, 3, field.number as u64);
+            put_uint(
+                &mut spec,
+                4,
+                if field.repeated { REPEATED } else { OPTIONAL },
+            );
+            match field.message {
+                Some(message) => {
+                    put_uint(&mut spec, 5, TYPE_MESSAGE);
+                    put_bytes(&mut spec, 6, format!(".wpi.proto.{message}").as_bytes());
+                }
+                None => put_uint(&mut spec, 5, TYPE_DOUBLE),
+            }
+
+            put_bytes(&mut descriptor, 2, &spec);
+        }
+
+        put_bytes(&mut out, 4, &descriptor);
+    }
+
+    put_bytes(&mut out, 12, b"proto3");
+    out
+}
+
+/// WPILib's `.proto` files for the messages we publish, as (file name, encoded
+/// `FileDescriptorProto`). They go under `/.schema/proto:<file name>`, where
+/// protobuf subscribers look for them.
+pub fn file_descriptors() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        (
+            "geometry2d.proto",
+            file_descriptor("geometry2d.proto", &[], GEOMETRY2D),
+        ),
+        (
+            "trajectory.proto",
+            file_descriptor("trajectory.proto", &["geometry2d.proto"], TRAJECTORY),
+        ),
+    ]
+}
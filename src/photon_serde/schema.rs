@@ -0,0 +1,707 @@
+//! Struct schemas, as published under `/.schema` by WPILib (`struct:...`) and
+//! PhotonVision (`photonstruct:Name:uuid`). They're used to check our compiled
+//! layouts against what the coprocessor actually sends, and to decode layouts we
+//! don't have types for.
+
+use std::{collections::HashMap, fmt, panic::Location, time::Duration};
+
+use crate::prelude::*;
+use thiserror::Error;
+
+/// Nesting deeper than this is assumed to be a schema that refers to itself
+const MAX_DEPTH: usize = 32;
+
+#[derive(Error, Debug)]
+pub enum SchemaError {
+    #[error("At {location}: Can't parse schema of {name} at {decl:?}: {reason}")]
+    Parse {
+        name: Box<str>,
+        decl: Box<str>,
+        reason: &'static str,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: No schema has been published for {name}")]
+    UnknownType {
+        name: Box<str>,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: {name} nests too deeply, it probably contains itself")]
+    TooDeep {
+        name: Box<str>,
+        location: &'static Location<'static>,
+    },
+
+    #[error(
+        "At {location}: Published {name} doesn't match our layout, data would be garbled\n  \
+         ours:   {ours}\n  theirs: {theirs}"
+    )]
+    Mismatch {
+        name: Box<str>,
+        ours: Box<str>,
+        theirs: Box<str>,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: {name} ended early while decoding")]
+    Truncated {
+        name: Box<str>,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: Decoded {name} has no {field} we can read")]
+    MissingField {
+        name: &'static str,
+        field: &'static str,
+        location: &'static Location<'static>,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Primitive {
+    Bool,
+    /// By size in bytes
+    Int(u8),
+    UInt(u8),
+    Float(u8),
+}
+
+impl Primitive {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "bool" => Self::Bool,
+            "char" | "int8" => Self::Int(1),
+            "int16" => Self::Int(2),
+            "int32" => Self::Int(4),
+            "int64" => Self::Int(8),
+            "uint8" => Self::UInt(1),
+            "uint16" => Self::UInt(2),
+            "uint32" => Self::UInt(4),
+            "uint64" => Self::UInt(8),
+            "float" | "float32" => Self::Float(4),
+            "double" | "float64" => Self::Float(8),
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Self::Bool => 1,
+            Self::Int(size) | Self::UInt(size) | Self::Float(size) => size as usize,
+        }
+    }
+
+    /// Signedness only changes what the bytes mean, not where they are
+    fn signless(self) -> Self {
+        match self {
+            Self::UInt(size) => Self::Int(size),
+            primitive => primitive,
+        }
+    }
+}
+
+impl fmt::Display for Primitive {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bool => write!(f, "bool"),
+            Self::Int(size) => write!(f, "int{}", size * 8),
+            Self::UInt(size) => write!(f, "uint{}", size * 8),
+            Self::Float(size) => write!(f, "float{}", size * 8),
+        }
+    }
+}
+
+/// A type flattened down to primitives. Struct boundaries and names are gone,
+/// so two layouts match when their [`Shape::signless`] forms are equal.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Shape {
+    Primitive(Primitive),
+    /// A bool, then the contents if it's set
+    Optional(Vec<Shape>),
+    /// A length, then that many elements. Schemas' `[?]` always have a `u8`
+    /// length.
+    List(Primitive, Vec<Shape>),
+    Fixed(usize, Vec<Shape>),
+}
+
+impl Shape {
+    /// `self` with unsigned integers made signed. Photon's schemas declare
+    /// fields like the sequence id signed where we keep them unsigned, which
+    /// reads the same bytes.
+    pub fn signless(&self) -> Self {
+        let signless = |shapes: &[Shape]| shapes.iter().map(Shape::signless).collect();
+
+        match self {
+            Self::Primitive(primitive) => Self::Primitive(primitive.signless()),
+            Self::Optional(inner) => Self::Optional(signless(inner)),
+            Self::List(len, inner) => Self::List(len.signless(), signless(inner)),
+            Self::Fixed(len, inner) => Self::Fixed(*len, signless(inner)),
+        }
+    }
+}
+
+pub fn shape_string(shapes: &[Shape]) -> String {
+    shapes.iter().map(|shape| shape.to_string()).join(" ")
+}
+
+impl fmt::Display for Shape {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Primitive(primitive) => write!(f, "{primitive}"),
+            Self::Optional(inner) => write!(f, "optional({})", shape_string(inner)),
+            Self::List(Primitive::UInt(1), inner) => write!(f, "[?]({})", shape_string(inner)),
+            Self::List(len, inner) => write!(f, "[?{len}]({})", shape_string(inner)),
+            Self::Fixed(len, inner) => write!(f, "[{len}]({})", shape_string(inner)),
+        }
+    }
+}
+
+/// A type whose photon_serde layout can be described as a [`Shape`]
+pub trait Shaped {
+    fn shape(out: &mut Vec<Shape>);
+
+    fn shapes() -> Vec<Shape> {
+        let mut out = vec![];
+        Self::shape(&mut out);
+        out
+    }
+}
+
+macro_rules! shaped {
+    ($($ty:ty => $primitive:expr),* $(,)?) => {
+        $(
+            impl Shaped for $ty {
+                fn shape(out: &mut Vec<Shape>) {
+                    out.push(Shape::Primitive($primitive));
+                }
+            }
+        )*
+    };
+}
+
+shaped! {
+    bool => Primitive::Bool,
+    u8 => Primitive::UInt(1),
+    i8 => Primitive::Int(1),
+    u16 => Primitive::UInt(2),
+    i16 => Primitive::Int(2),
+    u32 => Primitive::UInt(4),
+    i32 => Primitive::Int(4),
+    u64 => Primitive::UInt(8),
+    i64 => Primitive::Int(8),
+    f32 => Primitive::Float(4),
+    f64 => Primitive::Float(8),
+    Angle => Primitive::Float(8),
+    Length => Primitive::Float(8),
+    Duration => Primitive::UInt(8),
+    FiducialId => Primitive::Int(4),
+}
+
+impl<T: Shaped> Shaped for Option<T> {
+    fn shape(out: &mut Vec<Shape>) {
+        out.push(Shape::Optional(T::shapes()));
+    }
+}
+
+impl<T: Shaped, const N: usize> Shaped for [T; N] {
+    fn shape(out: &mut Vec<Shape>) {
+        out.push(Shape::Fixed(N, T::shapes()));
+    }
+}
+
+impl<T: Shaped> Shaped for Vec<T> {
+    fn shape(out: &mut Vec<Shape>) {
+        list_shape::<u8, T>(out);
+    }
+}
+
+/// A list of `T` with an `L` length prefix
+pub fn list_shape<L: Shaped, T: Shaped>(out: &mut Vec<Shape>) {
+    let [Shape::Primitive(len)] = L::shapes()[..] else {
+        panic!("list lengths have to be integers");
+    };
+
+    out.push(Shape::List(len, T::shapes()));
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Count {
+    One,
+    Optional,
+    /// `[?]`, photon's variable length arrays
+    List,
+    Fixed(usize),
+}
+
+/// One `type name` declaration of a schema
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SchemaField {
+    pub name: String,
+    /// A primitive, or another schema's name (`Transform3d`, `PhotonTrackedTarget:uuid`)
+    pub ty: String,
+    pub count: Count,
+}
+
+/// Parses a schema like `optional Foo:abc bar;double baz[4];Qux[?] quux`
+#[track_caller]
+pub fn parse(name: &str, schema: &str) -> Result<Vec<SchemaField>, SchemaError> {
+    let location = Location::caller();
+    let mut fields = vec![];
+
+    for decl in schema
+        .split(';')
+        .map(str::trim)
+        .filter(|decl| !decl.is_empty())
+    {
+        let error = |reason| SchemaError::Parse {
+            name: name.into(),
+            decl: decl.into(),
+            reason,
+            location,
+        };
+
+        if decl.starts_with("enum") {
+            return Err(error("enums aren't supported"));
+        }
+
+        let mut words = decl.split_whitespace().collect_vec();
+        let optional = words.first() == Some(&"optional");
+        if optional {
+            words.remove(0);
+        }
+
+        let [ty, field] = words[..] else {
+            return Err(error("expected `type name`"));
+        };
+
+        // photon puts [?] on either side
+        let (ty, ty_suffix) = split_suffix(ty);
+        let (field, field_suffix) = split_suffix(field);
+
+        if field.contains(':') {
+            return Err(error("bit-fields aren't supported"));
+        }
+
+        let count = match (optional, ty_suffix.or(field_suffix)) {
+            (true, None) => Count::Optional,
+            (true, Some(_)) => return Err(error("optional arrays aren't supported")),
+            (false, None) => Count::One,
+            (false, Some("?")) => Count::List,
+            (false, Some(len)) => Count::Fixed(len.parse().map_err(|_| error("bad array length"))?),
+        };
+
+        fields.push(SchemaField {
+            name: field.to_string(),
+            ty: ty.to_string(),
+            count,
+        });
+    }
+
+    Ok(fields)
+}
+
+/// `"foo[4]"` -> `("foo", Some("4"))`
+fn split_suffix(word: &str) -> (&str, Option<&str>) {
+    match word.split_once('[') {
+        Some((base, rest)) => (base, Some(rest.trim_end_matches(']'))),
+        None => (word, None),
+    }
+}
+
+/// A value decoded without a compiled type
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    UInt(u64),
+    Float(f64),
+    Optional(Option<Box<Value>>),
+    Array(Vec<Value>),
+    Struct(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// A field of a struct, by name
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        let Self::Struct(fields) = self else {
+            return None;
+        };
+
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value)
+    }
+
+    fn float(&self) -> Option<f64> {
+        match *self {
+            Self::Float(value) => Some(value),
+            Self::Int(value) => Some(value as f64),
+            Self::UInt(value) => Some(value as f64),
+            _ => None,
+        }
+    }
+
+    fn int(&self) -> Option<i64> {
+        match *self {
+            Self::Int(value) => Some(value),
+            Self::UInt(value) => Some(value as i64),
+            _ => None,
+        }
+    }
+
+    fn array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+/// A decoded struct, read by photon's field names
+#[derive(Clone, Copy)]
+struct Decoded<'a> {
+    name: &'static str,
+    value: &'a Value,
+}
+
+impl<'a> Decoded<'a> {
+    #[track_caller]
+    fn field<T>(
+        &self,
+        field: &'static str,
+        read: impl FnOnce(&'a Value) -> Option<T>,
+    ) -> Result<T, SchemaError> {
+        let location = Location::caller();
+
+        self.value
+            .get(field)
+            .and_then(read)
+            .ok_or(SchemaError::MissingField {
+                name: self.name,
+                field,
+                location,
+            })
+    }
+
+    #[track_caller]
+    fn float(&self, field: &'static str) -> Result<f64, SchemaError> {
+        self.field(field, Value::float)
+    }
+
+    #[track_caller]
+    fn micros(&self, field: &'static str) -> Result<Duration, SchemaError> {
+        Ok(Duration::from_micros(self.field(field, Value::int)? as u64))
+    }
+
+    #[track_caller]
+    fn nested(&self, field: &'static str, name: &'static str) -> Result<Self, SchemaError> {
+        self.field(field, |value| Some(Self { name, value }))
+    }
+
+    #[track_caller]
+    fn list(&self, field: &'static str, name: &'static str) -> Result<Vec<Self>, SchemaError> {
+        let values = self.field(field, Value::array)?;
+        Ok(values.iter().map(|value| Self { name, value }).collect())
+    }
+
+    fn transform3d(&self, field: &'static str) -> Result<Transform3d, SchemaError> {
+        let transform = self.nested(field, "Transform3d")?;
+        let translation = transform.nested("translation", "Translation3d")?;
+        let rotation = transform
+            .nested("rotation", "Rotation3d")?
+            .nested("q", "Quaternion")?;
+
+        Ok(Transform3d {
+            translation: Translate3d {
+                x: translation.float("x")?,
+                y: translation.float("y")?,
+                z: translation.float("z")?,
+            },
+            rotation: Quaternion {
+                w: rotation.float("w")?,
+                x: rotation.float("x")?,
+                y: rotation.float("y")?,
+                z: rotation.float("z")?,
+            },
+        })
+    }
+
+    fn corners(&self, field: &'static str) -> Result<Vec<TargetCorner>, SchemaError> {
+        self.list(field, "TargetCorner")?
+            .iter()
+            .map(|corner| {
+                Ok(TargetCorner {
+                    x: corner.float("x")?,
+                    y: corner.float("y")?,
+                })
+            })
+            .collect()
+    }
+
+    fn target(&self) -> Result<PhotonTrackedTarget, SchemaError> {
+        // like the compiled decoder, only -1 means no tag
+        let fiducial_id = self.field("fiducialId", |value| {
+            value
+                .int()
+                .filter(|&id| (-1..=u32::MAX as i64).contains(&id))
+        })?;
+
+        Ok(PhotonTrackedTarget {
+            yaw: self.float("yaw")?,
+            pitch: self.float("pitch")?,
+            area: self.float("area")?,
+            skew: self.float("skew")?,
+            fiducial_id: FiducialId((fiducial_id != -1).then_some(fiducial_id as u32)),
+            detected: DetectedObject {
+                // -1 wraps to NO_CLASS, like the compiled decoder
+                id: self.field("objDetectId", Value::int)? as u64,
+                confidence: self.float("objDetectConf")? as f32,
+            },
+            to_target: TargetTransforms {
+                best: self.transform3d("bestCameraToTarget")?,
+                alt: self.transform3d("altCameraToTarget")?,
+            },
+            ambiguity: self.float("poseAmbiguity")?,
+            area_rect_corners: self.corners("minAreaRectCorners")?,
+            detected_corners: self.corners("detectedCorners")?,
+        })
+    }
+
+    fn multitag(&self) -> Result<MultiTargetPNP, SchemaError> {
+        let pnp = self.nested("estimatedPose", "PnpResult")?;
+
+        Ok(MultiTargetPNP {
+            pnp: PNPResult {
+                best: pnp.transform3d("best")?,
+                alt: pnp.transform3d("alt")?,
+                error: pnp.float("bestReprojErr")?,
+                alt_error: pnp.float("altReprojErr")?,
+                ambiguity: pnp.float("ambiguity")?,
+            },
+            // the ids as a list, or just how many there are
+            num_fiducials: self.field("fiducialIDsUsed", |value| {
+                value.array().map(|ids| ids.len() as i64).or(value.int())
+            })? as u16,
+        })
+    }
+
+    fn result(&self) -> Result<PhotonResult, SchemaError> {
+        let metadata = self.nested("metadata", "PhotonPipelineMetadata")?;
+        let multitag = self.field("multitagResult", |value| match value {
+            Value::Optional(multitag) => Some(multitag.as_deref()),
+            _ => None,
+        })?;
+
+        Ok(PhotonResult {
+            metadata: PhotonPipelineMetadata {
+                seqid: metadata.field("sequenceID", Value::int)? as u64,
+                capture_time: metadata.micros("captureTimestampMicros")?,
+                publish_time: metadata.micros("publishTimestampMicros")?,
+                last_handshake: metadata.micros("timeSinceLastPong")?,
+            },
+            targets: self
+                .list("targets", "PhotonTrackedTarget")?
+                .iter()
+                .map(Decoded::target)
+                .try_collect()?,
+            pnp: multitag
+                .map(|value| {
+                    Decoded {
+                        name: "MultiTargetPNPResult",
+                        value,
+                    }
+                    .multitag()
+                })
+                .transpose()?,
+        })
+    }
+}
+
+/// Every schema we've seen, by type name (`Transform3d`, `PhotonPipelineResult:uuid`)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SchemaRegistry {
+    pub schemas: HashMap<String, Vec<SchemaField>>,
+}
+
+impl SchemaRegistry {
+    /// `topic` can be a whole schema topic or just the type name
+    #[track_caller]
+    pub fn add(&mut self, topic: &str, schema: &str) -> Result<(), SchemaError> {
+        let name = topic.trim_start_matches("/.schema/");
+        let name = name
+            .strip_prefix("photonstruct:")
+            .or(name.strip_prefix("struct:"))
+            .unwrap_or(name);
+
+        self.schemas.insert(name.to_string(), parse(name, schema)?);
+        Ok(())
+    }
+
+    #[track_caller]
+    fn fields(&self, name: &str, depth: usize) -> Result<&[SchemaField], SchemaError> {
+        if depth > MAX_DEPTH {
+            return Err(SchemaError::TooDeep {
+                name: name.into(),
+                location: Location::caller(),
+            });
+        }
+
+        self.schemas
+            .get(name)
+            .map(Vec::as_slice)
+            .ok_or_else(|| SchemaError::UnknownType {
+                name: name.into(),
+                location: Location::caller(),
+            })
+    }
+
+    fn flatten(&self, name: &str, depth: usize, out: &mut Vec<Shape>) -> Result<(), SchemaError> {
+        if let Some(primitive) = Primitive::parse(name) {
+            out.push(Shape::Primitive(primitive));
+            return Ok(());
+        }
+
+        for field in self.fields(name, depth)? {
+            let mut inner = vec![];
+            self.flatten(&field.ty, depth + 1, &mut inner)?;
+
+            match field.count {
+                Count::One => out.extend(inner),
+                Count::Optional => out.push(Shape::Optional(inner)),
+                Count::List => out.push(Shape::List(Primitive::UInt(1), inner)),
+                Count::Fixed(len) => out.push(Shape::Fixed(len, inner)),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// `name` flattened down to primitives
+    pub fn shapes(&self, name: &str) -> Result<Vec<Shape>, SchemaError> {
+        let mut out = vec![];
+        self.flatten(name, 0, &mut out)?;
+        Ok(out)
+    }
+
+    /// Checks the published `name` decodes the same way our `T` does
+    #[track_caller]
+    pub fn validate<T: Shaped>(&self, name: &str) -> Result<(), SchemaError> {
+        let (ours, theirs) = (T::shapes(), self.shapes(name)?);
+        let signless = |shapes: &[Shape]| shapes.iter().map(Shape::signless).collect_vec();
+
+        if signless(&ours) != signless(&theirs) {
+            return Err(SchemaError::Mismatch {
+                name: name.into(),
+                ours: shape_string(&ours).into(),
+                theirs: shape_string(&theirs).into(),
+                location: Location::caller(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Decodes `data` as the published `name`. Trailing bytes are ignored, like
+    /// the compiled decoders do.
+    pub fn decode(&self, name: &str, data: &[u8]) -> Result<Value, SchemaError> {
+        let mut data = data;
+        self.decode_type(name, &mut data, 0)
+    }
+
+    /// Decodes `data` as photon's published result layout `name`, by field
+    /// name rather than position. This is what reads results whose layout
+    /// doesn't match [`PhotonResult`]'s, as long as the fields we use are
+    /// still there.
+    pub fn decode_photon(&self, name: &str, data: &[u8]) -> Result<PhotonResult, SchemaError> {
+        let value = self.decode(name, data)?;
+
+        Decoded {
+            name: "PhotonPipelineResult",
+            value: &value,
+        }
+        .result()
+    }
+
+    #[track_caller]
+    fn take<'a>(
+        &self,
+        name: &str,
+        data: &mut &'a [u8],
+        len: usize,
+    ) -> Result<&'a [u8], SchemaError> {
+        if data.len() < len {
+            return Err(SchemaError::Truncated {
+                name: name.into(),
+                location: Location::caller(),
+            });
+        }
+
+        let (taken, rest) = data.split_at(len);
+        *data = rest;
+        Ok(taken)
+    }
+
+    fn decode_type(
+        &self,
+        name: &str,
+        data: &mut &[u8],
+        depth: usize,
+    ) -> Result<Value, SchemaError> {
+        if let Some(primitive) = Primitive::parse(name) {
+            let bytes = self.take(name, data, primitive.size())?;
+
+            return Ok(match primitive {
+                Primitive::Bool => Value::Bool(bytes[0] != 0),
+                Primitive::Int(1) => Value::Int(bytes[0] as i8 as i64),
+                Primitive::Int(2) => {
+                    Value::Int(i16::from_le_bytes(bytes.try_into().unwrap()) as i64)
+                }
+                Primitive::Int(4) => {
+                    Value::Int(i32::from_le_bytes(bytes.try_into().unwrap()) as i64)
+                }
+                Primitive::Int(_) => Value::Int(i64::from_le_bytes(bytes.try_into().unwrap())),
+                Primitive::UInt(1) => Value::UInt(bytes[0] as u64),
+                Primitive::UInt(2) => {
+                    Value::UInt(u16::from_le_bytes(bytes.try_into().unwrap()) as u64)
+                }
+                Primitive::UInt(4) => {
+                    Value::UInt(u32::from_le_bytes(bytes.try_into().unwrap()) as u64)
+                }
+                Primitive::UInt(_) => Value::UInt(u64::from_le_bytes(bytes.try_into().unwrap())),
+                Primitive::Float(4) => {
+                    Value::Float(f32::from_le_bytes(bytes.try_into().unwrap()) as f64)
+                }
+                Primitive::Float(_) => Value::Float(f64::from_le_bytes(bytes.try_into().unwrap())),
+            });
+        }
+
+        let mut fields = vec![];
+
+        for field in self.fields(name, depth)? {
+            let one = |data: &mut &[u8]| self.decode_type(&field.ty, data, depth + 1);
+
+            let value = match field.count {
+                Count::One => one(data)?,
+                Count::Optional => {
+                    let present = self.take(name, data, 1)?[0] != 0;
+                    Value::Optional(if present {
+                        Some(Box::new(one(data)?))
+                    } else {
+                        None
+                    })
+                }
+                Count::List => {
+                    let len = self.take(name, data, 1)?[0];
+                    Value::Array((0..len).map(|_| one(data)).try_collect()?)
+                }
+                Count::Fixed(len) => Value::Array((0..len).map(|_| one(data)).try_collect()?),
+            };
+
+            fields.push((field.name.clone(), value));
+        }
+
+        Ok(Value::Struct(fields))
+    }
+}
@@ -0,0 +1,140 @@
+//! Writing photon's layout, the inverse of [`Deserialize`]. Pathforger only
+//! ever reads photon's packets, so this exists for the round-trip tests.
+
+use std::panic::Location;
+
+use crate::prelude::*;
+use photon_serde::*;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SerializeError {
+    #[error("At {location}: {len} items is too many for a {prefix} length")]
+    TooLong {
+        len: usize,
+        prefix: &'static str,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: Fiducial id {id} doesn't fit in photon's i32")]
+    FiducialIdTooBig {
+        id: u32,
+        location: &'static Location<'static>,
+    },
+}
+
+/// Fields that are version-gated are always written, skipped ones never are
+pub trait Serialize {
+    fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError>;
+}
+
+pub fn serialize<S: Serialize + ?Sized>(value: &S) -> Result<Vec<u8>, SerializeError> {
+    let mut out = vec![];
+    value.serialize(&mut out)?;
+    Ok(out)
+}
+
+macro_rules! num_type {
+    ($($num:ident),*) => {
+        $(
+            impl Serialize for $num {
+                fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+                    out.extend(self.to_le_bytes());
+                    Ok(())
+                }
+            }
+        )*
+    };
+}
+
+num_type!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+impl Serialize for bool {
+    fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        (*self as u8).serialize(out)
+    }
+}
+
+impl Serialize for Angle {
+    fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        self.get::<radian>().serialize(out)
+    }
+}
+
+impl Serialize for Length {
+    fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        self.get::<meter>().serialize(out)
+    }
+}
+
+/// Whole microseconds, anything finer is dropped
+impl Serialize for Duration {
+    fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        (self.as_micros() as u64).serialize(out)
+    }
+}
+
+impl Serialize for FiducialId {
+    #[track_caller]
+    fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        let value = match self.0 {
+            None => -1,
+            Some(id) => i32::try_from(id).map_err(|_| SerializeError::FiducialIdTooBig {
+                id,
+                location: Location::caller(),
+            })?,
+        };
+
+        value.serialize(out)
+    }
+}
+
+impl<T: Serialize> Serialize for Option<T> {
+    fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        self.is_some().serialize(out)?;
+        match self {
+            Some(inner) => inner.serialize(out),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A list of `T` with an `L` length prefix. Fails if `items` is too long for
+/// `L` to count.
+#[track_caller]
+pub fn serialize_list<L, T>(items: &[T], out: &mut Vec<u8>) -> Result<(), SerializeError>
+where
+    L: Serialize + TryFrom<usize>,
+    T: Serialize,
+{
+    let Ok(len) = L::try_from(items.len()) else {
+        return Err(SerializeError::TooLong {
+            len: items.len(),
+            prefix: std::any::type_name::<L>(),
+            location: Location::caller(),
+        });
+    };
+
+    len.serialize(out)?;
+    for item in items {
+        item.serialize(out)?;
+    }
+
+    Ok(())
+}
+
+impl<T: Serialize> Serialize for Vec<T> {
+    fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        serialize_list::<u8, T>(self, out)
+    }
+}
+
+impl<T: Serialize, const N: usize> Serialize for [T; N] {
+    fn serialize(&self, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        for item in self {
+            item.serialize(out)?;
+        }
+
+        Ok(())
+    }
+}
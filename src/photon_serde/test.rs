@@ -1,4 +1,5 @@
 use super::*;
+use crate::util::fixtures::pose;
 use rand::{rngs::ThreadRng, Rng};
 
 macro_rules! join_bytes {
@@ -320,3 +321,1329 @@ test_for!(photon_result, dummy_photon_result, PhotonResult);
 test_for!(rotate2d, dummy_rotate2d, Rotate2d);
 test_for!(translate2d, dummy_translate2d, Translate2d);
 test_for!(pose2d, dummy_pose2d, Pose2d);
+
+macro_rules! struct_test_for {
+    ($test:ident, $dummy:ident, $ty:ty) => {
+        #[test]
+        fn $test() {
+            let mut rng = rand::thread_rng();
+            let (good, bytes) = $dummy(&mut rng);
+            let test = <$ty as WpiStruct>::decode(&bytes);
+
+            assert!(test.is_ok(), "{:?}", test.err());
+            assert_eq!(good, test.unwrap());
+            assert_eq!(bytes, good.encode());
+        }
+    };
+}
+
+// these layouts happen to match photon's
+struct_test_for!(struct_quaternion, dummy_quaternion, Quaternion);
+struct_test_for!(struct_translate3d, dummy_translate3d, Translate3d);
+struct_test_for!(struct_transform3d, dummy_transform3d, Transform3d);
+struct_test_for!(struct_rotate2d, dummy_rotate2d, Rotate2d);
+struct_test_for!(struct_translate2d, dummy_translate2d, Translate2d);
+struct_test_for!(struct_pose2d, dummy_pose2d, Pose2d);
+
+#[test]
+fn struct_pose3d() {
+    let mut rng = rand::thread_rng();
+    let (transform, bytes) = dummy_transform3d(&mut rng);
+    let pose = Pose3d {
+        translation: transform.translation,
+        rotation: transform.rotation,
+    };
+
+    assert_eq!(Pose3d::decode(&bytes).unwrap(), pose);
+    assert_eq!(pose.encode(), bytes);
+}
+
+#[test]
+fn struct_arrays() {
+    let mut rng = rand::thread_rng();
+    let poses = (0..3).map(|_| dummy_pose2d(&mut rng)).collect_vec();
+    let bytes = poses
+        .iter()
+        .flat_map(|(_, bytes)| bytes.clone())
+        .collect_vec();
+    let poses = poses.into_iter().map(|(pose, _)| pose).collect_vec();
+
+    // no length prefix, just values back to back
+    assert_eq!(bytes.len(), 3 * Pose2d::SIZE);
+    assert_eq!(Pose2d::decode_array(&bytes).unwrap(), poses);
+    assert_eq!(Pose2d::encode_array(&poses), bytes);
+    assert!(Pose2d::decode_array(&[]).unwrap().is_empty());
+    assert!(Pose2d::decode_array(&bytes[1..]).is_err());
+}
+
+#[test]
+fn struct_sizes() {
+    let pose = Pose2d::default().encode();
+
+    assert!(Pose2d::decode(&pose[..Pose2d::SIZE - 1]).is_err());
+    assert!(Pose2d::decode(&[pose.clone(), vec![0]].concat()).is_err());
+    assert_eq!(Transform2d::SIZE, 24);
+    assert_eq!(Pose3d::SIZE, 56);
+}
+
+#[test]
+fn struct_schemas() {
+    assert_eq!(Pose2d::type_string(), "struct:Pose2d");
+    assert_eq!(Pose2d::array_type_string(), "struct:Pose2d[]");
+    assert_eq!(
+        Pose2d::SCHEMA,
+        "Translation2d translation;Rotation2d rotation"
+    );
+
+    // every nested type has its schema published too
+    let names = Pose3d::DEPENDENCIES
+        .iter()
+        .map(|(name, _)| *name)
+        .collect_vec();
+    for name in ["Translation3d", "Rotation3d", "Quaternion"] {
+        assert!(names.contains(&name));
+    }
+}
+
+#[test]
+fn proto_known_bytes() {
+    let pose = pose(1.0, 0.0, 0.0);
+    let bytes = join_bytes!(
+        // translation, field 1, 9 bytes: x as field 1 fixed64, y left out
+        [0x0a, 0x09, 0x09],
+        1.0f64.to_le_bytes(),
+        // rotation, field 2, empty
+        [0x12, 0x00]
+    );
+
+    assert_eq!(pose.to_proto(), bytes);
+    assert_eq!(Pose2d::from_proto(&bytes).unwrap(), pose);
+    assert_eq!(
+        Pose2d::proto_type_string(),
+        "proto:wpi.proto.ProtobufPose2d"
+    );
+}
+
+#[test]
+fn proto_roundtrip() {
+    let mut rng = rand::thread_rng();
+    let (pose, _) = dummy_pose2d(&mut rng);
+    let (transform, _) = dummy_transform3d(&mut rng);
+    let pose3d = Pose3d {
+        translation: transform.translation,
+        rotation: transform.rotation,
+    };
+
+    assert_eq!(Pose2d::from_proto(&pose.to_proto()).unwrap(), pose);
+    assert_eq!(
+        Transform3d::from_proto(&transform.to_proto()).unwrap(),
+        transform
+    );
+    assert_eq!(Pose3d::from_proto(&pose3d.to_proto()).unwrap(), pose3d);
+}
+
+#[test]
+fn proto_lenient_reads() {
+    // unknown varint field 7 and fixed32 field 8, then y
+    let bytes = join_bytes!(
+        [0x38, 0x96, 0x01, 0x45, 0, 0, 0, 0, 0x11],
+        2.5f64.to_le_bytes()
+    );
+    let translation = Translate2d::from_proto(&bytes).unwrap();
+
+    assert_eq!(translation.x, Length::default());
+    assert_eq!(translation.y, Length::new::<meter>(2.5));
+
+    // an empty rotation is no rotation
+    assert_eq!(
+        Transform3d::from_proto(&[]).unwrap().rotation,
+        Quaternion::IDENTITY
+    );
+}
+
+#[test]
+fn proto_bad_input() {
+    let good = pose(1.0, 2.0, 170.0).to_proto();
+
+    assert!(Pose2d::from_proto(&good[..good.len() - 1]).is_err());
+    // x as a varint instead of a double
+    assert!(Translate2d::from_proto(&[0x08, 0x01]).is_err());
+    // wire type 3 (start group) isn't supported
+    assert!(Translate2d::from_proto(&[0x0b]).is_err());
+}
+
+#[test]
+fn proto_trajectory() {
+    use crate::planner::trajectory::{Trajectory, TrajectoryState};
+
+    // driving along +y, facing the way we're going
+    let states = (0..3)
+        .map(|i| TrajectoryState {
+            time: Time::new::<second>(i as f64 * 0.5),
+            pose: pose(0.0, i as f64, 90.0),
+            velocity: (Velocity::default(), Velocity::new::<mps>(2.0)),
+            acceleration: (Acceleration::default(), Acceleration::new::<mps2>(1.0)),
+            angular_velocity: AngularVelocity::default(),
+            curvature: 0.0,
+        })
+        .collect_vec();
+    let trajectory = Trajectory { states };
+
+    let decoded = Trajectory::from_proto(&trajectory.to_proto()).unwrap();
+    assert_eq!(decoded.states.len(), 3);
+
+    for (a, b) in trajectory.states.iter().zip(&decoded.states) {
+        assert_eq!(a.time, b.time);
+        assert_eq!(a.pose, b.pose);
+        assert!((a.velocity.1 - b.velocity.1).get::<mps>().abs() < 1e-9);
+        assert!(b.velocity.0.get::<mps>().abs() < 1e-9);
+        assert!((a.acceleration.1 - b.acceleration.1).get::<mps2>().abs() < 1e-9);
+    }
+}
+
+/// The length-delimited fields numbered `number` of a message
+fn proto_messages(data: &[u8], number: u32) -> Vec<Vec<u8>> {
+    use crate::photon_serde::protobuf::{Reader, WireValue};
+
+    let mut reader = Reader::new("descriptor", data);
+    let mut messages = vec![];
+    while let Some((field, value)) = reader.field().unwrap() {
+        if let (true, WireValue::Len(bytes)) = (field == number, value) {
+            messages.push(bytes.to_vec());
+        }
+    }
+    messages
+}
+
+fn proto_strings(data: &[u8], number: u32) -> Vec<String> {
+    proto_messages(data, number)
+        .into_iter()
+        .map(|bytes| String::from_utf8(bytes).unwrap())
+        .collect()
+}
+
+#[test]
+fn proto_file_descriptors() {
+    use crate::photon_serde::protobuf::file_descriptors;
+    use crate::planner::trajectory::{Trajectory, TrajectoryState};
+
+    let files = file_descriptors();
+    let [(geometry, geometry_bytes), (trajectory, trajectory_bytes)] = &files[..] else {
+        panic!("expected two files, got {}", files.len());
+    };
+    assert_eq!(*geometry, "geometry2d.proto");
+    assert_eq!(*trajectory, "trajectory.proto");
+
+    for (file, bytes) in [(geometry, geometry_bytes), (trajectory, trajectory_bytes)] {
+        assert_eq!(proto_strings(bytes, 1), [*file]);
+        assert_eq!(proto_strings(bytes, 2), ["wpi.proto"]);
+        assert_eq!(proto_strings(bytes, 12), ["proto3"]);
+    }
+    assert_eq!(proto_strings(trajectory_bytes, 3), ["geometry2d.proto"]);
+
+    // every message we encode is declared under the name we announce it with
+    let declared = [geometry_bytes, trajectory_bytes]
+        .into_iter()
+        .flat_map(|bytes| proto_messages(bytes, 4))
+        .map(|message| format!("wpi.proto.{}", proto_strings(&message, 1)[0]))
+        .collect_vec();
+    for message in [
+        Translate2d::MESSAGE,
+        Rotate2d::MESSAGE,
+        Pose2d::MESSAGE,
+        Transform2d::MESSAGE,
+        TrajectoryState::MESSAGE,
+        Trajectory::MESSAGE,
+    ] {
+        assert!(declared.iter().any(|name| name == message), "{message}");
+    }
+
+    // ProtobufTrajectory { repeated ProtobufTrajectoryState states = 2; }
+    let trajectory_message = proto_messages(trajectory_bytes, 4).pop().unwrap();
+    let [states] = &proto_messages(&trajectory_message, 2)[..] else {
+        panic!("ProtobufTrajectory should have one field");
+    };
+    let states = states.as_slice();
+    assert_eq!(proto_strings(states, 1), ["states"]);
+    assert_eq!(
+        proto_strings(states, 6),
+        [".wpi.proto.ProtobufTrajectoryState"]
+    );
+    // number 2, label repeated, type message
+    assert!(states.windows(6).any(|w| w == [0x18, 2, 0x20, 3, 0x28, 11]));
+}
+
+fn photon_registry(object_id: &str) -> SchemaRegistry {
+    let mut registry = SchemaRegistry::default();
+    for (topic, schema) in [
+        ("/.schema/struct:Translation3d", "double x;double y;double z"),
+        ("/.schema/struct:Quaternion", "double w;double x;double y;double z"),
+        ("/.schema/struct:Rotation3d", "Quaternion q"),
+        (
+            "/.schema/struct:Transform3d",
+            "Translation3d translation;Rotation3d rotation",
+        ),
+        ("/.schema/photonstruct:TargetCorner:c1", "float64 x;float64 y"),
+        (
+            "/.schema/photonstruct:PhotonPipelineMetadata:m1",
+            "int64 sequenceID;int64 captureTimestampMicros;int64 publishTimestampMicros;int64 timeSinceLastPong",
+        ),
+        (
+            "/.schema/photonstruct:PhotonTrackedTarget:t1",
+            &format!(
+                "float64 yaw;float64 pitch;float64 area;float64 skew;int32 fiducialId;\
+                 {object_id} objDetectId;float32 objDetectConf;Transform3d bestCameraToTarget;\
+                 Transform3d altCameraToTarget;float64 poseAmbiguity;\
+                 TargetCorner:c1[?] minAreaRectCorners;TargetCorner:c1 detectedCorners[?]"
+            ),
+        ),
+        (
+            "/.schema/photonstruct:PnpResult:p1",
+            "Transform3d best;Transform3d alt;float64 bestReprojErr;float64 altReprojErr;float64 ambiguity",
+        ),
+        (
+            "/.schema/photonstruct:MultiTargetPNPResult:n1",
+            "PnpResult:p1 estimatedPose;int16 fiducialIDsUsed",
+        ),
+        (
+            "/.schema/photonstruct:PhotonPipelineResult:r1",
+            "PhotonPipelineMetadata:m1 metadata;PhotonTrackedTarget:t1[?] targets;\
+             optional MultiTargetPNPResult:n1 multitagResult",
+        ),
+    ] {
+        registry.add(topic, schema).unwrap();
+    }
+    registry
+}
+
+#[test]
+fn schema_parse() {
+    let fields = schema::parse(
+        "Test",
+        " double a ; optional Foo:abc b;int8 c[4];Bar[?] d;uint16 e[?]",
+    )
+    .unwrap();
+
+    let counts = fields.iter().map(|field| field.count.clone()).collect_vec();
+    assert_eq!(
+        counts,
+        [
+            schema::Count::One,
+            schema::Count::Optional,
+            schema::Count::Fixed(4),
+            schema::Count::List,
+            schema::Count::List
+        ]
+    );
+    assert_eq!(fields[1].ty, "Foo:abc");
+    assert_eq!(fields[3].name, "d");
+
+    for bad in ["enum {a=1} int8 x", "int8 x:3", "double", "int8 x[y]"] {
+        assert!(schema::parse("Bad", bad).is_err(), "{bad}");
+    }
+}
+
+#[test]
+fn schema_validate() {
+    photon_registry("int64")
+        .validate::<PhotonResult>("PhotonPipelineResult:r1")
+        .unwrap();
+    photon_registry("int64")
+        .validate::<Transform3d>("Transform3d")
+        .unwrap();
+
+    let err = photon_registry("int32")
+        .validate::<PhotonResult>("PhotonPipelineResult:r1")
+        .unwrap_err();
+    assert!(matches!(err, SchemaError::Mismatch { .. }), "{err}");
+
+    let err = SchemaRegistry::default()
+        .validate::<PhotonResult>("PhotonPipelineResult:r1")
+        .unwrap_err();
+    assert!(matches!(err, SchemaError::UnknownType { .. }), "{err}");
+
+    let mut looped = SchemaRegistry::default();
+    looped.add("Loop", "Loop inner").unwrap();
+    assert!(matches!(
+        looped.shapes("Loop"),
+        Err(SchemaError::TooDeep { .. })
+    ));
+}
+
+#[test]
+fn schema_dynamic_decode() {
+    let mut registry = SchemaRegistry::default();
+    registry
+        .add(
+            "Thing",
+            "int16 id;float a;optional double b;bool flags[2];uint8 list[?]",
+        )
+        .unwrap();
+
+    let bytes = join_bytes!(
+        (-2i16).to_le_bytes(),
+        1.5f32.to_le_bytes(),
+        [1],
+        2.25f64.to_le_bytes(),
+        [1, 0],
+        [2, 7, 200]
+    );
+
+    let value = registry.decode("Thing", &bytes).unwrap();
+    assert_eq!(value.get("id"), Some(&schema::Value::Int(-2)));
+    assert_eq!(value.get("a"), Some(&schema::Value::Float(1.5)));
+    assert_eq!(
+        value.get("b"),
+        Some(&schema::Value::Optional(Some(Box::new(
+            schema::Value::Float(2.25)
+        ))))
+    );
+    assert_eq!(
+        value.get("flags"),
+        Some(&schema::Value::Array(vec![
+            schema::Value::Bool(true),
+            schema::Value::Bool(false)
+        ]))
+    );
+    assert_eq!(
+        value.get("list"),
+        Some(&schema::Value::Array(vec![
+            schema::Value::UInt(7),
+            schema::Value::UInt(200)
+        ]))
+    );
+
+    assert!(matches!(
+        registry.decode("Thing", &bytes[..bytes.len() - 1]),
+        Err(SchemaError::Truncated { .. })
+    ));
+}
+
+#[test]
+fn schema_decode_photon() {
+    let rng = &mut rand::thread_rng();
+    let registry = photon_registry("int64");
+
+    for _ in 0..100 {
+        let (result, bytes) = dummy_photon_result(rng);
+        assert_eq!(
+            registry
+                .decode_photon("PhotonPipelineResult:r1", &bytes)
+                .unwrap(),
+            result
+        );
+    }
+
+    // only -1 means no tag, in both decoders
+    let (_, mut bytes) = dummy_photon_result(rng);
+    // after the metadata, the target count, and yaw, pitch, area and skew
+    bytes[65..69].copy_from_slice(&(-5i32).to_le_bytes());
+    assert!(matches!(
+        registry.decode_photon("PhotonPipelineResult:r1", &bytes),
+        Err(SchemaError::MissingField {
+            field: "fiducialId",
+            ..
+        })
+    ));
+    assert!(matches!(
+        deserialize::<PhotonResult>(&bytes),
+        Err(DeserializeError::NegativeFiducialId { value: -5, .. })
+    ));
+
+    // a layout without the fields we read
+    let mut registry = SchemaRegistry::default();
+    registry.add("Other", "int64 metadata").unwrap();
+    assert!(matches!(
+        registry.decode_photon("Other", &[0; 8]),
+        Err(SchemaError::MissingField { .. })
+    ));
+}
+
+/// A target in the 2023/2024 layouts, which had no object detection fields.
+/// 2023 always sent four min area rect corners with no length prefix.
+fn dummy_legacy_target(rng: &mut ThreadRng, v2023: bool) -> (PhotonTrackedTarget, Vec<u8>) {
+    let yaw: f64 = rng.gen();
+    let pitch: f64 = rng.gen();
+    let area: f64 = rng.gen();
+    let skew: f64 = rng.gen();
+    let (fiducial_id, fiducial_bytes) = dummy_fiducial_id(rng);
+    let (to_target, transforms_bytes) = dummy_target_transforms(rng);
+    let ambiguity: f64 = rng.gen();
+    let (area_rect_corners, mut area_corners_bytes) = dummy_vec(4, dummy_target_corner, rng);
+    let (detected_corners, detected_corners_bytes) = dummy_vec(3, dummy_target_corner, rng);
+
+    if v2023 {
+        area_corners_bytes.remove(0);
+    }
+
+    let bytes = join_bytes!(
+        yaw.to_le_bytes(),
+        pitch.to_le_bytes(),
+        area.to_le_bytes(),
+        skew.to_le_bytes(),
+        fiducial_bytes,
+        transforms_bytes,
+        ambiguity.to_le_bytes(),
+        area_corners_bytes,
+        detected_corners_bytes
+    );
+
+    (
+        PhotonTrackedTarget {
+            yaw,
+            pitch,
+            area,
+            skew,
+            fiducial_id,
+            detected: DetectedObject {
+                id: NO_CLASS,
+                confidence: 0.0,
+            },
+            to_target,
+            ambiguity,
+            area_rect_corners,
+            detected_corners,
+        },
+        bytes,
+    )
+}
+
+#[test]
+fn version_2023() {
+    let rng = &mut rand::thread_rng();
+    let (a, a_bytes) = dummy_legacy_target(rng, true);
+    let (b, b_bytes) = dummy_legacy_target(rng, true);
+    let bytes = join_bytes!(25.0f64.to_le_bytes(), [2], a_bytes, b_bytes);
+
+    let received = Duration::from_secs(10);
+    let result = PhotonVersion::V2023.decode(&bytes, received).unwrap();
+
+    assert_eq!(result.targets, [a, b]);
+    assert_eq!(result.pnp, None);
+    assert_eq!(result.metadata.publish_time, received);
+    assert_eq!(
+        result.metadata.capture_time,
+        received - Duration::from_millis(25)
+    );
+
+    assert_eq!(
+        PhotonVersion::detect("rawBytes", &bytes),
+        Some(PhotonVersion::V2023)
+    );
+}
+
+#[test]
+fn version_2024() {
+    let rng = &mut rand::thread_rng();
+    let (target, target_bytes) = dummy_legacy_target(rng, false);
+    let (pnp, pnp_bytes) = dummy_pnp_result(rng);
+    let ids = (0..32i16)
+        .flat_map(|i| if i < 3 { i } else { -1 }.to_le_bytes())
+        .collect_vec();
+    let bytes = join_bytes!(5.0f64.to_le_bytes(), [1], target_bytes, [1], pnp_bytes, ids);
+
+    let result = PhotonVersion::V2024
+        .decode(&bytes, Duration::from_secs(1))
+        .unwrap();
+
+    assert_eq!(result.targets, [target]);
+    assert_eq!(
+        result.pnp,
+        Some(MultiTargetPNP {
+            pnp,
+            num_fiducials: 3
+        })
+    );
+
+    assert_eq!(
+        PhotonVersion::detect("rawBytes", &bytes),
+        Some(PhotonVersion::V2024)
+    );
+    assert!(!PhotonVersion::V2023.fits(&bytes));
+    assert_eq!(PhotonVersion::detect("rawBytes", &bytes[1..]), None);
+}
+
+#[test]
+fn version_select() {
+    let rng = &mut rand::thread_rng();
+    let (result, bytes) = dummy_photon_result(rng);
+
+    assert_eq!(
+        PhotonVersion::detect("photonstruct:PhotonPipelineResult:abc", &[]),
+        Some(PhotonVersion::V2025)
+    );
+    assert_eq!(
+        PhotonVersion::V2025.decode(&bytes, Duration::ZERO).unwrap(),
+        result
+    );
+    assert!(matches!(
+        PhotonVersion::V2025.decode(&bytes[..bytes.len() - 1], Duration::ZERO),
+        Err(DeserializeError::Truncated { .. })
+    ));
+
+    assert_eq!(
+        "2024".parse::<PhotonVersion>().unwrap(),
+        PhotonVersion::V2024
+    );
+    assert_eq!(
+        "v2023".parse::<PhotonVersion>().unwrap(),
+        PhotonVersion::V2023
+    );
+    assert!("2022".parse::<PhotonVersion>().is_err());
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PhotonSerde)]
+#[photon(repr = u8)]
+enum Led {
+    Off = 0,
+    On = 1,
+    Blink = 4,
+}
+
+/// Reads a `u16` of centimeters
+mod centimeters {
+    use super::*;
+
+    pub fn deserialize(data: &mut Cursor<&[u8]>) -> Result<Length, DeserializeError> {
+        Ok(Length::new::<meter>(u16::deserialize(data)? as f64 / 100.0))
+    }
+
+    pub fn serialize(height: &Length, out: &mut Vec<u8>) -> Result<(), SerializeError> {
+        ((height.get::<meter>() * 100.0).round() as u16).serialize(out)
+    }
+
+    pub fn shape(out: &mut Vec<schema::Shape>) {
+        u16::shape(out);
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, PhotonSerde, FloatHash)]
+struct Derived<T> {
+    id: T,
+    #[photon(len = u16)]
+    corners: Vec<TargetCorner>,
+    #[photon(with = centimeters)]
+    height: Length,
+    #[photon(version >= 2025, default = 7)]
+    added: u32,
+    #[photon(skip)]
+    cache: Option<u8>,
+    yaw: f64,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PhotonSerde)]
+struct Tuple(Led, [u8; 2]);
+
+/// Reads a count that was a `u8` before it was widened
+mod narrow_count {
+    use super::*;
+
+    pub fn deserialize(data: &mut Cursor<&[u8]>, _version: u32) -> Result<u32, DeserializeError> {
+        Ok(u8::deserialize(data)? as u32)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PhotonSerde)]
+struct Widened {
+    #[photon(version >= 2025, legacy = narrow_count)]
+    count: u32,
+    flag: bool,
+}
+
+#[test]
+fn derive_attributes() {
+    let rng = &mut rand::thread_rng();
+    let (corner, corner_bytes) = dummy_target_corner(rng);
+    let head = join_bytes!(
+        9u64.to_le_bytes(),
+        1u16.to_le_bytes(),
+        corner_bytes,
+        150u16.to_le_bytes()
+    );
+
+    let bytes = join_bytes!(head, 3u32.to_le_bytes(), 0.5f64.to_le_bytes());
+    let derived = deserialize::<Derived<u64>>(&bytes).unwrap();
+    assert_eq!(
+        derived,
+        Derived {
+            id: 9,
+            corners: vec![corner],
+            height: Length::new::<meter>(1.5),
+            added: 3,
+            cache: None,
+            yaw: 0.5,
+        }
+    );
+
+    let old_bytes = join_bytes!(head, 0.5f64.to_le_bytes());
+    let old = deserialize_version::<Derived<u64>>(&old_bytes, 2024).unwrap();
+    assert_eq!(
+        old,
+        Derived {
+            added: 7,
+            ..derived
+        }
+    );
+
+    assert_eq!(
+        schema::shape_string(&Derived::<u64>::shapes()),
+        "uint64 [?uint16](float64 float64) uint16 uint32 float64"
+    );
+
+    assert_eq!(
+        deserialize::<Tuple>(&[4, 1, 2]).unwrap(),
+        Tuple(Led::Blink, [1, 2])
+    );
+    let widened = Widened {
+        count: 5,
+        flag: true,
+    };
+    assert_eq!(deserialize::<Widened>(&[5, 0, 0, 0, 1]).unwrap(), widened);
+    assert_eq!(
+        deserialize_version::<Widened>(&[5, 1], 2024).unwrap(),
+        widened
+    );
+    assert_eq!(serialize(&widened).unwrap(), [5, 0, 0, 0, 1]);
+
+    assert_eq!(deserialize::<Led>(&[1]).unwrap(), Led::On);
+    assert_eq!(deserialize::<Led>(&[0]).unwrap(), Led::Off);
+    assert!(matches!(
+        deserialize::<Led>(&[2]),
+        Err(DeserializeError::BadDiscriminant { value: 2, .. })
+    ));
+}
+
+#[test]
+fn float_hash() {
+    use std::hash::{BuildHasher, RandomState};
+
+    let state = RandomState::new();
+    let corner = |x, y| state.hash_one(TargetCorner { x, y });
+
+    assert_eq!(corner(f64::NAN, 1.0), corner(-f64::NAN, 1.0));
+    assert_eq!(corner(0.0, 1.0), corner(-0.0, 1.0));
+    assert_ne!(corner(1.0, 2.0), corner(2.0, 1.0));
+}
+
+#[test]
+fn view_matches_owned() {
+    let rng = &mut rand::thread_rng();
+    let (result, bytes) = dummy_photon_result(rng);
+
+    let view = PhotonResultView::new(&bytes).unwrap();
+    assert_eq!(view.len(), result.targets.len());
+    assert_eq!(view.to_owned(), result);
+
+    let target = view.targets().next().unwrap();
+    assert_eq!(target.yaw(), result.targets[0].yaw);
+    assert_eq!(target.to_target(), result.targets[0].to_target);
+    assert_eq!(target.detected_corners().len(), 4);
+    assert!(target
+        .area_rect_corners()
+        .eq(result.targets[0].area_rect_corners.iter().copied()));
+
+    for len in [0, 32, 40, bytes.len() - 1] {
+        assert!(matches!(
+            PhotonResultView::new(&bytes[..len]),
+            Err(DeserializeError::Truncated { .. })
+        ));
+    }
+}
+
+#[test]
+fn deserialize_into_reuses() {
+    let rng = &mut rand::thread_rng();
+    let (first, first_bytes) = dummy_photon_result(rng);
+    let (next, next_bytes) = dummy_photon_result(rng);
+
+    let mut out = deserialize::<PhotonResult>(&first_bytes).unwrap();
+    assert_eq!(out, first);
+
+    let targets = out.targets.as_ptr();
+    let corners = out.targets[0].detected_corners.as_ptr();
+
+    deserialize_into(&next_bytes, &mut out).unwrap();
+    assert_eq!(out, next);
+    assert_eq!(out.targets.as_ptr(), targets);
+    assert_eq!(out.targets[0].detected_corners.as_ptr(), corners);
+}
+
+mod properties {
+    use super::*;
+    use proptest::{collection::vec, prelude::*, test_runner::TestCaseError};
+    use std::{
+        fmt::Debug,
+        hash::{DefaultHasher, Hash, Hasher},
+    };
+
+    /// Any float, NaNs and infinities included
+    fn float() -> impl Strategy<Value = f64> {
+        proptest::num::f64::ANY
+    }
+
+    /// Empty, full (255, the most a `u8` length can count) or a few
+    fn edge_vec<T: Debug + 'static>(item: BoxedStrategy<T>) -> impl Strategy<Value = Vec<T>> {
+        prop_oneof![Just(0), Just(255), 1..6usize].prop_flat_map(move |len| vec(item.clone(), len))
+    }
+
+    fn quaternion() -> BoxedStrategy<Quaternion> {
+        (float(), float(), float(), float())
+            .prop_map(|(w, x, y, z)| Quaternion { w, x, y, z })
+            .boxed()
+    }
+
+    fn translate3d() -> BoxedStrategy<Translate3d> {
+        (float(), float(), float())
+            .prop_map(|(x, y, z)| Translate3d { x, y, z })
+            .boxed()
+    }
+
+    fn transform3d() -> BoxedStrategy<Transform3d> {
+        (translate3d(), quaternion())
+            .prop_map(|(translation, rotation)| Transform3d {
+                translation,
+                rotation,
+            })
+            .boxed()
+    }
+
+    fn fiducial_id() -> BoxedStrategy<FiducialId> {
+        prop_oneof![Just(None), (0..=i32::MAX as u32).prop_map(Some)]
+            .prop_map(FiducialId)
+            .boxed()
+    }
+
+    fn detected_object() -> BoxedStrategy<DetectedObject> {
+        (any::<u64>(), proptest::num::f32::ANY)
+            .prop_map(|(id, confidence)| DetectedObject { id, confidence })
+            .boxed()
+    }
+
+    fn target_transforms() -> BoxedStrategy<TargetTransforms> {
+        (transform3d(), transform3d())
+            .prop_map(|(best, alt)| TargetTransforms { best, alt })
+            .boxed()
+    }
+
+    fn target_corner() -> BoxedStrategy<TargetCorner> {
+        (float(), float())
+            .prop_map(|(x, y)| TargetCorner { x, y })
+            .boxed()
+    }
+
+    fn pnp_result() -> BoxedStrategy<PNPResult> {
+        (transform3d(), transform3d(), float(), float(), float())
+            .prop_map(|(best, alt, error, alt_error, ambiguity)| PNPResult {
+                best,
+                alt,
+                error,
+                alt_error,
+                ambiguity,
+            })
+            .boxed()
+    }
+
+    fn micros() -> impl Strategy<Value = Duration> {
+        any::<u64>().prop_map(Duration::from_micros)
+    }
+
+    fn pipeline_metadata() -> BoxedStrategy<PhotonPipelineMetadata> {
+        (any::<u64>(), micros(), micros(), micros())
+            .prop_map(|(seqid, capture_time, publish_time, last_handshake)| {
+                PhotonPipelineMetadata {
+                    seqid,
+                    capture_time,
+                    publish_time,
+                    last_handshake,
+                }
+            })
+            .boxed()
+    }
+
+    fn tracked_target() -> BoxedStrategy<PhotonTrackedTarget> {
+        (
+            (float(), float(), float(), float()),
+            fiducial_id(),
+            detected_object(),
+            target_transforms(),
+            float(),
+            edge_vec(target_corner()),
+            edge_vec(target_corner()),
+        )
+            .prop_map(
+                |(
+                    (yaw, pitch, area, skew),
+                    fiducial_id,
+                    detected,
+                    to_target,
+                    ambiguity,
+                    area_rect_corners,
+                    detected_corners,
+                )| PhotonTrackedTarget {
+                    yaw,
+                    pitch,
+                    area,
+                    skew,
+                    fiducial_id,
+                    detected,
+                    to_target,
+                    ambiguity,
+                    area_rect_corners,
+                    detected_corners,
+                },
+            )
+            .boxed()
+    }
+
+    fn multi_target_pnp() -> BoxedStrategy<MultiTargetPNP> {
+        (pnp_result(), any::<u16>())
+            .prop_map(|(pnp, num_fiducials)| MultiTargetPNP { pnp, num_fiducials })
+            .boxed()
+    }
+
+    /// Targets keep to a few corners here, 255 targets of 255 corners each takes
+    /// proptest a long time to generate
+    fn photon_result() -> BoxedStrategy<PhotonResult> {
+        let target = (
+            (float(), float(), float(), float()),
+            fiducial_id(),
+            detected_object(),
+            target_transforms(),
+            float(),
+            vec(target_corner(), 0..=4),
+            vec(target_corner(), 0..=4),
+        )
+            .prop_map(
+                |(
+                    (yaw, pitch, area, skew),
+                    fiducial_id,
+                    detected,
+                    to_target,
+                    ambiguity,
+                    area_rect_corners,
+                    detected_corners,
+                )| PhotonTrackedTarget {
+                    yaw,
+                    pitch,
+                    area,
+                    skew,
+                    fiducial_id,
+                    detected,
+                    to_target,
+                    ambiguity,
+                    area_rect_corners,
+                    detected_corners,
+                },
+            );
+
+        (
+            pipeline_metadata(),
+            edge_vec(target.boxed()),
+            proptest::option::of(multi_target_pnp()),
+        )
+            .prop_map(|(metadata, targets, pnp)| PhotonResult {
+                metadata,
+                targets,
+                pnp,
+            })
+            .boxed()
+    }
+
+    fn translate2d() -> BoxedStrategy<Translate2d> {
+        (float(), float())
+            .prop_map(|(x, y)| Translate2d {
+                x: Length::new::<meter>(x),
+                y: Length::new::<meter>(y),
+            })
+            .boxed()
+    }
+
+    fn rotate2d() -> BoxedStrategy<Rotate2d> {
+        float()
+            .prop_map(|angle| Rotate2d {
+                angle: Angle::new::<radian>(angle),
+            })
+            .boxed()
+    }
+
+    fn hash<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Re-encoding what was decoded gives the same bytes, which (unlike `==`)
+    /// holds for NaNs too. Hashes have to match regardless. Cutting any bytes off
+    /// the end has to fail cleanly.
+    fn roundtrip<T>(value: &T) -> Result<(), TestCaseError>
+    where
+        T: Serialize + Deserialize + Hash + Debug,
+    {
+        let fail = |err: &dyn std::error::Error| TestCaseError::fail(err.to_string());
+        let bytes = serialize(value).map_err(|err| fail(&err))?;
+        let decoded = deserialize::<T>(&bytes).map_err(|err| fail(&err))?;
+
+        prop_assert_eq!(
+            serialize(&decoded).map_err(|err| fail(&err))?,
+            bytes.clone()
+        );
+        prop_assert_eq!(hash(&decoded), hash(value));
+
+        for cut in [0, bytes.len() / 2, bytes.len().saturating_sub(1)] {
+            if cut < bytes.len() {
+                prop_assert!(deserialize::<T>(&bytes[..cut]).is_err());
+            }
+        }
+
+        Ok(())
+    }
+
+    macro_rules! roundtrips {
+        ($($name:ident: $strategy:expr),* $(,)?) => {
+            proptest! {
+                $(
+                    #[test]
+                    fn $name(value in $strategy) {
+                        roundtrip(&value)?;
+                    }
+                )*
+            }
+        };
+    }
+
+    roundtrips! {
+        roundtrip_quaternion: quaternion(),
+        roundtrip_translate3d: translate3d(),
+        roundtrip_transform3d: transform3d(),
+        roundtrip_fiducial_id: fiducial_id(),
+        roundtrip_detected_object: detected_object(),
+        roundtrip_target_transforms: target_transforms(),
+        roundtrip_target_corner: target_corner(),
+        roundtrip_pnp_result: pnp_result(),
+        roundtrip_pipeline_metadata: pipeline_metadata(),
+        roundtrip_tracked_target: tracked_target(),
+        roundtrip_multi_target_pnp: multi_target_pnp(),
+        roundtrip_translate2d: translate2d(),
+        roundtrip_rotate2d: rotate2d(),
+        roundtrip_pose2d: (translate2d(), rotate2d())
+            .prop_map(|(translate, rotate)| Pose2d { translate, rotate }),
+        roundtrip_transform2d: (translate2d(), rotate2d())
+            .prop_map(|(translate, rotate)| Transform2d { translate, rotate }),
+        roundtrip_pose3d: (translate3d(), quaternion())
+            .prop_map(|(translation, rotation)| Pose3d { translation, rotation }),
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(64))]
+
+        #[test]
+        fn roundtrip_photon_result(value in photon_result()) {
+            roundtrip(&value)?;
+
+            let bytes = serialize(&value).unwrap();
+            let view = PhotonResultView::new(&bytes).unwrap();
+            prop_assert_eq!(serialize(&view.to_owned()).unwrap(), bytes.clone());
+        }
+
+        #[test]
+        fn deserialize_into_any(first in photon_result(), next in photon_result()) {
+            let mut out = first;
+            let bytes = serialize(&next).unwrap();
+            deserialize_into(&bytes, &mut out).unwrap();
+            prop_assert_eq!(serialize(&out).unwrap(), bytes);
+        }
+    }
+
+    proptest! {
+        /// Only -1 means no tag. Anything else negative fails to decode rather
+        /// than wrapping into a huge tag id.
+        #[test]
+        fn negative_fiducial_ids(id in i32::MIN..-1) {
+            let rejected = matches!(
+                deserialize::<FiducialId>(&id.to_le_bytes()),
+                Err(DeserializeError::NegativeFiducialId { value, .. }) if value == id
+            );
+            prop_assert!(rejected);
+        }
+
+        #[test]
+        fn nan_hashes_agree(payload in 1..(1u64 << 52), sign: bool) {
+            let nan = f64::from_bits(0x7ff0_0000_0000_0000 | payload | (sign as u64) << 63);
+            prop_assert!(nan.is_nan());
+
+            let corner = |x| TargetCorner { x, y: 1.0 };
+            prop_assert_eq!(hash(&corner(nan)), hash(&corner(f64::NAN)));
+        }
+
+        /// The same checks as the fuzz target, on a smaller budget
+        #[test]
+        fn arbitrary_bytes(bytes in vec(any::<u8>(), 0..1024)) {
+            let _ = deserialize::<PhotonResult>(&bytes);
+
+            if let Ok(view) = PhotonResultView::new(&bytes) {
+                for target in view.targets() {
+                    let _ = target.to_owned();
+                }
+            }
+
+            for version in PhotonVersion::ALL {
+                let _ = version.decode(&bytes, Duration::ZERO);
+            }
+        }
+    }
+}
+
+/// Set to rewrite each fixture's `.json` from what it decodes to, instead of
+/// checking against it
+const REGENERATE_GOLDENS: &str = "PATHFORGER_REGENERATE_GOLDENS";
+
+/// When the fixtures are taken to have arrived, which older layouts work their
+/// capture time back from
+const FIXTURE_RECEIVED: Duration = Duration::from_secs(100);
+
+/// `fixtures/photon` at the crate root
+fn fixtures_dir() -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/photon")
+}
+
+fn golden_json(result: &PhotonResult) -> serde_json::Value {
+    use serde_json::json;
+
+    let transform = |transform: &Transform3d| {
+        json!({
+            "translation": transform.translation.to_array(),
+            "rotation": [
+                transform.rotation.w,
+                transform.rotation.x,
+                transform.rotation.y,
+                transform.rotation.z
+            ],
+        })
+    };
+    let corners = |corners: &[TargetCorner]| {
+        corners
+            .iter()
+            .map(|corner| [corner.x, corner.y])
+            .collect_vec()
+    };
+
+    json!({
+        "metadata": {
+            "seqid": result.metadata.seqid,
+            "capture_micros": result.metadata.capture_time.as_micros() as u64,
+            "publish_micros": result.metadata.publish_time.as_micros() as u64,
+            "last_handshake_micros": result.metadata.last_handshake.as_micros() as u64,
+        },
+        "targets": result.targets.iter().map(|target| json!({
+            "yaw": target.yaw,
+            "pitch": target.pitch,
+            "area": target.area,
+            "skew": target.skew,
+            "fiducial_id": target.fiducial_id.0,
+            "class": (target.detected.id != NO_CLASS).then_some(target.detected.id),
+            "confidence": target.detected.confidence,
+            "best": transform(&target.to_target.best),
+            "alt": transform(&target.to_target.alt),
+            "ambiguity": target.ambiguity,
+            "area_rect_corners": corners(&target.area_rect_corners),
+            "detected_corners": corners(&target.detected_corners),
+        })).collect_vec(),
+        "multi_tag": result.pnp.map(|multi| json!({
+            "best": transform(&multi.pnp.best),
+            "alt": transform(&multi.pnp.alt),
+            "error": multi.pnp.error,
+            "alt_error": multi.pnp.alt_error,
+            "ambiguity": multi.pnp.ambiguity,
+            "num_fiducials": multi.num_fiducials,
+        })),
+    })
+}
+
+/// Decodes every `fixtures/photon/<version>/<name>.bin` with that version's
+/// layout and compares it to `<name>.json`
+#[test]
+fn golden_fixtures() {
+    let regenerate = std::env::var_os(REGENERATE_GOLDENS).is_some();
+    let mut checked = 0;
+    let mut failures = vec![];
+
+    for dir in std::fs::read_dir(fixtures_dir()).unwrap() {
+        let dir = dir.unwrap().path();
+        if !dir.is_dir() {
+            continue;
+        }
+
+        let version = dir
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .parse::<PhotonVersion>()
+            .unwrap();
+
+        for file in std::fs::read_dir(&dir).unwrap() {
+            let bin = file.unwrap().path();
+            if bin.extension().is_none_or(|ext| ext != "bin") {
+                continue;
+            }
+
+            let bytes = std::fs::read(&bin).unwrap();
+            let result = match version.decode(&bytes, FIXTURE_RECEIVED) {
+                Ok(result) => result,
+                Err(err) => {
+                    failures.push(format!("{}: {err}", bin.display()));
+                    continue;
+                }
+            };
+
+            assert_eq!(
+                PhotonVersion::detect("rawBytes", &bytes),
+                Some(version),
+                "{}",
+                bin.display()
+            );
+
+            let json = serde_json::to_string_pretty(&golden_json(&result)).unwrap() + "\n";
+            let golden = bin.with_extension("json");
+
+            if regenerate {
+                std::fs::write(&golden, json).unwrap();
+            } else {
+                match std::fs::read_to_string(&golden) {
+                    Ok(expected) if expected == json => {}
+                    Ok(_) => failures.push(format!("{} doesn't match", golden.display())),
+                    Err(err) => failures.push(format!("{}: {err}", golden.display())),
+                }
+            }
+
+            checked += 1;
+        }
+    }
+
+    assert!(checked > 0, "no fixtures in {}", fixtures_dir().display());
+    assert!(
+        failures.is_empty(),
+        "{}\n(set {REGENERATE_GOLDENS}=1 to rewrite the goldens, then review the diff)",
+        failures.join("\n")
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_export_units() {
+    use serde_json::json;
+
+    let pose = Pose2d {
+        translate: Translate2d {
+            x: Length::new::<uom::si::length::centimeter>(150.0),
+            y: Length::new::<meter>(2.0),
+        },
+        rotate: Rotate2d {
+            angle: Angle::new::<degree>(180.0),
+        },
+    };
+    let pose_json = json!({
+        "translate": { "x": 1.5, "y": 2.0 },
+        "rotate": { "angle": std::f64::consts::PI },
+    });
+
+    let exported = export::to_json(&pose).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&exported).unwrap(),
+        pose_json
+    );
+
+    let exported = export::to_msgpack(&pose).unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<serde_json::Value>(&exported).unwrap(),
+        pose_json
+    );
+
+    // the goldens spell photon fields out in the same units
+    let bytes = std::fs::read(fixtures_dir().join("2025/apriltag_multitag.bin")).unwrap();
+    let result = deserialize::<PhotonResult>(&bytes).unwrap();
+    let golden = golden_json(&result);
+    let exported = export::to_json(&result).unwrap();
+    let exported = serde_json::from_str::<serde_json::Value>(&exported).unwrap();
+
+    assert_eq!(
+        exported["metadata"]["capture_time"],
+        golden["metadata"]["capture_micros"]
+    );
+    assert_eq!(exported["targets"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        exported["targets"][0]["fiducial_id"],
+        golden["targets"][0]["fiducial_id"]
+    );
+    assert_eq!(
+        exported["pnp"]["pnp"]["best"]["translation"]["x"],
+        golden["multi_tag"]["best"]["translation"][0]
+    );
+    assert_eq!(exported["pnp"]["num_fiducials"], 2);
+}
+
+#[test]
+fn dump_summary() {
+    use dump::{parse_hex, DumpFilter, HexError, Summary};
+
+    assert_eq!(
+        parse_hex("0a1B ff\n00").unwrap(),
+        vec![0x0a, 0x1b, 0xff, 0x00]
+    );
+    assert!(matches!(parse_hex("0a1"), Err(HexError::OddLength { .. })));
+    assert!(matches!(
+        parse_hex("0g"),
+        Err(HexError::BadDigit { found: 'g', .. })
+    ));
+
+    let bytes = std::fs::read(fixtures_dir().join("2025/apriltag_multitag.bin")).unwrap();
+    let result = deserialize::<PhotonResult>(&bytes).unwrap();
+    let summary = Summary {
+        camera: Some("front"),
+        version: PhotonVersion::V2025,
+        result: &result,
+    };
+    let text = summary.to_string();
+    let header = text.lines().next().unwrap();
+
+    assert!(header.starts_with("[front] seq 1201  latency 30.0ms  targets 2  ids [3, 4]"));
+    assert!(header.ends_with("from 2 tags"));
+    assert_eq!(text.lines().count(), 3);
+
+    let filter = DumpFilter {
+        cameras: vec!["front".to_string()],
+        fiducials: vec![4],
+    };
+    assert!(filter.camera("front"));
+    assert!(!filter.camera("back"));
+    assert!(DumpFilter::default().camera("back"));
+
+    let filtered = filter.apply(result.clone()).unwrap();
+    assert_eq!(filtered.targets.len(), 1);
+    assert_eq!(filtered.targets[0].fiducial_id, FiducialId(Some(4)));
+    assert!(filtered.pnp.is_some());
+
+    let filter = DumpFilter {
+        fiducials: vec![9],
+        ..Default::default()
+    };
+    assert_eq!(filter.apply(result), None);
+
+    // without sequence ids, and with latency worked back from arrival
+    let bytes = std::fs::read(fixtures_dir().join("2023/apriltags.bin")).unwrap();
+    let result = PhotonVersion::V2023
+        .decode(&bytes, FIXTURE_RECEIVED)
+        .unwrap();
+    let summary = Summary {
+        camera: None,
+        version: PhotonVersion::V2023,
+        result: &result,
+    };
+
+    assert!(summary.to_string().starts_with("latency 32.5ms  targets 2"));
+    assert!(summary
+        .to_string()
+        .lines()
+        .next()
+        .unwrap()
+        .ends_with("no pnp"));
+}
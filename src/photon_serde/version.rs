@@ -0,0 +1,165 @@
+//! The `rawData` layouts of PhotonVision releases. Older ones are read straight
+//! into [`PhotonResult`] by its `#[photon(version >= ...)]` fields, with
+//! [`legacy`] reading what those releases sent instead, so the rest of
+//! pathforger only ever sees the current one.
+
+use std::{io::Cursor, panic::Location, str::FromStr, time::Duration};
+
+use crate::prelude::*;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+#[error("At {location}: Unknown PhotonVision version {found:?}, expected 2023, 2024 or 2025")]
+pub struct UnknownVersion {
+    pub found: String,
+    pub location: &'static Location<'static>,
+}
+
+/// The PhotonVision release a coprocessor runs, which decides its packet layout
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum PhotonVersion {
+    /// Latency, then targets with exactly four min area rect corners
+    V2023,
+    /// Latency, targets, then an always-present multi-tag result with 32 id slots
+    V2024,
+    /// [`PhotonResult`] as it's defined in this crate, announced as a
+    /// `photonstruct:`
+    #[default]
+    V2025,
+}
+
+impl PhotonVersion {
+    /// Newest first, which is the order [`PhotonVersion::detect`] tries them in
+    pub const ALL: [Self; 3] = [Self::V2025, Self::V2024, Self::V2023];
+
+    /// The protocol version its layout is read with, which is what
+    /// `#[photon(version >= ...)]` compares against
+    pub fn protocol(&self) -> u32 {
+        match self {
+            Self::V2023 => 2023,
+            Self::V2024 => 2024,
+            Self::V2025 => 2025,
+        }
+    }
+
+    /// Decodes a `rawData` packet. Older layouts only send a latency, so their
+    /// capture time is worked back from `received`, when the packet arrived.
+    pub fn decode(
+        &self,
+        bytes: &[u8],
+        received: Duration,
+    ) -> Result<PhotonResult, DeserializeError> {
+        let mut result = deserialize_version::<PhotonResult>(bytes, self.protocol())?;
+
+        if *self < Self::V2025 {
+            legacy::published_at(&mut result.metadata, received);
+        }
+
+        Ok(result)
+    }
+
+    /// Whether `bytes` decode as this version with nothing left over
+    pub fn fits(&self, bytes: &[u8]) -> bool {
+        let mut data = Cursor::new(bytes);
+        let decoded = PhotonResult::deserialize_version(&mut data, self.protocol()).is_ok();
+
+        decoded && data.position() == bytes.len() as u64
+    }
+
+    /// Guesses the version from how a packet was announced and what's in it.
+    /// Only 2025 announces a `photonstruct:`; older ones are told apart by which
+    /// layout the packet fits exactly.
+    pub fn detect(type_string: &str, bytes: &[u8]) -> Option<Self> {
+        if type_string.starts_with("photonstruct:") {
+            return Some(Self::V2025);
+        }
+
+        Self::ALL.into_iter().find(|version| version.fits(bytes))
+    }
+}
+
+impl FromStr for PhotonVersion {
+    type Err = UnknownVersion;
+
+    #[track_caller]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().trim_start_matches('v') {
+            "2023" => Ok(Self::V2023),
+            "2024" => Ok(Self::V2024),
+            "2025" => Ok(Self::V2025),
+            _ => Err(UnknownVersion {
+                found: s.to_string(),
+                location: Location::caller(),
+            }),
+        }
+    }
+}
+
+/// How fields of [`PhotonResult`] were laid out before they took their current
+/// form, for `#[photon(legacy = ...)]`
+pub mod legacy {
+    use super::*;
+
+    /// 2023 always sent four min area rect corners, with no length prefix
+    pub mod four_corners {
+        use super::*;
+
+        pub fn deserialize(
+            data: &mut Cursor<&[u8]>,
+            version: u32,
+        ) -> Result<Vec<TargetCorner>, DeserializeError> {
+            Ok(<[TargetCorner; 4]>::deserialize_version(data, version)?.to_vec())
+        }
+    }
+
+    /// Before 2025 there was only a latency in milliseconds, with no sequence
+    /// ids or handshake times. It's read as a capture at zero, published
+    /// `latency` later, until [`published_at`] moves it to when it arrived.
+    pub mod latency {
+        use super::*;
+
+        pub fn deserialize(
+            data: &mut Cursor<&[u8]>,
+            _version: u32,
+        ) -> Result<PhotonPipelineMetadata, DeserializeError> {
+            let latency_ms = f64::deserialize(data)?;
+            let latency = Duration::try_from_secs_f64(latency_ms / 1000.0).unwrap_or_default();
+
+            Ok(PhotonPipelineMetadata {
+                seqid: 0,
+                capture_time: Duration::ZERO,
+                publish_time: latency,
+                last_handshake: Duration::ZERO,
+            })
+        }
+    }
+
+    /// Shifts what [`latency`] read so it was published at `received`
+    pub fn published_at(metadata: &mut PhotonPipelineMetadata, received: Duration) {
+        let latency = metadata.publish_time - metadata.capture_time;
+
+        metadata.capture_time = received.saturating_sub(latency);
+        metadata.publish_time = received;
+    }
+
+    /// 2023 had no multi-tag result. 2024 always sent one, as an optional PnP
+    /// result and 32 id slots, where unused slots are -1.
+    pub mod multitag {
+        use super::*;
+
+        pub fn deserialize(
+            data: &mut Cursor<&[u8]>,
+            version: u32,
+        ) -> Result<Option<MultiTargetPNP>, DeserializeError> {
+            if version < 2024 {
+                return Ok(None);
+            }
+
+            let pnp = Option::<PNPResult>::deserialize(data)?;
+            let fiducial_ids = <[u16; 32]>::deserialize(data)?;
+            let num_fiducials = fiducial_ids.iter().filter(|&&id| id as i16 != -1).count() as u16;
+
+            Ok(pnp.map(|pnp| MultiTargetPNP { pnp, num_fiducials }))
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Borrowed decoding of [`PhotonResult`] frames. A [`PhotonResultView`] checks
+//! the frame's lengths once up front, then reads targets and corners straight
+//! out of the NT payload as they're iterated, without allocating.
+
+use std::{io::Cursor, panic::Location};
+
+use crate::prelude::*;
+
+const TRANSFORM3D: usize = 7 * 8;
+const CORNER: usize = 2 * 8;
+
+// offsets into a target
+const YAW: usize = 0;
+const PITCH: usize = 8;
+const AREA: usize = 16;
+const SKEW: usize = 24;
+const FIDUCIAL_ID: usize = 32;
+const DETECTED: usize = 36;
+const TO_TARGET: usize = 48;
+const AMBIGUITY: usize = TO_TARGET + 2 * TRANSFORM3D;
+const AREA_RECT_CORNERS: usize = AMBIGUITY + 8;
+
+fn f64_at(bytes: &[u8], offset: usize) -> f64 {
+    f64::from_le_bytes(bytes[offset..offset + 8].try_into().unwrap())
+}
+
+/// Decodes a fixed-size value from the front of `bytes`. Only used on ranges
+/// [`target_len`] has checked.
+fn fixed_at<T: Deserialize>(bytes: &[u8], offset: usize) -> T {
+    T::deserialize(&mut Cursor::new(&bytes[offset..])).expect("checked when the view was made")
+}
+
+/// `needed` more bytes than `bytes` has
+#[track_caller]
+fn check_len(bytes: &[u8], needed: usize) -> Result<(), DeserializeError> {
+    if bytes.len() < needed {
+        return Err(DeserializeError::Truncated {
+            needed: needed - bytes.len(),
+            location: Location::caller(),
+        });
+    }
+
+    Ok(())
+}
+
+/// How many bytes the target at the front of `bytes` takes up
+fn target_len(bytes: &[u8]) -> Result<usize, DeserializeError> {
+    let mut len = AREA_RECT_CORNERS;
+
+    // area rect corners, then detected corners
+    for _ in 0..2 {
+        check_len(bytes, len + 1)?;
+        len += 1 + bytes[len] as usize * CORNER;
+    }
+
+    check_len(bytes, len)?;
+    Ok(len)
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct PhotonResultView<'a> {
+    pub metadata: PhotonPipelineMetadata,
+    /// Every target, back to back
+    targets: &'a [u8],
+    num_targets: usize,
+    pub pnp: Option<MultiTargetPNP>,
+}
+
+impl<'a> PhotonResultView<'a> {
+    /// Checks `bytes` hold a whole frame. Trailing bytes are ignored, like
+    /// [`deserialize`] does.
+    pub fn new(bytes: &'a [u8]) -> Result<Self, DeserializeError> {
+        let mut data = Cursor::new(bytes);
+        let metadata = PhotonPipelineMetadata::deserialize(&mut data)?;
+        let num_targets = u8::deserialize(&mut data)? as usize;
+
+        let start = data.position() as usize;
+        let mut end = start;
+        for _ in 0..num_targets {
+            end += target_len(&bytes[end..])?;
+        }
+
+        data.set_position(end as u64);
+        let pnp = Option::<MultiTargetPNP>::deserialize(&mut data)?;
+
+        Ok(Self {
+            metadata,
+            targets: &bytes[start..end],
+            num_targets,
+            pnp,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.num_targets
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_targets == 0
+    }
+
+    pub fn targets(&self) -> Targets<'a> {
+        Targets {
+            bytes: self.targets,
+            remaining: self.num_targets,
+        }
+    }
+
+    pub fn to_owned(&self) -> PhotonResult {
+        PhotonResult {
+            metadata: self.metadata,
+            targets: self.targets().map(|target| target.to_owned()).collect(),
+            pnp: self.pnp,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Targets<'a> {
+    bytes: &'a [u8],
+    remaining: usize,
+}
+
+impl<'a> Iterator for Targets<'a> {
+    type Item = TargetView<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+
+        let len = target_len(self.bytes).expect("checked when the view was made");
+        let (target, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        self.remaining -= 1;
+
+        Some(TargetView { bytes: target })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl ExactSizeIterator for Targets<'_> {}
+
+/// One [`PhotonTrackedTarget`], read field by field as it's asked for
+#[derive(Clone, Copy, Debug)]
+pub struct TargetView<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> TargetView<'a> {
+    pub fn yaw(&self) -> f64 {
+        f64_at(self.bytes, YAW)
+    }
+
+    pub fn pitch(&self) -> f64 {
+        f64_at(self.bytes, PITCH)
+    }
+
+    pub fn area(&self) -> f64 {
+        f64_at(self.bytes, AREA)
+    }
+
+    pub fn skew(&self) -> f64 {
+        f64_at(self.bytes, SKEW)
+    }
+
+    pub fn fiducial_id(&self) -> FiducialId {
+        fixed_at(self.bytes, FIDUCIAL_ID)
+    }
+
+    pub fn detected(&self) -> DetectedObject {
+        fixed_at(self.bytes, DETECTED)
+    }
+
+    pub fn to_target(&self) -> TargetTransforms {
+        fixed_at(self.bytes, TO_TARGET)
+    }
+
+    pub fn ambiguity(&self) -> f64 {
+        f64_at(self.bytes, AMBIGUITY)
+    }
+
+    pub fn area_rect_corners(&self) -> Corners<'a> {
+        Corners::at(self.bytes, AREA_RECT_CORNERS)
+    }
+
+    pub fn detected_corners(&self) -> Corners<'a> {
+        let area_rect = self.bytes[AREA_RECT_CORNERS] as usize;
+        Corners::at(self.bytes, AREA_RECT_CORNERS + 1 + area_rect * CORNER)
+    }
+
+    pub fn to_owned(&self) -> PhotonTrackedTarget {
+        PhotonTrackedTarget {
+            yaw: self.yaw(),
+            pitch: self.pitch(),
+            area: self.area(),
+            skew: self.skew(),
+            fiducial_id: self.fiducial_id(),
+            detected: self.detected(),
+            to_target: self.to_target(),
+            ambiguity: self.ambiguity(),
+            area_rect_corners: self.area_rect_corners().collect(),
+            detected_corners: self.detected_corners().collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Corners<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Corners<'a> {
+    /// The length-prefixed list at `offset`
+    fn at(bytes: &'a [u8], offset: usize) -> Self {
+        let len = bytes[offset] as usize;
+        Self {
+            bytes: &bytes[offset + 1..offset + 1 + len * CORNER],
+        }
+    }
+}
+
+impl Iterator for Corners<'_> {
+    type Item = TargetCorner;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.bytes.is_empty() {
+            return None;
+        }
+
+        let corner = TargetCorner {
+            x: f64_at(self.bytes, 0),
+            y: f64_at(self.bytes, 8),
+        };
+        self.bytes = &self.bytes[CORNER..];
+
+        Some(corner)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.bytes.len() / CORNER;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Corners<'_> {}
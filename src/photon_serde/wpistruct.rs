@@ -0,0 +1,248 @@
+//! WPILib's struct serialization (`struct:Pose2d` and friends), which robot code
+//! uses for geometry since 2024. Values are packed little-endian with no
+//! padding, and arrays are values back to back with no length prefix.
+
+use std::panic::Location;
+
+use crate::prelude::*;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StructError {
+    #[error("At {location}: struct:{name} is {expected} bytes, got {found}")]
+    WrongSize {
+        name: &'static str,
+        found: usize,
+        expected: usize,
+        location: &'static Location<'static>,
+    },
+}
+
+/// A type with a fixed-size WPILib struct layout
+pub trait WpiStruct: Sized {
+    /// The name after `struct:` in type strings and schema topics
+    const NAME: &'static str;
+    const SCHEMA: &'static str;
+    const SIZE: usize;
+    /// (name, schema) of every struct this one nests, which have to be published
+    /// alongside it
+    const DEPENDENCIES: &'static [(&'static str, &'static str)] = &[];
+
+    fn pack(&self, out: &mut Vec<u8>);
+
+    /// `data` is exactly `SIZE` bytes
+    fn unpack(data: &[u8]) -> Self;
+
+    fn type_string() -> String {
+        format!("struct:{}", Self::NAME)
+    }
+
+    fn array_type_string() -> String {
+        format!("struct:{}[]", Self::NAME)
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE);
+        self.pack(&mut out);
+        out
+    }
+
+    #[track_caller]
+    fn decode(data: &[u8]) -> Result<Self, StructError> {
+        if data.len() != Self::SIZE {
+            return Err(StructError::WrongSize {
+                name: Self::NAME,
+                found: data.len(),
+                expected: Self::SIZE,
+                location: Location::caller(),
+            });
+        }
+
+        Ok(Self::unpack(data))
+    }
+
+    fn encode_array(values: &[Self]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(Self::SIZE * values.len());
+        for value in values {
+            value.pack(&mut out);
+        }
+        out
+    }
+
+    #[track_caller]
+    fn decode_array(data: &[u8]) -> Result<Vec<Self>, StructError> {
+        if !data.len().is_multiple_of(Self::SIZE) {
+            return Err(StructError::WrongSize {
+                name: Self::NAME,
+                found: data.len(),
+                expected: data.len().next_multiple_of(Self::SIZE),
+                location: Location::caller(),
+            });
+        }
+
+        Ok((0..data.len())
+            .step_by(Self::SIZE)
+            .map(|start| Self::unpack(&data[start..start + Self::SIZE]))
+            .collect())
+    }
+}
+
+fn f64_at(data: &[u8], offset: usize) -> f64 {
+    f64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+const TRANSLATION2D: (&str, &str) = ("Translation2d", "double x;double y");
+const ROTATION2D: (&str, &str) = ("Rotation2d", "double value");
+const TRANSLATION3D: (&str, &str) = ("Translation3d", "double x;double y;double z");
+const QUATERNION: (&str, &str) = ("Quaternion", "double w;double x;double y;double z");
+const ROTATION3D: (&str, &str) = ("Rotation3d", "Quaternion q");
+
+impl WpiStruct for Translate2d {
+    const NAME: &'static str = TRANSLATION2D.0;
+    const SCHEMA: &'static str = TRANSLATION2D.1;
+    const SIZE: usize = 16;
+
+    fn pack(&self, out: &mut Vec<u8>) {
+        out.extend(self.x.get::<meter>().to_le_bytes());
+        out.extend(self.y.get::<meter>().to_le_bytes());
+    }
+
+    fn unpack(data: &[u8]) -> Self {
+        Self {
+            x: Length::new::<meter>(f64_at(data, 0)),
+            y: Length::new::<meter>(f64_at(data, 8)),
+        }
+    }
+}
+
+impl WpiStruct for Rotate2d {
+    const NAME: &'static str = ROTATION2D.0;
+    const SCHEMA: &'static str = ROTATION2D.1;
+    const SIZE: usize = 8;
+
+    fn pack(&self, out: &mut Vec<u8>) {
+        out.extend(self.angle.get::<radian>().to_le_bytes());
+    }
+
+    fn unpack(data: &[u8]) -> Self {
+        Self {
+            angle: Angle::new::<radian>(f64_at(data, 0)),
+        }
+    }
+}
+
+impl WpiStruct for Pose2d {
+    const NAME: &'static str = "Pose2d";
+    const SCHEMA: &'static str = "Translation2d translation;Rotation2d rotation";
+    const SIZE: usize = Translate2d::SIZE + Rotate2d::SIZE;
+    const DEPENDENCIES: &'static [(&'static str, &'static str)] = &[TRANSLATION2D, ROTATION2D];
+
+    fn pack(&self, out: &mut Vec<u8>) {
+        self.translate.pack(out);
+        self.rotate.pack(out);
+    }
+
+    fn unpack(data: &[u8]) -> Self {
+        Self {
+            translate: Translate2d::unpack(&data[..Translate2d::SIZE]),
+            rotate: Rotate2d::unpack(&data[Translate2d::SIZE..]),
+        }
+    }
+}
+
+impl WpiStruct for Transform2d {
+    const NAME: &'static str = "Transform2d";
+    const SCHEMA: &'static str = "Translation2d translation;Rotation2d rotation";
+    const SIZE: usize = Translate2d::SIZE + Rotate2d::SIZE;
+    const DEPENDENCIES: &'static [(&'static str, &'static str)] = &[TRANSLATION2D, ROTATION2D];
+
+    fn pack(&self, out: &mut Vec<u8>) {
+        self.translate.pack(out);
+        self.rotate.pack(out);
+    }
+
+    fn unpack(data: &[u8]) -> Self {
+        Self {
+            translate: Translate2d::unpack(&data[..Translate2d::SIZE]),
+            rotate: Rotate2d::unpack(&data[Translate2d::SIZE..]),
+        }
+    }
+}
+
+impl WpiStruct for Translate3d {
+    const NAME: &'static str = TRANSLATION3D.0;
+    const SCHEMA: &'static str = TRANSLATION3D.1;
+    const SIZE: usize = 24;
+
+    fn pack(&self, out: &mut Vec<u8>) {
+        for value in self.to_array() {
+            out.extend(value.to_le_bytes());
+        }
+    }
+
+    fn unpack(data: &[u8]) -> Self {
+        [f64_at(data, 0), f64_at(data, 8), f64_at(data, 16)].into()
+    }
+}
+
+impl WpiStruct for Quaternion {
+    const NAME: &'static str = QUATERNION.0;
+    const SCHEMA: &'static str = QUATERNION.1;
+    const SIZE: usize = 32;
+
+    fn pack(&self, out: &mut Vec<u8>) {
+        for value in [self.w, self.x, self.y, self.z] {
+            out.extend(value.to_le_bytes());
+        }
+    }
+
+    fn unpack(data: &[u8]) -> Self {
+        Self {
+            w: f64_at(data, 0),
+            x: f64_at(data, 8),
+            y: f64_at(data, 16),
+            z: f64_at(data, 24),
+        }
+    }
+}
+
+// WPILib wraps the quaternion in a Rotation3d, which doesn't change the layout
+impl WpiStruct for Pose3d {
+    const NAME: &'static str = "Pose3d";
+    const SCHEMA: &'static str = "Translation3d translation;Rotation3d rotation";
+    const SIZE: usize = Translate3d::SIZE + Quaternion::SIZE;
+    const DEPENDENCIES: &'static [(&'static str, &'static str)] =
+        &[TRANSLATION3D, ROTATION3D, QUATERNION];
+
+    fn pack(&self, out: &mut Vec<u8>) {
+        self.translation.pack(out);
+        self.rotation.pack(out);
+    }
+
+    fn unpack(data: &[u8]) -> Self {
+        Self {
+            translation: Translate3d::unpack(&data[..Translate3d::SIZE]),
+            rotation: Quaternion::unpack(&data[Translate3d::SIZE..]),
+        }
+    }
+}
+
+impl WpiStruct for Transform3d {
+    const NAME: &'static str = "Transform3d";
+    const SCHEMA: &'static str = "Translation3d translation;Rotation3d rotation";
+    const SIZE: usize = Translate3d::SIZE + Quaternion::SIZE;
+    const DEPENDENCIES: &'static [(&'static str, &'static str)] =
+        &[TRANSLATION3D, ROTATION3D, QUATERNION];
+
+    fn pack(&self, out: &mut Vec<u8>) {
+        self.translation.pack(out);
+        self.rotation.pack(out);
+    }
+
+    fn unpack(data: &[u8]) -> Self {
+        Self {
+            translation: Translate3d::unpack(&data[..Translate3d::SIZE]),
+            rotation: Quaternion::unpack(&data[Translate3d::SIZE..]),
+        }
+    }
+}
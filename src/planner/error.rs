@@ -0,0 +1,134 @@
+use crate::prelude::*;
+use std::io;
+use std::{backtrace::Backtrace, panic::Location};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum NavGridError {
+    #[error("At {location}: IO error:\n{source}")]
+    IOError {
+        #[from]
+        source: io::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[cfg(feature = "json")]
+    #[error("At {location}: Malformed navgrid JSON:\n{source}")]
+    JsonError {
+        #[from]
+        source: serde_json::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Navgrid row {row} has {found} nodes, expected {expected}")]
+    RaggedRow {
+        row: usize,
+        found: usize,
+        expected: usize,
+        location: &'static Location<'static>,
+    },
+
+    #[error(
+        "At {location}: Navgrid is {found:?} nodes (rows, cols), but the field needs {expected:?}"
+    )]
+    SizeMismatch {
+        found: (usize, usize),
+        expected: (usize, usize),
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: Navgrid is for a {found:?}m field (x, y), but ours is {expected:?}m")]
+    FieldMismatch {
+        found: (f64, f64),
+        expected: (f64, f64),
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: Navgrid node size must be positive, found {node_size}m")]
+    BadNodeSize {
+        node_size: f64,
+        location: &'static Location<'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum PathFileError {
+    #[error("At {location}: IO error:\n{source}")]
+    IOError {
+        #[from]
+        source: io::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[cfg(feature = "json")]
+    #[error("At {location}: Malformed .path JSON:\n{source}")]
+    JsonError {
+        #[from]
+        source: serde_json::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: A path needs at least 2 waypoints, found {found}")]
+    TooFewWaypoints {
+        found: usize,
+        location: &'static Location<'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum TrajectoryError {
+    #[error("At {location}: A trajectory needs at least 2 distinct points, found {found}")]
+    TooFewPoints {
+        found: usize,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: The robot can't move under these constraints ({reason})")]
+    Infeasible {
+        reason: &'static str,
+        location: &'static Location<'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum SmoothingError {
+    #[error("At {location}: A path needs at least 2 points, found {found}")]
+    TooFewPoints {
+        found: usize,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: The smoothed path clips an obstacle leaving {at:?}")]
+    Collision {
+        at: Translate2d,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: Curvature {curvature}/m exceeds the limit of {max}/m")]
+    CurvatureExceeded {
+        curvature: f64,
+        max: f64,
+        location: &'static Location<'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum SearchError {
+    #[error("At {location}: {at:?} is off the navgrid")]
+    OffGrid {
+        at: Translate2d,
+        location: &'static Location<'static>,
+    },
+
+    #[error("At {location}: No route from {from:?} to {to:?} avoids every obstacle")]
+    NoRoute {
+        from: Translate2d,
+        to: Translate2d,
+        location: &'static Location<'static>,
+    },
+}
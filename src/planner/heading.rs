@@ -0,0 +1,109 @@
+use crate::prelude::*;
+
+use game::enemy::Enemy;
+
+use super::trajectory::wrap_radians;
+
+/// Distance over which the heading blends from the robot's current heading into
+/// the profile, so a profile that starts facing elsewhere doesn't ask for a snap
+const BLEND_DISTANCE: f64 = 0.5;
+
+/// Where a holonomic robot should face while it drives a path. Translation and
+/// heading are planned separately; [`Trajectory::generate`] then slows down
+/// wherever the heading changes faster than the angular velocity limit allows.
+///
+/// [`Trajectory::generate`]: super::trajectory::Trajectory::generate
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeadingProfile {
+    /// Keep the starting heading
+    Hold,
+    /// Keep a point on the field in front of the robot
+    FaceTarget(Translate2d),
+    /// Face the direction of travel
+    FaceTravel,
+    /// Turn from the starting heading to the goal's, spread over the whole path
+    RotateToGoal,
+    /// Keep the closest enemy robot in front of us, for defense
+    FaceNearestEnemy,
+}
+
+/// Headings for each point of `path`, starting at `start` and ending at `goal`'s
+/// heading if the profile calls for it. `enemies` is only used by
+/// [`HeadingProfile::FaceNearestEnemy`].
+pub fn plan(
+    profile: HeadingProfile,
+    path: &[Translate2d],
+    start: Rotate2d,
+    goal: Rotate2d,
+    enemies: &[Enemy],
+) -> Vec<Pose2d> {
+    if path.is_empty() {
+        return vec![];
+    }
+
+    let xy = |point: &Translate2d| (point.x.get::<meter>(), point.y.get::<meter>());
+    let points = path.iter().map(xy).collect_vec();
+    let start = start.angle.get::<radian>();
+    let goal = goal.angle.get::<radian>();
+
+    let mut travelled = vec![0.0];
+    for [a, b] in points.array_windows::<2>() {
+        travelled.push(travelled[travelled.len() - 1] + (b.0 - a.0).hypot(b.1 - a.1));
+    }
+
+    let total = travelled[travelled.len() - 1];
+    let facing = |from: (f64, f64), to: (f64, f64)| (to.1 - from.1).atan2(to.0 - from.0);
+    let enemies = enemies
+        .iter()
+        .filter_map(|enemy| enemy.history.last())
+        .map(|point| xy(&point.pose.translate))
+        .collect_vec();
+
+    let targets = (0..points.len()).map(|i| match profile {
+        HeadingProfile::Hold => start,
+        HeadingProfile::FaceTarget(target) => facing(points[i], xy(&target)),
+        HeadingProfile::FaceTravel => {
+            let (a, b) = match i {
+                0 => (0, 1.min(points.len() - 1)),
+                i => (i - 1, i),
+            };
+
+            if a == b {
+                start
+            } else {
+                facing(points[a], points[b])
+            }
+        }
+        HeadingProfile::RotateToGoal => {
+            let frac = if total > 0.0 {
+                travelled[i] / total
+            } else {
+                1.0
+            };
+            start + wrap_radians(goal - start) * frac
+        }
+        HeadingProfile::FaceNearestEnemy => enemies
+            .iter()
+            .min_by(|a, b| {
+                let dist =
+                    |enemy: &(f64, f64)| (enemy.0 - points[i].0).hypot(enemy.1 - points[i].1);
+                dist(a).total_cmp(&dist(b))
+            })
+            .map_or(start, |enemy| facing(points[i], *enemy)),
+    });
+
+    targets
+        .enumerate()
+        .map(|(i, target)| {
+            let blend = (travelled[i] / BLEND_DISTANCE).min(1.0);
+            let heading = wrap_radians(start + wrap_radians(target - start) * blend);
+
+            Pose2d {
+                translate: path[i],
+                rotate: Rotate2d {
+                    angle: Angle::new::<radian>(heading),
+                },
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,10 @@
+pub mod error;
+pub mod heading;
+pub mod navgrid;
+pub mod pathplanner;
+pub mod search;
+pub mod smoothing;
+pub mod trajectory;
+
+#[cfg(test)]
+mod test;
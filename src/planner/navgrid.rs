@@ -0,0 +1,238 @@
+use crate::prelude::*;
+use std::panic::Location;
+#[cfg(feature = "json")]
+use std::{fs, path::Path};
+
+use game::consts::{FIELD_LENGTH, FIELD_WIDTH};
+
+use super::error::NavGridError;
+
+/// How far a navgrid's `field_size` may be from ours, in meters, since
+/// PathPlanner rounds it
+const FIELD_TOLERANCE: f64 = 0.01;
+
+/// On-disk layout of PathPlanner's `deploy/pathplanner/navgrid.json`
+#[cfg(feature = "json")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct NavGridFile {
+    #[serde(
+        rename = "field_size",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    field_size: Option<FieldSizeFile>,
+    #[serde(rename = "nodeSizeMeters")]
+    node_size_meters: f64,
+    grid: Vec<Vec<bool>>,
+}
+
+#[cfg(feature = "json")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct FieldSizeFile {
+    x: f64,
+    y: f64,
+}
+
+/// Anything else makes the grid's shape meaningless
+fn check_node_size(
+    node_size: Length,
+    location: &'static Location<'static>,
+) -> Result<(), NavGridError> {
+    let node_size = node_size.get::<meter>();
+
+    if !node_size.is_finite() || node_size <= 0.0 {
+        return Err(NavGridError::BadNodeSize {
+            node_size,
+            location,
+        });
+    }
+
+    Ok(())
+}
+
+/// The static obstacle map. Nodes are squares of `node_size`, starting at the
+/// field origin.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NavGrid {
+    pub node_size: Length,
+    pub field_size: Translate2d,
+    /// Indexed `[[row, col]]`, where rows step along y (field width) and columns
+    /// step along x (field length). `true` is an obstacle.
+    pub grid: Array2<bool>,
+}
+
+impl NavGrid {
+    /// An obstacle-free grid covering the whole field. Fails on the same node
+    /// sizes [`NavGrid::validate`] does.
+    #[track_caller]
+    pub fn empty(node_size: Length) -> Result<Self, NavGridError> {
+        check_node_size(node_size, Location::caller())?;
+
+        let field_size = Translate2d {
+            x: FIELD_LENGTH(),
+            y: FIELD_WIDTH(),
+        };
+
+        Ok(Self {
+            node_size,
+            field_size,
+            grid: Array2::from_elem(Self::expected_shape(node_size), false),
+        })
+    }
+
+    #[cfg(feature = "json")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, NavGridError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), NavGridError> {
+        fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "json")]
+    #[track_caller]
+    pub fn from_json(json: &str) -> Result<Self, NavGridError> {
+        let file: NavGridFile = serde_json::from_str(json)?;
+
+        let cols = file.grid.first().map_or(0, Vec::len);
+        for (row, nodes) in file.grid.iter().enumerate() {
+            if nodes.len() != cols {
+                return Err(NavGridError::RaggedRow {
+                    row,
+                    found: nodes.len(),
+                    expected: cols,
+                    location: Location::caller(),
+                });
+            }
+        }
+
+        let shape = (file.grid.len(), cols);
+        let grid = Array2::from_shape_vec(shape, file.grid.into_iter().flatten().collect())
+            .expect("rows were checked to be the same length");
+
+        let field_size = match file.field_size {
+            Some(FieldSizeFile { x, y }) => Translate2d {
+                x: Length::new::<meter>(x),
+                y: Length::new::<meter>(y),
+            },
+            None => Translate2d {
+                x: FIELD_LENGTH(),
+                y: FIELD_WIDTH(),
+            },
+        };
+
+        let navgrid = Self {
+            node_size: Length::new::<meter>(file.node_size_meters),
+            field_size,
+            grid,
+        };
+
+        navgrid.validate()?;
+        Ok(navgrid)
+    }
+
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String, NavGridError> {
+        let file = NavGridFile {
+            field_size: Some(FieldSizeFile {
+                x: self.field_size.x.get::<meter>(),
+                y: self.field_size.y.get::<meter>(),
+            }),
+            node_size_meters: self.node_size.get::<meter>(),
+            grid: self.grid.outer_iter().map(|row| row.to_vec()).collect(),
+        };
+
+        Ok(serde_json::to_string(&file)?)
+    }
+
+    /// Checks that the grid covers exactly the field described in `game::consts`
+    #[track_caller]
+    pub fn validate(&self) -> Result<(), NavGridError> {
+        let location = Location::caller();
+        check_node_size(self.node_size, location)?;
+
+        let found = (
+            self.field_size.x.get::<meter>(),
+            self.field_size.y.get::<meter>(),
+        );
+        let expected = (FIELD_LENGTH().get::<meter>(), FIELD_WIDTH().get::<meter>());
+
+        if (found.0 - expected.0).abs() > FIELD_TOLERANCE
+            || (found.1 - expected.1).abs() > FIELD_TOLERANCE
+        {
+            return Err(NavGridError::FieldMismatch {
+                found,
+                expected,
+                location,
+            });
+        }
+
+        let expected = Self::expected_shape(self.node_size);
+
+        if self.grid.dim() != expected {
+            return Err(NavGridError::SizeMismatch {
+                found: self.grid.dim(),
+                expected,
+                location,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// (rows, cols) needed to cover the field with nodes of `node_size`
+    pub fn expected_shape(node_size: Length) -> (usize, usize) {
+        // PathPlanner rounds partial nodes up; the epsilon keeps 8.4 / 0.2 from
+        // becoming 43 rows
+        let count = |len: Length| (len.get::<meter>() / node_size.get::<meter>() - 1e-9).ceil();
+
+        (
+            count(FIELD_WIDTH()) as usize,
+            count(FIELD_LENGTH()) as usize,
+        )
+    }
+
+    /// (row, col) of the node containing `point`, or `None` if it's off the grid
+    pub fn node_of(&self, point: Translate2d) -> Option<(usize, usize)> {
+        let size = self.node_size.get::<meter>();
+        let row = (point.y.get::<meter>() / size).floor();
+        let col = (point.x.get::<meter>() / size).floor();
+        let (rows, cols) = self.grid.dim();
+
+        if row < 0.0 || col < 0.0 || row as usize >= rows || col as usize >= cols {
+            return None;
+        }
+
+        Some((row as usize, col as usize))
+    }
+
+    /// Center of the node at (row, col)
+    pub fn center_of(&self, (row, col): (usize, usize)) -> Translate2d {
+        Translate2d {
+            x: self.node_size * (col as f64 + 0.5),
+            y: self.node_size * (row as f64 + 0.5),
+        }
+    }
+
+    /// A copy with every node whose center is within `clearance` of one of
+    /// `points` marked as an obstacle, for driving around other robots
+    pub fn with_obstacles(&self, points: &[Translate2d], clearance: Length) -> Self {
+        let mut navgrid = self.clone();
+
+        for ((row, col), obstacle) in navgrid.grid.indexed_iter_mut() {
+            let center = self.center_of((row, col));
+            *obstacle |= points
+                .iter()
+                .any(|point| (point.x - center.x).hypot(point.y - center.y) < clearance);
+        }
+
+        navgrid
+    }
+
+    /// Anything off the grid counts as an obstacle
+    pub fn is_obstacle(&self, point: Translate2d) -> bool {
+        self.node_of(point).is_none_or(|node| self.grid[node])
+    }
+}
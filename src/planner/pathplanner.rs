@@ -0,0 +1,340 @@
+use crate::prelude::*;
+use std::panic::Location;
+
+use game::consts::{MAX_ACCEL, MAX_ANGULAR_ACCEL, MAX_ANGULAR_SPEED, MAX_SPEED};
+
+use super::{
+    error::PathFileError,
+    smoothing::{self, CubicBezier},
+};
+
+/// One anchor of the bezier chain. The first waypoint has no `prev_control`, and
+/// the last has no `next_control`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BezierWaypoint {
+    pub anchor: Translate2d,
+    pub prev_control: Option<Translate2d>,
+    pub next_control: Option<Translate2d>,
+}
+
+/// Holonomic rotation to reach at `position`, measured in waypoints (1.5 is
+/// halfway between the second and third waypoint)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RotationTarget {
+    pub position: f64,
+    pub rotation: Rotate2d,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PathConstraints {
+    pub max_speed: Velocity,
+    pub max_accel: Acceleration,
+    pub max_angular_speed: AngularVelocity,
+    pub max_angular_accel: AngularAcceleration,
+}
+
+impl Default for PathConstraints {
+    fn default() -> Self {
+        Self {
+            max_speed: MAX_SPEED(),
+            max_accel: MAX_ACCEL(),
+            max_angular_speed: MAX_ANGULAR_SPEED(),
+            max_angular_accel: MAX_ANGULAR_ACCEL(),
+        }
+    }
+}
+
+/// A path in PathPlanner's bezier representation, used both to export our
+/// plans to the GUI and to load hand-drawn routes
+#[derive(Clone, Debug, PartialEq)]
+pub struct PathPlannerPath {
+    pub waypoints: Vec<BezierWaypoint>,
+    pub rotation_targets: Vec<RotationTarget>,
+    pub constraints: PathConstraints,
+    pub start_rotation: Rotate2d,
+    pub end_rotation: Rotate2d,
+    pub end_speed: Velocity,
+    pub reversed: bool,
+}
+
+impl PathPlannerPath {
+    /// Fits a smooth bezier chain through `points` (see [`smoothing::fit`]), ending at rest
+    #[track_caller]
+    pub fn from_translations(points: &[Translate2d]) -> Result<Self, PathFileError> {
+        if points.len() < 2 {
+            return Err(PathFileError::TooFewWaypoints {
+                found: points.len(),
+                location: Location::caller(),
+            });
+        }
+
+        let waypoints = smoothing::fit(points, &vec![1.0; points.len()]);
+
+        Ok(Self {
+            waypoints,
+            rotation_targets: vec![],
+            constraints: PathConstraints::default(),
+            start_rotation: Rotate2d::default(),
+            end_rotation: Rotate2d::default(),
+            end_speed: Velocity::default(),
+            reversed: false,
+        })
+    }
+
+    /// Like [`Self::from_translations`], but every intermediate pose's heading
+    /// becomes a rotation target at its waypoint
+    #[track_caller]
+    pub fn from_poses(poses: &[Pose2d]) -> Result<Self, PathFileError> {
+        let translations = poses.iter().map(|pose| pose.translate).collect_vec();
+        let mut path = Self::from_translations(&translations)?;
+
+        path.start_rotation = poses[0].rotate;
+        path.end_rotation = poses[poses.len() - 1].rotate;
+        path.rotation_targets = poses[1..poses.len() - 1]
+            .iter()
+            .enumerate()
+            .map(|(i, pose)| RotationTarget {
+                position: (i + 1) as f64,
+                rotation: pose.rotate,
+            })
+            .collect();
+
+        Ok(path)
+    }
+
+    /// Samples the bezier chain `per_segment` times between each pair of
+    /// anchors, so imported routes can be used like any other waypoint list
+    pub fn sample(&self, per_segment: usize) -> Vec<Translate2d> {
+        let per_segment = per_segment.max(1);
+        let mut points = vec![self.waypoints[0].anchor];
+
+        for [start, end] in self.waypoints.array_windows::<2>() {
+            let segment = CubicBezier::between(start, end);
+
+            points.extend(
+                (1..=per_segment).map(|step| segment.point(step as f64 / per_segment as f64)),
+            );
+        }
+
+        points
+    }
+}
+
+/// PathPlanner's `.path` files, and reading and writing our paths as them
+#[cfg(feature = "json")]
+mod file {
+    use super::*;
+    use serde_json::Value;
+    use std::{fs, path::Path};
+
+    const PATH_VERSION: f64 = 1.0;
+
+    // On-disk layout of PathPlanner's `deploy/pathplanner/paths/*.path` (format 1.0)
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct PathFile {
+        version: f64,
+        waypoints: Vec<WaypointFile>,
+        #[serde(default)]
+        rotation_targets: Vec<RotationTargetFile>,
+        #[serde(default)]
+        constraint_zones: Vec<Value>,
+        #[serde(default)]
+        event_markers: Vec<Value>,
+        global_constraints: ConstraintsFile,
+        goal_end_state: EndStateFile,
+        #[serde(default)]
+        reversed: bool,
+        #[serde(default)]
+        folder: Option<String>,
+        #[serde(default)]
+        preview_starting_state: Option<EndStateFile>,
+        #[serde(default)]
+        use_default_constraints: bool,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct WaypointFile {
+        anchor: PointFile,
+        prev_control: Option<PointFile>,
+        next_control: Option<PointFile>,
+        #[serde(default)]
+        is_locked: bool,
+        #[serde(default)]
+        linked_name: Option<String>,
+    }
+
+    #[derive(Clone, Copy, serde::Serialize, serde::Deserialize)]
+    struct PointFile {
+        x: f64,
+        y: f64,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct RotationTargetFile {
+        waypoint_relative_pos: f64,
+        rotation_degrees: f64,
+        #[serde(default)]
+        rotate_fast: bool,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct ConstraintsFile {
+        max_velocity: f64,
+        max_acceleration: f64,
+        max_angular_velocity: f64,
+        max_angular_acceleration: f64,
+    }
+
+    #[derive(serde::Serialize, serde::Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct EndStateFile {
+        velocity: f64,
+        rotation: f64,
+        #[serde(default)]
+        rotate_fast: bool,
+    }
+
+    impl From<PointFile> for Translate2d {
+        fn from(PointFile { x, y }: PointFile) -> Self {
+            Translate2d {
+                x: Length::new::<meter>(x),
+                y: Length::new::<meter>(y),
+            }
+        }
+    }
+
+    impl From<Translate2d> for PointFile {
+        fn from(Translate2d { x, y }: Translate2d) -> Self {
+            PointFile {
+                x: x.get::<meter>(),
+                y: y.get::<meter>(),
+            }
+        }
+    }
+
+    impl PathPlannerPath {
+        pub fn load(path: impl AsRef<Path>) -> Result<Self, PathFileError> {
+            Self::from_json(&fs::read_to_string(path)?)
+        }
+
+        pub fn save(&self, path: impl AsRef<Path>) -> Result<(), PathFileError> {
+            fs::write(path, self.to_json()?)?;
+            Ok(())
+        }
+
+        #[track_caller]
+        pub fn from_json(json: &str) -> Result<Self, PathFileError> {
+            let file: PathFile = serde_json::from_str(json)?;
+
+            if file.waypoints.len() < 2 {
+                return Err(PathFileError::TooFewWaypoints {
+                    found: file.waypoints.len(),
+                    location: Location::caller(),
+                });
+            }
+
+            let degrees = |deg: f64| Rotate2d {
+                angle: Angle::new::<degree>(deg),
+            };
+
+            Ok(Self {
+                waypoints: file
+                    .waypoints
+                    .into_iter()
+                    .map(|waypoint| BezierWaypoint {
+                        anchor: waypoint.anchor.into(),
+                        prev_control: waypoint.prev_control.map(Into::into),
+                        next_control: waypoint.next_control.map(Into::into),
+                    })
+                    .collect(),
+                rotation_targets: file
+                    .rotation_targets
+                    .into_iter()
+                    .map(|target| RotationTarget {
+                        position: target.waypoint_relative_pos,
+                        rotation: degrees(target.rotation_degrees),
+                    })
+                    .collect(),
+                constraints: PathConstraints {
+                    max_speed: Velocity::new::<mps>(file.global_constraints.max_velocity),
+                    max_accel: Acceleration::new::<mps2>(file.global_constraints.max_acceleration),
+                    max_angular_speed: AngularVelocity::new::<radps>(
+                        file.global_constraints.max_angular_velocity.to_radians(),
+                    ),
+                    max_angular_accel: AngularAcceleration::new::<radps2>(
+                        file.global_constraints
+                            .max_angular_acceleration
+                            .to_radians(),
+                    ),
+                },
+                start_rotation: file
+                    .preview_starting_state
+                    .map_or(Rotate2d::default(), |state| degrees(state.rotation)),
+                end_rotation: degrees(file.goal_end_state.rotation),
+                end_speed: Velocity::new::<mps>(file.goal_end_state.velocity),
+                reversed: file.reversed,
+            })
+        }
+
+        pub fn to_json(&self) -> Result<String, PathFileError> {
+            let file = PathFile {
+                version: PATH_VERSION,
+                waypoints: self
+                    .waypoints
+                    .iter()
+                    .map(|waypoint| WaypointFile {
+                        anchor: waypoint.anchor.into(),
+                        prev_control: waypoint.prev_control.map(Into::into),
+                        next_control: waypoint.next_control.map(Into::into),
+                        is_locked: false,
+                        linked_name: None,
+                    })
+                    .collect(),
+                rotation_targets: self
+                    .rotation_targets
+                    .iter()
+                    .map(|target| RotationTargetFile {
+                        waypoint_relative_pos: target.position,
+                        rotation_degrees: target.rotation.angle.get::<degree>(),
+                        rotate_fast: false,
+                    })
+                    .collect(),
+                constraint_zones: vec![],
+                event_markers: vec![],
+                global_constraints: ConstraintsFile {
+                    max_velocity: self.constraints.max_speed.get::<mps>(),
+                    max_acceleration: self.constraints.max_accel.get::<mps2>(),
+                    max_angular_velocity: self
+                        .constraints
+                        .max_angular_speed
+                        .get::<radps>()
+                        .to_degrees(),
+                    max_angular_acceleration: self
+                        .constraints
+                        .max_angular_accel
+                        .get::<radps2>()
+                        .to_degrees(),
+                },
+                goal_end_state: EndStateFile {
+                    velocity: self.end_speed.get::<mps>(),
+                    rotation: self.end_rotation.angle.get::<degree>(),
+                    rotate_fast: false,
+                },
+                reversed: self.reversed,
+                folder: None,
+                preview_starting_state: Some(EndStateFile {
+                    velocity: 0.0,
+                    rotation: self.start_rotation.angle.get::<degree>(),
+                    rotate_fast: false,
+                }),
+                use_default_constraints: false,
+            };
+
+            Ok(serde_json::to_string_pretty(&file)?)
+        }
+    }
+}
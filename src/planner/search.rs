@@ -0,0 +1,136 @@
+use crate::prelude::*;
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+    f64::consts::SQRT_2,
+    panic::Location,
+};
+
+use super::{error::SearchError, navgrid::NavGrid};
+
+type Node = (usize, usize);
+
+/// A node waiting to be expanded, ordered so the heap pops the lowest estimate
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Open {
+    estimate: f64,
+    node: Node,
+}
+
+impl Eq for Open {}
+
+impl Ord for Open {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.total_cmp(&self.estimate)
+    }
+}
+
+impl PartialOrd for Open {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Octile distance in nodes, exact on an 8-connected grid with no obstacles
+fn heuristic((ar, ac): Node, (br, bc): Node) -> f64 {
+    let (dr, dc) = (ar.abs_diff(br) as f64, ac.abs_diff(bc) as f64);
+    dr.max(dc) + (SQRT_2 - 1.0) * dr.min(dc)
+}
+
+/// Free neighbours of `node` and the cost to step to each. Diagonals are only
+/// allowed when both nodes beside them are free, so the route never squeezes
+/// between two obstacles touching at a corner.
+fn neighbours(navgrid: &NavGrid, (row, col): Node) -> impl Iterator<Item = (Node, f64)> + '_ {
+    let (rows, cols) = navgrid.grid.dim();
+    let free = move |row: isize, col: isize| {
+        row >= 0
+            && col >= 0
+            && (row as usize) < rows
+            && (col as usize) < cols
+            && !navgrid.grid[[row as usize, col as usize]]
+    };
+    let (row, col) = (row as isize, col as isize);
+
+    (-1..=1)
+        .cartesian_product(-1..=1)
+        .filter(|&step| step != (0, 0))
+        .filter(move |&(dr, dc)| {
+            free(row + dr, col + dc)
+                && (dr == 0 || dc == 0 || (free(row + dr, col) && free(row, col + dc)))
+        })
+        .map(move |(dr, dc)| {
+            let cost = if dr == 0 || dc == 0 { 1.0 } else { SQRT_2 };
+            (((row + dr) as usize, (col + dc) as usize), cost)
+        })
+}
+
+/// A* over the navgrid's nodes, from `start` to `goal`. The route starts and
+/// ends on the exact points, with node centers between; [`smooth`] is meant to
+/// run on it next.
+///
+/// `start`'s own node may be an obstacle, since the robot can be pushed into
+/// one and still needs a way out.
+///
+/// [`smooth`]: super::smoothing::smooth
+#[track_caller]
+pub fn search(
+    navgrid: &NavGrid,
+    start: Translate2d,
+    goal: Translate2d,
+) -> Result<Vec<Translate2d>, SearchError> {
+    let location = Location::caller();
+    let node_of = |point: Translate2d| {
+        navgrid.node_of(point).ok_or(SearchError::OffGrid {
+            at: point,
+            location,
+        })
+    };
+
+    let from = node_of(start)?;
+    let to = node_of(goal)?;
+
+    let mut open = BinaryHeap::from([Open {
+        estimate: heuristic(from, to),
+        node: from,
+    }]);
+    let mut cost = HashMap::from([(from, 0.0)]);
+    let mut came_from = HashMap::new();
+
+    while let Some(Open { node, .. }) = open.pop() {
+        if node == to {
+            let mut route = vec![goal];
+            let mut node = to;
+
+            while let Some(&previous) = came_from.get(&node) {
+                if previous != from {
+                    route.push(navgrid.center_of(previous));
+                }
+                node = previous;
+            }
+
+            route.push(start);
+            route.reverse();
+            return Ok(route);
+        }
+
+        for (next, step) in neighbours(navgrid, node) {
+            let next_cost = cost[&node] + step;
+            if cost.get(&next).is_some_and(|&known| known <= next_cost) {
+                continue;
+            }
+
+            cost.insert(next, next_cost);
+            came_from.insert(next, node);
+            open.push(Open {
+                estimate: next_cost + heuristic(next, to),
+                node: next,
+            });
+        }
+    }
+
+    Err(SearchError::NoRoute {
+        from: start,
+        to: goal,
+        location,
+    })
+}
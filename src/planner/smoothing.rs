@@ -0,0 +1,418 @@
+use crate::prelude::*;
+use std::panic::Location;
+
+use super::{error::SmoothingError, navgrid::NavGrid, pathplanner::BezierWaypoint};
+
+/// Arc length lookup resolution, per segment
+const LENGTH_SAMPLES: usize = 32;
+
+/// How many times a segment's tangents are stretched before giving up on the
+/// curvature limit
+const TENSION_STEPS: usize = 8;
+
+fn xy(point: Translate2d) -> (f64, f64) {
+    (point.x.get::<meter>(), point.y.get::<meter>())
+}
+
+fn translate(x: f64, y: f64) -> Translate2d {
+    Translate2d {
+        x: Length::new::<meter>(x),
+        y: Length::new::<meter>(y),
+    }
+}
+
+/// Whether the straight line from `a` to `b` stays clear of obstacles, checked
+/// every quarter node
+pub fn line_of_sight(navgrid: &NavGrid, a: Translate2d, b: Translate2d) -> bool {
+    let ((ax, ay), (bx, by)) = (xy(a), xy(b));
+    let step = navgrid.node_size.get::<meter>() / 4.0;
+    let steps = ((bx - ax).hypot(by - ay) / step).ceil().max(1.0) as usize;
+
+    (0..=steps).all(|i| {
+        let t = i as f64 / steps as f64;
+        !navgrid.is_obstacle(translate(ax + (bx - ax) * t, ay + (by - ay) * t))
+    })
+}
+
+/// Indices into `points` that are still needed once every waypoint that can see
+/// a later one directly is skipped
+pub fn shortcut(navgrid: &NavGrid, points: &[Translate2d]) -> Vec<usize> {
+    if points.is_empty() {
+        return vec![];
+    }
+
+    let mut kept = vec![0];
+    let mut anchor = 0;
+
+    while anchor < points.len() - 1 {
+        // furthest visible point, falling back to the next one so we always progress
+        let next = (anchor + 1..points.len())
+            .rev()
+            .find(|&i| line_of_sight(navgrid, points[anchor], points[i]))
+            .unwrap_or(anchor + 1);
+
+        kept.push(next);
+        anchor = next;
+    }
+
+    kept
+}
+
+/// Fits a cubic bezier chain through `points` with Catmull-Rom tangents. Each
+/// anchor's tangent is scaled by its `tension` (1.0 is plain Catmull-Rom).
+pub fn fit(points: &[Translate2d], tension: &[f64]) -> Vec<BezierWaypoint> {
+    let last = points.len().saturating_sub(1);
+    let lerp = |a: Translate2d, b: Translate2d, t: f64| Translate2d {
+        x: a.x + (b.x - a.x) * t,
+        y: a.y + (b.y - a.y) * t,
+    };
+
+    (0..points.len())
+        .map(|i| {
+            let anchor = points[i];
+            let tension = tension[i];
+
+            // a third of the way towards the neighbour at the ends, otherwise
+            // a sixth of the chord between both neighbours
+            let (prev_control, next_control) = match i {
+                _ if last == 0 => (None, None),
+                0 => (None, Some(lerp(anchor, points[1], tension / 3.0))),
+                i if i == last => (Some(lerp(anchor, points[i - 1], tension / 3.0)), None),
+                i => {
+                    let (prev, next) = (points[i - 1], points[i + 1]);
+                    let dx = (next.x - prev.x) * tension / 6.0;
+                    let dy = (next.y - prev.y) * tension / 6.0;
+
+                    (
+                        Some(Translate2d {
+                            x: anchor.x - dx,
+                            y: anchor.y - dy,
+                        }),
+                        Some(Translate2d {
+                            x: anchor.x + dx,
+                            y: anchor.y + dy,
+                        }),
+                    )
+                }
+            };
+
+            BezierWaypoint {
+                anchor,
+                prev_control,
+                next_control,
+            }
+        })
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CubicBezier(pub [Translate2d; 4]);
+
+impl CubicBezier {
+    /// The segment leaving `start` and arriving at `end`
+    pub fn between(start: &BezierWaypoint, end: &BezierWaypoint) -> Self {
+        Self([
+            start.anchor,
+            start.next_control.unwrap_or(start.anchor),
+            end.prev_control.unwrap_or(end.anchor),
+            end.anchor,
+        ])
+    }
+
+    fn controls(&self) -> [(f64, f64); 4] {
+        self.0.map(xy)
+    }
+
+    pub fn point(&self, t: f64) -> Translate2d {
+        let [p0, p1, p2, p3] = self.controls();
+        let u = 1.0 - t;
+        let (b0, b1, b2, b3) = (u * u * u, 3.0 * u * u * t, 3.0 * u * t * t, t * t * t);
+
+        translate(
+            p0.0 * b0 + p1.0 * b1 + p2.0 * b2 + p3.0 * b3,
+            p0.1 * b0 + p1.1 * b1 + p2.1 * b2 + p3.1 * b3,
+        )
+    }
+
+    fn derivatives(&self, t: f64) -> ((f64, f64), (f64, f64)) {
+        let [p0, p1, p2, p3] = self.controls();
+        let u = 1.0 - t;
+
+        let d1 = |a: f64, b: f64, c: f64, d: f64| {
+            3.0 * u * u * (b - a) + 6.0 * u * t * (c - b) + 3.0 * t * t * (d - c)
+        };
+        let d2 = |a: f64, b: f64, c: f64, d: f64| {
+            6.0 * u * (c - 2.0 * b + a) + 6.0 * t * (d - 2.0 * c + b)
+        };
+
+        (
+            (d1(p0.0, p1.0, p2.0, p3.0), d1(p0.1, p1.1, p2.1, p3.1)),
+            (d2(p0.0, p1.0, p2.0, p3.0), d2(p0.1, p1.1, p2.1, p3.1)),
+        )
+    }
+
+    /// Direction of travel at `t`
+    pub fn heading(&self, t: f64) -> Rotate2d {
+        let ((dx, dy), _) = self.derivatives(t);
+
+        Rotate2d {
+            angle: Angle::new::<radian>(dy.atan2(dx)),
+        }
+    }
+
+    /// Signed curvature at `t`, in 1/m. Positive turns left.
+    pub fn curvature(&self, t: f64) -> f64 {
+        let ((dx, dy), (ddx, ddy)) = self.derivatives(t);
+        let speed = dx.hypot(dy);
+
+        if speed < 1e-9 {
+            return 0.0;
+        }
+
+        (dx * ddy - dy * ddx) / speed.powi(3)
+    }
+
+    fn max_curvature(&self) -> f64 {
+        (0..=LENGTH_SAMPLES)
+            .map(|i| self.curvature(i as f64 / LENGTH_SAMPLES as f64).abs())
+            .fold(0.0, f64::max)
+    }
+}
+
+/// A continuous path made of cubic bezier segments, parameterized by arc length
+#[derive(Clone, Debug, PartialEq)]
+pub struct SmoothPath {
+    pub segments: Vec<CubicBezier>,
+    /// Per segment, the arc length at each of `LENGTH_SAMPLES + 1` evenly spaced
+    /// `t`s, measured from the start of the path
+    lengths: Vec<Vec<f64>>,
+}
+
+impl SmoothPath {
+    pub fn new(waypoints: &[BezierWaypoint]) -> Self {
+        let segments = waypoints
+            .array_windows::<2>()
+            .map(|[start, end]| CubicBezier::between(start, end))
+            .collect_vec();
+
+        let mut total = 0.0;
+        let lengths = segments
+            .iter()
+            .map(|segment| {
+                let mut prev = xy(segment.point(0.0));
+
+                (0..=LENGTH_SAMPLES)
+                    .map(|i| {
+                        let point = xy(segment.point(i as f64 / LENGTH_SAMPLES as f64));
+                        total += (point.0 - prev.0).hypot(point.1 - prev.1);
+                        prev = point;
+                        total
+                    })
+                    .collect_vec()
+            })
+            .collect();
+
+        Self { segments, lengths }
+    }
+
+    pub fn length(&self) -> Length {
+        let total = self.lengths.last().and_then(|lengths| lengths.last());
+        Length::new::<meter>(total.copied().unwrap_or_default())
+    }
+
+    /// (segment, t) at arc length `s`, clamped to the ends of the path
+    fn locate(&self, s: Length) -> (usize, f64) {
+        let s = s.get::<meter>();
+        let segment = self
+            .lengths
+            .partition_point(|lengths| lengths[LENGTH_SAMPLES] < s)
+            .min(self.segments.len() - 1);
+
+        let lengths = &self.lengths[segment];
+        let i = lengths
+            .partition_point(|&len| len < s)
+            .clamp(1, LENGTH_SAMPLES);
+        let (before, after) = (lengths[i - 1], lengths[i]);
+        let frac = if after > before {
+            (s - before) / (after - before)
+        } else {
+            0.0
+        };
+
+        (
+            segment,
+            ((i - 1) as f64 + frac.clamp(0.0, 1.0)) / LENGTH_SAMPLES as f64,
+        )
+    }
+
+    pub fn point_at(&self, s: Length) -> Translate2d {
+        let (segment, t) = self.locate(s);
+        self.segments[segment].point(t)
+    }
+
+    pub fn heading_at(&self, s: Length) -> Rotate2d {
+        let (segment, t) = self.locate(s);
+        self.segments[segment].heading(t)
+    }
+
+    pub fn curvature_at(&self, s: Length) -> f64 {
+        let (segment, t) = self.locate(s);
+        self.segments[segment].curvature(t)
+    }
+
+    /// Points every `step` along the path, always including both ends
+    pub fn sample(&self, step: Length) -> Vec<Translate2d> {
+        let length = self.length();
+        let count = (length.get::<meter>() / step.get::<meter>())
+            .ceil()
+            .max(1.0) as usize;
+
+        (0..=count)
+            .map(|i| self.point_at(length * (i as f64 / count as f64)))
+            .collect()
+    }
+
+    /// Like [`Self::sample`], with the direction of travel as the heading
+    pub fn sample_poses(&self, step: Length) -> Vec<Pose2d> {
+        let length = self.length();
+        let count = (length.get::<meter>() / step.get::<meter>())
+            .ceil()
+            .max(1.0) as usize;
+
+        (0..=count)
+            .map(|i| {
+                let s = length * (i as f64 / count as f64);
+
+                Pose2d {
+                    translate: self.point_at(s),
+                    rotate: self.heading_at(s),
+                }
+            })
+            .collect()
+    }
+
+    fn collides(&self, navgrid: &NavGrid, segment: usize) -> bool {
+        let step = navgrid.node_size.get::<meter>() / 4.0;
+        let lengths = &self.lengths[segment];
+        let length = lengths[LENGTH_SAMPLES]
+            - if segment == 0 {
+                0.0
+            } else {
+                self.lengths[segment - 1][LENGTH_SAMPLES]
+            };
+        let steps = (length / step).ceil().max(1.0) as usize;
+
+        (0..=steps)
+            .any(|i| navgrid.is_obstacle(self.segments[segment].point(i as f64 / steps as f64)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SmoothingConfig {
+    /// In 1/m, the inverse of the tightest turn radius allowed
+    pub max_curvature: f64,
+}
+
+impl Default for SmoothingConfig {
+    fn default() -> Self {
+        // 0.5m turn radius
+        Self { max_curvature: 2.0 }
+    }
+}
+
+/// Turns a jagged grid-search path into a smooth one: shortcut anything with line
+/// of sight, fit a bezier chain through what's left, then widen turns that are too
+/// tight and put back waypoints wherever the curve clips an obstacle.
+#[track_caller]
+pub fn smooth(
+    navgrid: &NavGrid,
+    points: &[Translate2d],
+    config: &SmoothingConfig,
+) -> Result<SmoothPath, SmoothingError> {
+    if points.len() < 2 {
+        return Err(SmoothingError::TooFewPoints {
+            found: points.len(),
+            location: Location::caller(),
+        });
+    }
+
+    let mut kept = shortcut(navgrid, points);
+
+    loop {
+        let anchors = kept.iter().map(|&i| points[i]).collect_vec();
+        let mut tension = vec![1.0; anchors.len()];
+        let mut path = SmoothPath::new(&fit(&anchors, &tension));
+
+        // stretch the tangents around tight segments, but never past half of the
+        // shorter neighbouring segment, or the curve starts to loop
+        for _ in 0..TENSION_STEPS {
+            let tight = (0..path.segments.len())
+                .filter(|&i| path.segments[i].max_curvature() > config.max_curvature)
+                .collect_vec();
+
+            if tight.is_empty() {
+                break;
+            }
+
+            for i in tight {
+                for anchor in [i, i + 1] {
+                    tension[anchor] = (tension[anchor] * 1.25).min(max_tension(&anchors, anchor));
+                }
+            }
+
+            path = SmoothPath::new(&fit(&anchors, &tension));
+        }
+
+        // restore the middle original waypoint of the first segment that clips an obstacle
+        let Some(segment) = (0..path.segments.len()).find(|&i| path.collides(navgrid, i)) else {
+            let curvature = path
+                .segments
+                .iter()
+                .map(CubicBezier::max_curvature)
+                .fold(0.0, f64::max);
+
+            if curvature > config.max_curvature {
+                return Err(SmoothingError::CurvatureExceeded {
+                    curvature,
+                    max: config.max_curvature,
+                    location: Location::caller(),
+                });
+            }
+
+            return Ok(path);
+        };
+
+        let (from, to) = (kept[segment], kept[segment + 1]);
+
+        if to - from < 2 {
+            return Err(SmoothingError::Collision {
+                at: points[from],
+                location: Location::caller(),
+            });
+        }
+
+        kept.insert(segment + 1, (from + to) / 2);
+    }
+}
+
+/// Largest tension at `anchor` that keeps its control points within half of the
+/// shorter neighbouring segment
+fn max_tension(anchors: &[Translate2d], anchor: usize) -> f64 {
+    let dist = |a: usize, b: usize| {
+        let ((ax, ay), (bx, by)) = (xy(anchors[a]), xy(anchors[b]));
+        (bx - ax).hypot(by - ay)
+    };
+
+    if anchor == 0 || anchor == anchors.len() - 1 {
+        return 1.5;
+    }
+
+    let shortest = dist(anchor - 1, anchor).min(dist(anchor, anchor + 1));
+    let chord = dist(anchor - 1, anchor + 1);
+
+    if chord < 1e-9 {
+        return 1.0;
+    }
+
+    (3.0 * shortest / chord).max(1.0)
+}
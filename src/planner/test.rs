@@ -0,0 +1,435 @@
+#[cfg(feature = "json")]
+use super::pathplanner::PathPlannerPath;
+use super::{
+    error::{NavGridError, SearchError, TrajectoryError},
+    heading::{self, HeadingProfile},
+    navgrid::NavGrid,
+    search,
+    smoothing::{self, SmoothingConfig},
+    trajectory::{Trajectory, TrajectoryConfig},
+};
+use crate::prelude::*;
+use crate::util::fixtures::pose;
+use std::time::Instant;
+
+use game::enemy::{DataPoint, Enemy};
+
+#[cfg(feature = "json")]
+fn stage_grid() -> NavGrid {
+    let mut navgrid = NavGrid::empty(Length::new::<meter>(0.3)).unwrap();
+    let (rows, cols) = navgrid.grid.dim();
+
+    // a block in the middle of the field
+    navgrid.grid[[rows / 2, cols / 2]] = true;
+    navgrid.grid[[rows / 2 + 1, cols / 2]] = true;
+    navgrid
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn navgrid_roundtrip() {
+    let navgrid = stage_grid();
+    let json = navgrid.to_json().unwrap();
+    let parsed = NavGrid::from_json(&json);
+
+    assert!(parsed.is_ok(), "{:?}", parsed.err());
+    assert_eq!(navgrid, parsed.unwrap());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn navgrid_pathplanner_format() {
+    let (rows, cols) = NavGrid::expected_shape(Length::new::<meter>(0.3));
+    let row = format!("[{}]", vec!["false"; cols].join(","));
+    let grid = format!("[{}]", vec![row; rows].join(","));
+    let json =
+        format!(r#"{{"field_size":{{"x":16.54,"y":8.21}},"nodeSizeMeters":0.3,"grid":{grid}}}"#);
+
+    let navgrid = NavGrid::from_json(&json).unwrap();
+    let middle = Translate2d {
+        x: Length::new::<meter>(8.0),
+        y: Length::new::<meter>(4.0),
+    };
+    let offfield = Translate2d {
+        x: Length::new::<meter>(-1.0),
+        y: Length::new::<meter>(4.0),
+    };
+
+    assert!(!navgrid.is_obstacle(middle));
+    assert!(navgrid.is_obstacle(offfield));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn navgrid_wrong_size() {
+    let json = r#"{"nodeSizeMeters":0.3,"grid":[[false,false],[false,false]]}"#;
+
+    assert!(matches!(
+        NavGrid::from_json(json),
+        Err(NavGridError::SizeMismatch { .. })
+    ));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn navgrid_wrong_field() {
+    let (rows, cols) = NavGrid::expected_shape(Length::new::<meter>(0.3));
+    let row = format!("[{}]", vec!["false"; cols].join(","));
+    let grid = format!("[{}]", vec![row; rows].join(","));
+
+    // the 2023 field, with a grid that happens to be the right shape
+    let json =
+        format!(r#"{{"field_size":{{"x":16.54,"y":8.02}},"nodeSizeMeters":0.3,"grid":{grid}}}"#);
+    assert!(matches!(
+        NavGrid::from_json(&json),
+        Err(NavGridError::FieldMismatch { .. })
+    ));
+
+    for node_size in ["0", "-0.3"] {
+        let json = format!(r#"{{"nodeSizeMeters":{node_size},"grid":[]}}"#);
+        assert!(matches!(
+            NavGrid::from_json(&json),
+            Err(NavGridError::BadNodeSize { .. })
+        ));
+    }
+}
+
+#[test]
+fn navgrid_empty_bad_node_size() {
+    for node_size in [0.0, -0.3, f64::NAN, f64::INFINITY] {
+        assert!(matches!(
+            NavGrid::empty(Length::new::<meter>(node_size)),
+            Err(NavGridError::BadNodeSize { .. })
+        ));
+    }
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn navgrid_ragged() {
+    let json = r#"{"nodeSizeMeters":0.3,"grid":[[false,false],[false]]}"#;
+
+    assert!(matches!(
+        NavGrid::from_json(json),
+        Err(NavGridError::RaggedRow { row: 1, .. })
+    ));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn pathplanner_roundtrip() {
+    let poses = [
+        pose(1.0, 1.0, 0.0),
+        pose(3.0, 2.0, 45.0),
+        pose(5.0, 1.5, 90.0),
+    ];
+    let path = PathPlannerPath::from_poses(&poses).unwrap();
+    let parsed = PathPlannerPath::from_json(&path.to_json().unwrap()).unwrap();
+
+    assert_eq!(parsed.waypoints.len(), 3);
+    assert_eq!(parsed.rotation_targets.len(), 1);
+    assert_eq!(parsed.rotation_targets[0].position, 1.0);
+
+    let sampled = path.sample(10);
+    let reparsed = parsed.sample(10);
+
+    for (a, b) in sampled.iter().zip(&reparsed) {
+        assert!((a.x - b.x).abs().get::<meter>() < 1e-9);
+        assert!((a.y - b.y).abs().get::<meter>() < 1e-9);
+    }
+
+    // the curve passes through every anchor
+    assert_eq!(sampled.len(), 21);
+    assert_eq!(sampled[10], poses[1].translate);
+    assert_eq!(sampled[20], poses[2].translate);
+}
+
+fn line(from: f64, to: f64, steps: usize) -> Vec<Pose2d> {
+    (0..=steps)
+        .map(|i| pose(from + (to - from) * i as f64 / steps as f64, 0.0, 0.0))
+        .collect()
+}
+
+#[test]
+fn trajectory_respects_accel() {
+    let config = TrajectoryConfig::default();
+    let trajectory = Trajectory::generate(&line(0.0, 4.0, 400), &config).unwrap();
+    let accel = config.constraints.max_accel.get::<mps2>();
+
+    // too short to reach max speed, so accelerate to the middle and back down
+    let expected = 2.0 * (2.0 * 2.0 / accel).sqrt();
+    let total = trajectory.total_time().get::<second>();
+    assert!((total - expected).abs() < 1e-2, "{total} vs {expected}");
+
+    let peak = trajectory.sample(trajectory.total_time() / 2.0);
+    assert!((peak.speed().get::<mps>() - (2.0 * accel * 2.0).sqrt()).abs() < 0.05);
+    assert!(trajectory
+        .states
+        .iter()
+        .all(|state| state.speed() <= config.constraints.max_speed));
+    assert_eq!(
+        trajectory.states.last().unwrap().speed(),
+        Velocity::default()
+    );
+}
+
+#[test]
+fn trajectory_two_points() {
+    let config = TrajectoryConfig::default();
+    let path = [pose(1.0, 1.0, 0.0), pose(1.5, 1.0, 0.0)];
+    let trajectory = Trajectory::generate(&path, &config).unwrap();
+    let accel = config.constraints.max_accel.get::<mps2>();
+
+    let expected = 2.0 * (0.5 / accel).sqrt();
+    let total = trajectory.total_time().get::<second>();
+    assert!((total - expected).abs() < 1e-9, "{total} vs {expected}");
+
+    let middle = trajectory.sample(trajectory.total_time() / 2.0);
+    assert!(middle.pose.translate.x.get::<meter>().is_finite());
+    assert!((middle.speed().get::<mps>() - (accel * 0.5).sqrt()).abs() < 1e-9);
+}
+
+#[test]
+fn trajectory_bad_limits() {
+    let path = line(0.0, 4.0, 400);
+
+    for accel in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        let config = TrajectoryConfig {
+            max_centripetal_accel: Acceleration::new::<mps2>(accel),
+            ..Default::default()
+        };
+        assert!(matches!(
+            Trajectory::generate(&path, &config),
+            Err(TrajectoryError::Infeasible { .. })
+        ));
+    }
+}
+
+#[test]
+fn trajectory_moving_start() {
+    let config = TrajectoryConfig {
+        start_speed: Velocity::new::<mps>(3.0),
+        ..Default::default()
+    };
+    let trajectory = Trajectory::generate(&line(0.0, 4.0, 400), &config).unwrap();
+
+    assert!((trajectory.states[0].speed().get::<mps>() - 3.0).abs() < 1e-9);
+}
+
+#[test]
+fn trajectory_slows_for_turns() {
+    // a 1m radius semicircle
+    let arc = (0..=180)
+        .map(|deg| {
+            let theta = (deg as f64).to_radians();
+            pose(theta.cos(), theta.sin(), 0.0)
+        })
+        .collect_vec();
+
+    let config = TrajectoryConfig {
+        start_speed: Velocity::new::<mps>(100.0),
+        ..Default::default()
+    };
+    let trajectory = Trajectory::generate(&arc, &config).unwrap();
+    let limit = config.max_centripetal_accel.get::<mps2>().sqrt();
+
+    for state in &trajectory.states[1..trajectory.states.len() - 1] {
+        assert!((state.curvature - 1.0).abs() < 1e-3);
+        assert!(state.speed().get::<mps>() <= limit + 1e-9);
+    }
+}
+
+fn translate(x: f64, y: f64) -> Translate2d {
+    pose(x, y, 0.0).translate
+}
+
+#[test]
+fn smoothing_around_obstacle() {
+    let mut navgrid = NavGrid::empty(Length::new::<meter>(0.3)).unwrap();
+
+    // a wall from y=1.8 to y=3.9, at x=4.5 to 5.1
+    for row in 6..13 {
+        for col in 15..17 {
+            navgrid.grid[[row, col]] = true;
+        }
+    }
+
+    // a stair-stepped route over the top of the wall
+    let mut jagged = vec![];
+    for i in 0..=10 {
+        jagged.push(translate(2.0 + 0.25 * i as f64, 2.5 + 0.2 * i as f64));
+    }
+    for i in 1..=10 {
+        jagged.push(translate(4.5 + 0.1 * i as f64, 4.5));
+    }
+    for i in 1..=10 {
+        jagged.push(translate(5.5 + 0.25 * i as f64, 4.5 - 0.2 * i as f64));
+    }
+
+    let config = SmoothingConfig::default();
+    let path = smoothing::smooth(&navgrid, &jagged, &config).unwrap();
+    let step = Length::new::<meter>(0.02);
+
+    assert!(path.segments.len() < jagged.len() - 1);
+    assert!(path
+        .sample(step)
+        .into_iter()
+        .all(|point| !navgrid.is_obstacle(point)));
+
+    let mut s = Length::default();
+    while s < path.length() {
+        assert!(path.curvature_at(s).abs() <= config.max_curvature + 1e-6);
+        s += step;
+    }
+
+    assert_eq!(path.point_at(Length::default()), jagged[0]);
+    assert_eq!(path.point_at(path.length()), jagged[jagged.len() - 1]);
+}
+
+#[test]
+fn heading_rotate_to_goal() {
+    let path = line(0.0, 4.0, 400)
+        .into_iter()
+        .map(|pose| pose.translate)
+        .collect_vec();
+    let start = pose(0.0, 0.0, 0.0).rotate;
+    let goal = pose(0.0, 0.0, 170.0).rotate;
+
+    let poses = heading::plan(HeadingProfile::RotateToGoal, &path, start, goal, &[]);
+    let end = poses[poses.len() - 1].rotate.angle.get::<degree>();
+    assert!((end - 170.0).abs() < 1e-6, "{end}");
+
+    // turning the long way round would be -190deg, so every heading stays positive
+    assert!(poses
+        .iter()
+        .all(|pose| pose.rotate.angle.get::<degree>() >= -1e-9));
+
+    let config = TrajectoryConfig::default();
+    let trajectory = Trajectory::generate(&poses, &config).unwrap();
+    let limit = config.constraints.max_angular_speed.get::<radps>();
+
+    assert!(trajectory
+        .states
+        .iter()
+        .all(|state| state.angular_velocity.get::<radps>().abs() <= limit + 1e-9));
+}
+
+#[test]
+fn heading_face_target() {
+    let path = line(0.0, 4.0, 40)
+        .into_iter()
+        .map(|pose| pose.translate)
+        .collect_vec();
+    let target = translate(2.0, 2.0);
+    let start = pose(0.0, 0.0, 45.0).rotate;
+
+    let poses = heading::plan(HeadingProfile::FaceTarget(target), &path, start, start, &[]);
+
+    // directly under the target, past the blend distance
+    assert!((poses[20].rotate.angle.get::<degree>() - 90.0).abs() < 1e-6);
+}
+
+#[test]
+fn heading_face_nearest_enemy() {
+    let path = line(0.0, 4.0, 40)
+        .into_iter()
+        .map(|pose| pose.translate)
+        .collect_vec();
+    let start = pose(0.0, 0.0, 45.0).rotate;
+
+    // a tracked enemy that hasn't been seen yet has nowhere to face
+    let unseen = Enemy {
+        id: 0,
+        history: vec![],
+    };
+    let poses = heading::plan(
+        HeadingProfile::FaceNearestEnemy,
+        &path,
+        start,
+        start,
+        std::slice::from_ref(&unseen),
+    );
+    assert!(poses.iter().all(|pose| pose.rotate == start));
+
+    let seen = Enemy {
+        id: 1,
+        history: vec![DataPoint {
+            time: Instant::now(),
+            pose: pose(2.0, 2.0, 0.0),
+            size: Default::default(),
+            confidence: 1.0,
+        }],
+    };
+    let poses = heading::plan(
+        HeadingProfile::FaceNearestEnemy,
+        &path,
+        start,
+        start,
+        &[unseen, seen],
+    );
+    assert!((poses[20].rotate.angle.get::<degree>() - 90.0).abs() < 1e-6);
+}
+
+#[test]
+fn search_around_obstacles() {
+    let mut navgrid = NavGrid::empty(Length::new::<meter>(0.3)).unwrap();
+
+    // the same wall as smoothing_around_obstacle
+    for row in 6..13 {
+        for col in 15..17 {
+            navgrid.grid[[row, col]] = true;
+        }
+    }
+
+    let (start, goal) = (translate(2.0, 2.5), translate(8.0, 2.5));
+    let route = search::search(&navgrid, start, goal).unwrap();
+
+    assert_eq!(route[0], start);
+    assert_eq!(route[route.len() - 1], goal);
+    assert!(route.iter().all(|&point| !navgrid.is_obstacle(point)));
+
+    for [a, b] in route.array_windows::<2>() {
+        assert!(smoothing::line_of_sight(&navgrid, *a, *b));
+    }
+
+    // the search and smoothing hand off to each other
+    let path = smoothing::smooth(&navgrid, &route, &SmoothingConfig::default()).unwrap();
+    assert!(path
+        .sample(Length::new::<meter>(0.02))
+        .into_iter()
+        .all(|point| !navgrid.is_obstacle(point)));
+
+    // another robot parked on the straight line is driven around too
+    let enemy = translate(5.0, 5.0);
+    let clearance = Length::new::<meter>(0.9);
+    let crowded = navgrid.with_obstacles(&[enemy], clearance);
+    let route = search::search(&crowded, translate(5.0, 7.0), translate(5.0, 1.0)).unwrap();
+
+    assert!(route
+        .iter()
+        .all(|point| { (point.x - enemy.x).hypot(point.y - enemy.y) >= clearance }));
+}
+
+#[test]
+fn search_no_route() {
+    let mut navgrid = NavGrid::empty(Length::new::<meter>(0.3)).unwrap();
+    let (rows, _) = navgrid.grid.dim();
+
+    // a wall across the whole field
+    for row in 0..rows {
+        navgrid.grid[[row, 20]] = true;
+    }
+
+    let result = search::search(&navgrid, translate(2.0, 2.0), translate(10.0, 2.0));
+    assert!(matches!(result, Err(SearchError::NoRoute { .. })));
+
+    let result = search::search(&navgrid, translate(2.0, 2.0), translate(-1.0, 2.0));
+    assert!(matches!(result, Err(SearchError::OffGrid { .. })));
+
+    // a robot pushed into an obstacle can still drive out of it
+    let mut navgrid = NavGrid::empty(Length::new::<meter>(0.3)).unwrap();
+    let start = translate(2.0, 2.0);
+    let node = navgrid.node_of(start).unwrap();
+    navgrid.grid[node] = true;
+    assert!(search::search(&navgrid, start, translate(6.0, 2.0)).is_ok());
+}
@@ -0,0 +1,333 @@
+use crate::prelude::*;
+use std::{f64::consts::PI, panic::Location};
+
+use game::consts::{MAX_ACCEL, MAX_SPEED};
+
+use super::{error::TrajectoryError, pathplanner::PathConstraints};
+
+/// Wraps an angle in radians to [-pi, pi)
+pub(crate) fn wrap_radians(angle: f64) -> f64 {
+    (angle + PI).rem_euclid(2.0 * PI) - PI
+}
+
+/// Where the swerve modules sit relative to the robot center, and how fast
+/// each can drive
+#[derive(Clone, Debug, PartialEq)]
+pub struct SwerveKinematics {
+    pub modules: Vec<Translate2d>,
+    pub max_module_speed: Velocity,
+}
+
+impl SwerveKinematics {
+    /// Four modules on the corners of a square, `track` apart
+    pub fn square(track: Length, max_module_speed: Velocity) -> Self {
+        let half = track / 2.0;
+
+        Self {
+            modules: [(1.0, 1.0), (1.0, -1.0), (-1.0, 1.0), (-1.0, -1.0)]
+                .into_iter()
+                .map(|(sx, sy)| Translate2d {
+                    x: half * sx,
+                    y: half * sy,
+                })
+                .collect(),
+            max_module_speed,
+        }
+    }
+
+    /// Distance from the robot center to the furthest module
+    pub fn radius(&self) -> Length {
+        self.modules
+            .iter()
+            .map(|module| module.x.hypot(module.y))
+            .fold(Length::default(), Length::max)
+    }
+}
+
+impl Default for SwerveKinematics {
+    fn default() -> Self {
+        Self::square(Length::new::<meter>(0.6), MAX_SPEED())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrajectoryConfig {
+    pub constraints: PathConstraints,
+    pub max_centripetal_accel: Acceleration,
+    pub kinematics: SwerveKinematics,
+    /// Speed along the path at the first point. Non-zero when replanning while
+    /// the robot is already moving.
+    pub start_speed: Velocity,
+    pub end_speed: Velocity,
+}
+
+impl Default for TrajectoryConfig {
+    fn default() -> Self {
+        Self {
+            constraints: PathConstraints::default(),
+            max_centripetal_accel: MAX_ACCEL(),
+            kinematics: SwerveKinematics::default(),
+            start_speed: Velocity::default(),
+            end_speed: Velocity::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct TrajectoryState {
+    pub time: Time,
+    pub pose: Pose2d,
+    /// Field-relative (vx, vy)
+    pub velocity: (Velocity, Velocity),
+    /// Field-relative (ax, ay), including the centripetal part
+    pub acceleration: (Acceleration, Acceleration),
+    pub angular_velocity: AngularVelocity,
+    /// Signed, in 1/m. Positive turns left.
+    pub curvature: f64,
+}
+
+impl TrajectoryState {
+    pub fn speed(&self) -> Velocity {
+        self.velocity.0.hypot(self.velocity.1)
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trajectory {
+    pub states: Vec<TrajectoryState>,
+}
+
+impl Trajectory {
+    /// Time-parameterizes a geometric path. `path` should be densely sampled;
+    /// curvature is estimated from neighbouring points and the heading is
+    /// interpolated between each point's `rotate`.
+    #[track_caller]
+    pub fn generate(path: &[Pose2d], config: &TrajectoryConfig) -> Result<Self, TrajectoryError> {
+        let constraints = config.constraints;
+        let max_speed = constraints.max_speed.get::<mps>();
+        let max_accel = constraints.max_accel.get::<mps2>();
+        let max_omega = constraints.max_angular_speed.get::<radps>();
+        let max_centripetal = config.max_centripetal_accel.get::<mps2>();
+        let max_module = config.kinematics.max_module_speed.get::<mps>();
+        let radius = config.kinematics.radius().get::<meter>();
+
+        if [max_speed, max_accel, max_omega, max_centripetal, max_module]
+            .iter()
+            .any(|limit| !limit.is_finite() || *limit <= 0.0)
+        {
+            return Err(TrajectoryError::Infeasible {
+                reason: "speed and acceleration limits must be finite and positive",
+                location: Location::caller(),
+            });
+        }
+
+        // drop repeated points, they have no direction
+        let mut points = path
+            .iter()
+            .map(|pose| {
+                (
+                    pose.translate.x.get::<meter>(),
+                    pose.translate.y.get::<meter>(),
+                    pose.rotate.angle.get::<radian>(),
+                )
+            })
+            .dedup_by(|a, b| (a.0 - b.0).hypot(a.1 - b.1) < 1e-9)
+            .collect_vec();
+
+        if points.len() < 2 {
+            return Err(TrajectoryError::TooFewPoints {
+                found: points.len(),
+                location: Location::caller(),
+            });
+        }
+
+        // a lone segment from rest to rest peaks in its middle, which needs a
+        // point of its own to carry that speed
+        if let [a, b] = points[..] {
+            let middle = (
+                (a.0 + b.0) / 2.0,
+                (a.1 + b.1) / 2.0,
+                a.2 + wrap_radians(b.2 - a.2) / 2.0,
+            );
+            points.insert(1, middle);
+        }
+
+        let n = points.len();
+        let ds = points
+            .array_windows::<2>()
+            .map(|[a, b]| (b.0 - a.0).hypot(b.1 - a.1))
+            .collect_vec();
+
+        // direction of travel at each point, centered where possible
+        let tangent = (0..n)
+            .map(|i| {
+                let (a, b) = (points[i.saturating_sub(1)], points[(i + 1).min(n - 1)]);
+                let len = (b.0 - a.0).hypot(b.1 - a.1);
+                ((b.0 - a.0) / len, (b.1 - a.1) / len)
+            })
+            .collect_vec();
+
+        // signed curvature of the circle through each point and its neighbours
+        let curvature = (0..n)
+            .map(|i| {
+                if i == 0 || i == n - 1 {
+                    return 0.0;
+                }
+
+                let (a, b, c) = (points[i - 1], points[i], points[i + 1]);
+                let cross = (b.0 - a.0) * (c.1 - a.1) - (b.1 - a.1) * (c.0 - a.0);
+                let (ab, bc, ca) = (ds[i - 1], ds[i], (c.0 - a.0).hypot(c.1 - a.1));
+
+                2.0 * cross / (ab * bc * ca)
+            })
+            .collect_vec();
+
+        // heading change per meter travelled, looking ahead
+        let heading_rate = (0..n)
+            .map(|i| {
+                let j = i.min(n - 2);
+                wrap_radians(points[j + 1].2 - points[j].2) / ds[j]
+            })
+            .collect_vec();
+
+        let limit = (0..n)
+            .map(|i| {
+                let mut limit = max_speed;
+
+                if curvature[i].abs() > 1e-9 {
+                    limit = limit.min((max_centripetal / curvature[i].abs()).sqrt());
+                }
+
+                if heading_rate[i].abs() > 1e-9 {
+                    limit = limit.min(max_omega / heading_rate[i].abs());
+                }
+
+                // worst case, a module's drive and rotation velocities line up
+                limit.min(max_module / (1.0 + heading_rate[i].abs() * radius))
+            })
+            .collect_vec();
+
+        let mut speed = limit.clone();
+        speed[0] = speed[0].min(config.start_speed.get::<mps>().max(0.0));
+        speed[n - 1] = speed[n - 1].min(config.end_speed.get::<mps>().max(0.0));
+
+        for i in 1..n {
+            speed[i] = speed[i].min((speed[i - 1].powi(2) + 2.0 * max_accel * ds[i - 1]).sqrt());
+        }
+
+        for i in (0..n - 1).rev() {
+            speed[i] = speed[i].min((speed[i + 1].powi(2) + 2.0 * max_accel * ds[i]).sqrt());
+        }
+
+        let mut time = 0.0;
+        let mut states = Vec::with_capacity(n);
+
+        for i in 0..n {
+            let (x, y, heading) = points[i];
+            let (tx, ty) = tangent[i];
+            let v = speed[i];
+
+            // tangential acceleration over the segment ahead (behind, for the last point)
+            let j = i.min(n - 2);
+            let accel = (speed[j + 1].powi(2) - speed[j].powi(2)) / (2.0 * ds[j]);
+            let centripetal = v * v * curvature[i];
+
+            states.push(TrajectoryState {
+                time: Time::new::<second>(time),
+                pose: Pose2d {
+                    translate: Translate2d {
+                        x: Length::new::<meter>(x),
+                        y: Length::new::<meter>(y),
+                    },
+                    rotate: Rotate2d {
+                        angle: Angle::new::<radian>(heading),
+                    },
+                },
+                velocity: (Velocity::new::<mps>(v * tx), Velocity::new::<mps>(v * ty)),
+                acceleration: (
+                    Acceleration::new::<mps2>(accel * tx - centripetal * ty),
+                    Acceleration::new::<mps2>(accel * ty + centripetal * tx),
+                ),
+                angular_velocity: AngularVelocity::new::<radps>(v * heading_rate[i]),
+                curvature: curvature[i],
+            });
+
+            if i < n - 1 {
+                let sum = speed[i] + speed[i + 1];
+
+                // a segment that starts and ends at rest accelerates through its
+                // first half and brakes through the rest
+                time += if sum > 1e-9 {
+                    2.0 * ds[i] / sum
+                } else {
+                    2.0 * (ds[i] / max_accel).sqrt()
+                };
+            }
+        }
+
+        Ok(Self { states })
+    }
+
+    pub fn total_time(&self) -> Time {
+        self.states
+            .last()
+            .map(|state| state.time)
+            .unwrap_or_default()
+    }
+
+    /// The state at `time`, assuming constant acceleration between states.
+    /// Clamps to the first and last state.
+    pub fn sample(&self, time: Time) -> TrajectoryState {
+        let Some(last) = self.states.last() else {
+            return TrajectoryState::default();
+        };
+
+        if time <= self.states[0].time {
+            return self.states[0];
+        }
+
+        if time >= last.time {
+            return *last;
+        }
+
+        let next = self.states.partition_point(|state| state.time <= time);
+        let (a, b) = (self.states[next - 1], self.states[next]);
+
+        let dt = (b.time - a.time).get::<second>();
+        let tau = (time - a.time).get::<second>();
+        let (va, vb) = (a.speed().get::<mps>(), b.speed().get::<mps>());
+
+        // distance covered vs the whole segment, under constant acceleration
+        let accel = (vb - va) / dt;
+        let travelled = va * tau + 0.5 * accel * tau * tau;
+        let covered = 0.5 * (va + vb) * dt;
+        let frac = if covered > 1e-9 {
+            (travelled / covered).clamp(0.0, 1.0)
+        } else {
+            tau / dt
+        };
+        let lerp = |p: f64, q: f64| p + (q - p) * frac;
+        let heading_a = a.pose.rotate.angle.get::<radian>();
+        let heading_b = heading_a + wrap_radians(b.pose.rotate.angle.get::<radian>() - heading_a);
+
+        TrajectoryState {
+            time,
+            pose: Pose2d {
+                translate: Translate2d {
+                    x: a.pose.translate.x + (b.pose.translate.x - a.pose.translate.x) * frac,
+                    y: a.pose.translate.y + (b.pose.translate.y - a.pose.translate.y) * frac,
+                },
+                rotate: Rotate2d {
+                    angle: Angle::new::<radian>(wrap_radians(lerp(heading_a, heading_b))),
+                },
+            },
+            velocity: (
+                a.velocity.0 + (b.velocity.0 - a.velocity.0) * (tau / dt),
+                a.velocity.1 + (b.velocity.1 - a.velocity.1) * (tau / dt),
+            ),
+            acceleration: a.acceleration,
+            angular_velocity: a.angular_velocity,
+            curvature: lerp(a.curvature, b.curvature),
+        }
+    }
+}
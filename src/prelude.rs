@@ -1,10 +1,17 @@
+#[cfg(feature = "json")]
+pub(crate) use crate::networktables;
 pub use crate::photon_serde::prelude::*;
 pub use crate::util::*;
-pub(crate) use crate::{game, networktables, photon_serde};
+pub(crate) use crate::{game, photon_serde, planner, sim};
 pub use itertools::{max, min, Itertools};
 pub use ndarray::{concatenate, prelude::*, stack};
 pub use uom::si::{
-    acceleration::meter_per_second_squared as mps2, angle::radian,
-    angular_velocity::radian_per_second as radps, f64::*, length::meter, time::second,
+    acceleration::meter_per_second_squared as mps2,
+    angle::{degree, radian},
+    angular_acceleration::radian_per_second_squared as radps2,
+    angular_velocity::radian_per_second as radps,
+    f64::*,
+    length::meter,
+    time::second,
     velocity::meter_per_second as mps,
 };
@@ -0,0 +1,15 @@
+use std::io;
+use std::{backtrace::Backtrace, panic::Location};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum RenderError {
+    #[error("At {location}: IO error:\n{source}")]
+    IOError {
+        #[from]
+        source: io::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+}
@@ -0,0 +1,300 @@
+//! Draws the field, obstacles, tracked enemies and planned paths to SVG, either
+//! as a single picture or as an animation over a replay
+
+pub mod error;
+
+#[cfg(test)]
+mod test;
+
+use crate::prelude::*;
+use std::{fs, path::Path, time::Duration};
+
+use error::RenderError;
+use game::{
+    consts::{FIELD_LENGTH, FIELD_WIDTH, ROBOT_SIZE},
+    enemy::Enemy,
+    tracker::Tracker,
+};
+use planner::navgrid::NavGrid;
+
+/// Enemy colors, picked by id
+const PALETTE: [&str; 8] = [
+    "#e6194b", "#3cb44b", "#4363d8", "#f58231", "#911eb4", "#42d4f4", "#f032e6", "#9a6324",
+];
+
+/// Everything drawn in one picture
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Scene<'a> {
+    pub navgrid: Option<&'a NavGrid>,
+    pub enemies: &'a [Enemy],
+    pub path: &'a [Pose2d],
+    pub ours: Option<Pose2d>,
+}
+
+/// One moment of a replay. The navgrid doesn't change, so it's passed to
+/// [`Renderer::animate`] once instead.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ReplayFrame {
+    pub time: Duration,
+    pub enemies: Vec<Enemy>,
+    pub path: Vec<Pose2d>,
+    pub ours: Option<Pose2d>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Renderer {
+    /// Pixels per meter
+    pub scale: f64,
+    /// Blank space around the field, in pixels
+    pub margin: f64,
+    /// History points drawn behind each enemy
+    pub trail: usize,
+    /// How far ahead velocity arrows point
+    pub arrow_time: Time,
+}
+
+impl Default for Renderer {
+    fn default() -> Self {
+        Self {
+            scale: 50.0,
+            margin: 20.0,
+            trail: 50,
+            arrow_time: Time::new::<second>(0.5),
+        }
+    }
+}
+
+impl Renderer {
+    /// SVG coordinates of a field point. SVG's y axis points down, the field's up.
+    fn point(&self, x: Length, y: Length) -> (f64, f64) {
+        (
+            self.margin + x.get::<meter>() * self.scale,
+            self.margin + (FIELD_WIDTH() - y).get::<meter>() * self.scale,
+        )
+    }
+
+    fn header(&self) -> String {
+        let width = FIELD_LENGTH().get::<meter>() * self.scale + 2.0 * self.margin;
+        let height = FIELD_WIDTH().get::<meter>() * self.scale + 2.0 * self.margin;
+
+        format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width:.0}\" height=\"{height:.0}\" \
+             viewBox=\"0 0 {width:.1} {height:.1}\">\n\
+             <defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+             markerWidth=\"6\" markerHeight=\"6\" orient=\"auto-start-reverse\">\
+             <path d=\"M 0 0 L 10 5 L 0 10 z\" fill=\"context-stroke\"/></marker></defs>\n\
+             <rect width=\"100%\" height=\"100%\" fill=\"#fafafa\"/>\n"
+        )
+    }
+
+    /// The field outline and static obstacles, which every frame shares
+    fn field(&self, navgrid: Option<&NavGrid>) -> String {
+        let (left, top) = self.point(Length::default(), FIELD_WIDTH());
+        let mut svg = format!(
+            "<rect class=\"field\" x=\"{left:.1}\" y=\"{top:.1}\" width=\"{:.1}\" height=\"{:.1}\" \
+             fill=\"none\" stroke=\"#222\" stroke-width=\"2\"/>\n",
+            FIELD_LENGTH().get::<meter>() * self.scale,
+            FIELD_WIDTH().get::<meter>() * self.scale,
+        );
+
+        let Some(navgrid) = navgrid else {
+            return svg;
+        };
+
+        // one rect per run of obstacles along a row, rather than per node
+        let size = navgrid.node_size;
+        for (row, nodes) in navgrid.grid.rows().into_iter().enumerate() {
+            let runs = nodes
+                .iter()
+                .positions(|obstacle| *obstacle)
+                .map(|col| (col, col))
+                .coalesce(|(start, end), (next, _)| {
+                    if next == end + 1 {
+                        Ok((start, next))
+                    } else {
+                        Err(((start, end), (next, next)))
+                    }
+                });
+
+            for (start, end) in runs {
+                let (x, y) = self.point(size * start as f64, size * (row + 1) as f64);
+                svg += &format!(
+                    "<rect class=\"obstacle\" x=\"{x:.1}\" y=\"{y:.1}\" width=\"{:.1}\" \
+                     height=\"{:.1}\" fill=\"#888\"/>\n",
+                    (size * (end - start + 1) as f64).get::<meter>() * self.scale,
+                    size.get::<meter>() * self.scale,
+                );
+            }
+        }
+
+        svg
+    }
+
+    /// A box of `size` at `pose`, rotated with it
+    fn robot(&self, pose: Pose2d, (length, width): (Length, Length), style: &str) -> String {
+        let (cx, cy) = self.point(pose.translate.x, pose.translate.y);
+        let (w, h) = (
+            length.get::<meter>() * self.scale,
+            width.get::<meter>() * self.scale,
+        );
+
+        // SVG rotations are clockwise, since y points down
+        format!(
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{w:.1}\" height=\"{h:.1}\" {style} \
+             transform=\"rotate({:.2} {cx:.1} {cy:.1})\"/>\n",
+            cx - w / 2.0,
+            cy - h / 2.0,
+            -pose.rotate.angle.get::<degree>(),
+        )
+    }
+
+    fn enemy(&self, enemy: &Enemy) -> String {
+        let Some(latest) = enemy.history.last() else {
+            return String::new();
+        };
+
+        let color = PALETTE[enemy.id as usize % PALETTE.len()];
+        let mut svg = format!("<g class=\"enemy\" data-id=\"{}\">\n", enemy.id);
+
+        let trail = enemy.history[enemy.history.len().saturating_sub(self.trail)..]
+            .iter()
+            .map(|dp| {
+                let (x, y) = self.point(dp.pose.translate.x, dp.pose.translate.y);
+                format!("{x:.1},{y:.1}")
+            })
+            .join(" ");
+
+        svg += &format!(
+            "<polyline class=\"trail\" points=\"{trail}\" fill=\"none\" stroke=\"{color}\" \
+             stroke-opacity=\"0.5\" stroke-width=\"2\"/>\n"
+        );
+        svg += &self.robot(
+            latest.pose,
+            latest.size,
+            &format!("fill=\"{color}\" fill-opacity=\"0.3\" stroke=\"{color}\""),
+        );
+
+        let (vx, vy) = Tracker::velocity(enemy);
+        let Translate2d { x, y } = latest.pose.translate;
+        let (x1, y1) = self.point(x, y);
+        let (x2, y2) = self.point(x + vx * self.arrow_time, y + vy * self.arrow_time);
+
+        if (x2 - x1).hypot(y2 - y1) > 1.0 {
+            svg += &format!(
+                "<line class=\"velocity\" x1=\"{x1:.1}\" y1=\"{y1:.1}\" x2=\"{x2:.1}\" \
+                 y2=\"{y2:.1}\" stroke=\"{color}\" stroke-width=\"2\" marker-end=\"url(#arrow)\"/>\n"
+            );
+        }
+
+        svg += &format!(
+            "<text x=\"{x1:.1}\" y=\"{y1:.1}\" font-size=\"12\" text-anchor=\"middle\" \
+             dominant-baseline=\"middle\">{}</text>\n</g>\n",
+            enemy.id
+        );
+
+        svg
+    }
+
+    fn path(&self, path: &[Pose2d]) -> String {
+        if path.is_empty() {
+            return String::new();
+        }
+
+        let points = path
+            .iter()
+            .map(|pose| {
+                let (x, y) = self.point(pose.translate.x, pose.translate.y);
+                format!("{x:.1},{y:.1}")
+            })
+            .join(" ");
+
+        format!(
+            "<polyline class=\"path\" points=\"{points}\" fill=\"none\" stroke=\"#0a0\" \
+             stroke-width=\"3\" stroke-dasharray=\"8 4\"/>\n"
+        )
+    }
+
+    /// Everything that changes between frames
+    fn dynamic(&self, enemies: &[Enemy], path: &[Pose2d], ours: Option<Pose2d>) -> String {
+        let mut svg = self.path(path);
+
+        if let Some(ours) = ours {
+            svg += &self.robot(
+                ours,
+                (ROBOT_SIZE(), ROBOT_SIZE()),
+                "class=\"ours\" fill=\"#0a0\" fill-opacity=\"0.4\" stroke=\"#060\"",
+            );
+        }
+
+        for enemy in enemies {
+            svg += &self.enemy(enemy);
+        }
+
+        svg
+    }
+
+    pub fn render(&self, scene: &Scene) -> String {
+        let mut svg = self.header();
+        svg += &self.field(scene.navgrid);
+        svg += &self.dynamic(scene.enemies, scene.path, scene.ours);
+        svg += "</svg>\n";
+        svg
+    }
+
+    /// An animated SVG that steps through `frames` in real time and loops. Each
+    /// frame shows until the next one's time.
+    pub fn animate(&self, navgrid: Option<&NavGrid>, frames: &[ReplayFrame]) -> String {
+        let mut svg = self.header();
+        svg += &self.field(navgrid);
+
+        let (Some(first), Some(last)) = (frames.first(), frames.last()) else {
+            svg += "</svg>\n";
+            return svg;
+        };
+
+        // the last frame is held for as long as the one before it
+        let hold = match frames {
+            [.., before, last] => last.time.saturating_sub(before.time),
+            _ => Duration::from_secs(1),
+        };
+        let total = (last.time - first.time + hold).as_secs_f64().max(1e-3);
+        let key = |time: Duration| ((time - first.time).as_secs_f64() / total).clamp(0.0, 1.0);
+
+        for (i, frame) in frames.iter().enumerate() {
+            let end = frames.get(i + 1).map_or(last.time + hold, |next| next.time);
+
+            svg += &format!(
+                "<g class=\"frame\" visibility=\"hidden\">\n\
+                 <animate attributeName=\"visibility\" values=\"hidden;visible;hidden\" \
+                 keyTimes=\"0;{:.5};{:.5}\" dur=\"{total:.3}s\" calcMode=\"discrete\" \
+                 repeatCount=\"indefinite\"/>\n\
+                 <text x=\"{:.1}\" y=\"{:.1}\" font-size=\"14\">t = {:.2}s</text>\n",
+                key(frame.time),
+                key(end),
+                self.margin,
+                self.margin - 5.0,
+                frame.time.as_secs_f64(),
+            );
+            svg += &self.dynamic(&frame.enemies, &frame.path, frame.ours);
+            svg += "</g>\n";
+        }
+
+        svg += "</svg>\n";
+        svg
+    }
+
+    pub fn save(&self, scene: &Scene, path: impl AsRef<Path>) -> Result<(), RenderError> {
+        fs::write(path, self.render(scene))?;
+        Ok(())
+    }
+
+    pub fn save_animation(
+        &self,
+        navgrid: Option<&NavGrid>,
+        frames: &[ReplayFrame],
+        path: impl AsRef<Path>,
+    ) -> Result<(), RenderError> {
+        fs::write(path, self.animate(navgrid, frames))?;
+        Ok(())
+    }
+}
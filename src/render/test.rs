@@ -0,0 +1,94 @@
+use super::{Renderer, ReplayFrame, Scene};
+use crate::prelude::*;
+use crate::util::fixtures::pose;
+use std::time::{Duration, Instant};
+
+use game::{
+    consts::{FIELD_WIDTH, ROBOT_SIZE},
+    enemy::{DataPoint, Enemy},
+};
+use planner::navgrid::NavGrid;
+
+/// An enemy driving along +x at 1m/s
+fn enemy(id: u8, start: Instant) -> Enemy {
+    Enemy {
+        id,
+        history: (0..5)
+            .map(|i| DataPoint {
+                time: start + Duration::from_millis(100 * i),
+                pose: pose(3.0 + 0.1 * i as f64, 2.0, 0.0),
+                size: (ROBOT_SIZE(), ROBOT_SIZE()),
+                confidence: 1.0,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn scene() {
+    let mut navgrid = NavGrid::empty(Length::new::<meter>(1.0)).unwrap();
+    // two separate runs on one row
+    navgrid.grid[[2, 3]] = true;
+    navgrid.grid[[2, 4]] = true;
+    navgrid.grid[[2, 6]] = true;
+
+    let enemies = [enemy(1, Instant::now())];
+    let path = [pose(1.0, 1.0, 0.0), pose(5.0, 5.0, 0.0)];
+    let svg = Renderer::default().render(&Scene {
+        navgrid: Some(&navgrid),
+        enemies: &enemies,
+        path: &path,
+        ours: Some(pose(1.0, 1.0, 0.0)),
+    });
+
+    assert!(svg.starts_with("<svg"));
+    assert!(svg.trim_end().ends_with("</svg>"));
+    assert_eq!(svg.matches("class=\"obstacle\"").count(), 2);
+    assert_eq!(svg.matches("class=\"enemy\"").count(), 1);
+    assert_eq!(svg.matches("class=\"velocity\"").count(), 1);
+    assert_eq!(svg.matches("class=\"path\"").count(), 1);
+    assert_eq!(svg.matches("class=\"ours\"").count(), 1);
+
+    // 2 runs, 1 obstacle wide and 2 wide, at 50px/m
+    assert!(svg.contains("width=\"100.0\" height=\"50.0\" fill=\"#888\""));
+    assert!(svg.contains("width=\"50.0\" height=\"50.0\" fill=\"#888\""));
+}
+
+#[test]
+fn field_is_flipped() {
+    let renderer = Renderer::default();
+    let svg = renderer.render(&Scene {
+        path: &[pose(0.0, 0.0, 0.0), pose(0.0, 1.0, 0.0)],
+        ..Default::default()
+    });
+
+    // the field origin is the bottom left corner of the picture
+    let bottom = renderer.margin + FIELD_WIDTH().get::<meter>() * renderer.scale;
+    assert!(svg.contains(&format!(
+        "points=\"20.0,{bottom:.1} 20.0,{:.1}\"",
+        bottom - 50.0
+    )));
+}
+
+#[test]
+fn animation() {
+    let start = Instant::now();
+    let frames = (0..3)
+        .map(|i| ReplayFrame {
+            time: Duration::from_millis(500 * i),
+            enemies: vec![enemy(0, start), enemy(1, start)],
+            ..Default::default()
+        })
+        .collect_vec();
+
+    let svg = Renderer::default().animate(None, &frames);
+
+    assert_eq!(svg.matches("class=\"frame\"").count(), 3);
+    assert_eq!(svg.matches("class=\"enemy\"").count(), 6);
+    assert_eq!(svg.matches("class=\"field\"").count(), 1);
+    assert!(svg.contains("keyTimes=\"0;0.00000;0.33333\" dur=\"1.500s\""));
+    assert!(svg.contains("keyTimes=\"0;0.66667;1.00000\""));
+
+    let empty = Renderer::default().animate(None, &[]);
+    assert!(empty.trim_end().ends_with("</svg>"));
+}
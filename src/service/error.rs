@@ -0,0 +1,103 @@
+use std::{backtrace::Backtrace, panic::Location};
+use std::{io, path::PathBuf};
+
+use thiserror::Error;
+
+use crate::{
+    photon_serde::version::UnknownVersion,
+    planner::error::{SearchError, SmoothingError, TrajectoryError},
+};
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("At {location}: IO error:\n{source}")]
+    IOError {
+        #[from]
+        source: io::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Malformed config JSON:\n{source}")]
+    JsonError {
+        #[from]
+        source: serde_json::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: {source}")]
+    UnknownVersion {
+        #[from]
+        source: UnknownVersion,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Invalid {field}: {reason}")]
+    Invalid {
+        field: &'static str,
+        reason: String,
+        location: &'static Location<'static>,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum PlanError {
+    #[error("At {location}: Search failed:\n{source}")]
+    SearchError {
+        #[from]
+        source: SearchError,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Smoothing failed:\n{source}")]
+    SmoothingError {
+        #[from]
+        source: SmoothingError,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Trajectory generation failed:\n{source}")]
+    TrajectoryError {
+        #[from]
+        source: TrajectoryError,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+}
+
+#[derive(Error, Debug)]
+pub enum LogError {
+    #[error("At {location}: IO error:\n{source}")]
+    IOError {
+        #[from]
+        source: io::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Couldn't open the log file:\n{source}")]
+    AppenderError {
+        #[from]
+        source: tracing_appender::rolling::InitError,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: A logger is already installed:\n{source}")]
+    AlreadyInstalled {
+        #[from]
+        source: tracing_subscriber::util::TryInitError,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: Log file {} isn't a file name", path.display())]
+    NotAFile {
+        path: PathBuf,
+        location: &'static Location<'static>,
+    },
+}
@@ -0,0 +1,197 @@
+//! Where the service's logs go. Each stage of the pipeline runs in a `debug`
+//! span, and at `debug` every span is logged as it closes with how long it was
+//! busy, so a slow tick shows which stage it went to.
+
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    error::{self, Error},
+    fs, io,
+    panic::Location,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use super::error::{ConfigError, LogError};
+use tracing_appender::{non_blocking::WorkerGuard, rolling::RollingFileAppender};
+use tracing_subscriber::{
+    filter::LevelFilter, fmt::format::FmtSpan, layer::SubscriberExt, util::SubscriberInitExt,
+    EnvFilter,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+}
+
+impl FromStr for LogLevel {
+    type Err = ConfigError;
+
+    #[track_caller]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "error" => Ok(Self::Error),
+            "warn" => Ok(Self::Warn),
+            "info" => Ok(Self::Info),
+            "debug" => Ok(Self::Debug),
+            _ => Err(ConfigError::Invalid {
+                field: "log level",
+                reason: format!("{s:?} isn't error, warn, info or debug"),
+                location: Location::caller(),
+            }),
+        }
+    }
+}
+
+impl From<LogLevel> for LevelFilter {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => LevelFilter::ERROR,
+            LogLevel::Warn => LevelFilter::WARN,
+            LogLevel::Info => LevelFilter::INFO,
+            LogLevel::Debug => LevelFilter::DEBUG,
+        }
+    }
+}
+
+/// How often the log file is started afresh
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Rotation {
+    /// One file that grows for as long as the service runs
+    Never,
+    Hourly,
+    #[default]
+    Daily,
+}
+
+impl FromStr for Rotation {
+    type Err = ConfigError;
+
+    #[track_caller]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "never" => Ok(Self::Never),
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            _ => Err(ConfigError::Invalid {
+                field: "log rotation",
+                reason: format!("{s:?} isn't never, hourly or daily"),
+                location: Location::caller(),
+            }),
+        }
+    }
+}
+
+impl From<Rotation> for tracing_appender::rolling::Rotation {
+    fn from(rotation: Rotation) -> Self {
+        match rotation {
+            Rotation::Never => Self::NEVER,
+            Rotation::Hourly => Self::HOURLY,
+            Rotation::Daily => Self::DAILY,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogConfig {
+    /// Overridden by `RUST_LOG` when it's set
+    pub level: LogLevel,
+    pub stderr: bool,
+    /// Rotated files are named after this one, with the date appended
+    pub file: Option<PathBuf>,
+    pub rotation: Rotation,
+    /// Rotated files kept besides the current one
+    pub keep: usize,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LogLevel::default(),
+            stderr: true,
+            file: None,
+            rotation: Rotation::default(),
+            keep: 7,
+        }
+    }
+}
+
+/// Keeps the file sink writing. Dropping it flushes whatever is still
+/// buffered, so it should live until the service stops.
+#[must_use]
+pub struct LogGuard {
+    _file: Option<WorkerGuard>,
+}
+
+/// Installs the global subscriber with the sinks `config` asks for
+#[track_caller]
+pub fn init(config: &LogConfig) -> Result<LogGuard, LogError> {
+    let location = Location::caller();
+    let filter = EnvFilter::builder()
+        .with_default_directive(LevelFilter::from(config.level).into())
+        .from_env_lossy();
+
+    let stderr = config.stderr.then(|| {
+        tracing_subscriber::fmt::layer()
+            .with_writer(io::stderr)
+            .with_span_events(FmtSpan::CLOSE)
+    });
+
+    let (file, guard) = match &config.file {
+        Some(path) => {
+            let (dir, name) = match (path.parent(), path.file_name()) {
+                (Some(dir), Some(name)) => (dir, name.to_string_lossy()),
+                _ => {
+                    return Err(LogError::NotAFile {
+                        path: path.clone(),
+                        location,
+                    })
+                }
+            };
+            let dir = if dir.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                dir
+            };
+
+            fs::create_dir_all(dir)?;
+            let appender = RollingFileAppender::builder()
+                .rotation(config.rotation.into())
+                .filename_prefix(name)
+                .max_log_files(config.keep + 1)
+                .build(dir)?;
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+
+            let layer = tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(writer)
+                .with_span_events(FmtSpan::CLOSE);
+
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
+
+    tracing_subscriber::registry()
+        .with(stderr)
+        .with(file)
+        .with(filter)
+        .try_init()?;
+
+    Ok(LogGuard { _file: guard })
+}
+
+/// Logs `err`, and its backtrace at `debug` if one was captured
+/// (`RUST_BACKTRACE=1`)
+pub fn report(err: &(dyn Error + 'static)) {
+    tracing::error!("{err}");
+
+    if let Some(backtrace) = error::request_ref::<Backtrace>(err) {
+        if backtrace.status() == BacktraceStatus::Captured {
+            tracing::debug!("{backtrace}");
+        }
+    }
+}
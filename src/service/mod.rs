@@ -0,0 +1,439 @@
+//! What the coprocessor runs at boot: its config, the state shared between the
+//! NT worker's callbacks, and the exit codes it stops with.
+//!
+//! Photon results go through the preprocessor into the tracker, and every
+//! change to the enemies or the destination replans the path around them.
+
+pub mod error;
+pub mod logging;
+pub mod prometheus;
+
+#[cfg(test)]
+mod test;
+
+use crate::prelude::*;
+use std::{
+    collections::HashMap,
+    fs,
+    net::{Ipv4Addr, SocketAddr},
+    panic::Location,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    render::ReplayFrame,
+    util::{
+        metrics::{Metrics, Stage},
+        PreprocessorResponse,
+    },
+};
+use error::{ConfigError, PlanError};
+use game::{consts::ROBOT_SIZE, tracker::Tracker};
+use logging::LogConfig;
+use networktables::{
+    codec::Codec, error::PhotonWorkerError, field::Field, SchemaPolicy, WorkerOptions,
+};
+use nt_client::NTAddr;
+use planner::{
+    error::NavGridError,
+    heading::{self, HeadingProfile},
+    navgrid::NavGrid,
+    search,
+    smoothing::{self, SmoothingConfig},
+    trajectory::{Trajectory, TrajectoryConfig},
+};
+
+/// PathPlanner's default node size, for when no navgrid is configured
+const NODE_SIZE: f64 = 0.2;
+
+/// Spacing of the smoothed path's samples, which the trajectory is built from
+const PATH_STEP: f64 = 0.05;
+
+/// Closer than this to the destination and there's nothing left to plan
+const ARRIVED: f64 = 0.05;
+
+/// How far ahead the dashboard shows enemies' predicted positions
+const LOOKAHEAD: Duration = Duration::from_millis(500);
+
+/// Time between recorded replay frames, since results arrive far faster
+const REPLAY_PERIOD: Duration = Duration::from_millis(100);
+
+/// Why the service stopped, as its exit code, so the service manager's log
+/// says which part to look at
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Exit {
+    /// Stopped by Ctrl-C or SIGTERM
+    Shutdown,
+    /// Bad arguments, config or navgrid
+    Config,
+    /// NT failed in a way reconnecting won't fix
+    NetworkTables,
+    /// Photon results that keep failing to decode, or whose schema doesn't
+    /// match ours
+    Photon,
+    /// Robot code's poses keep failing to decode
+    Robot,
+    /// Writing the replay failed
+    Replay,
+}
+
+impl Exit {
+    pub fn code(self) -> u8 {
+        match self {
+            Self::Shutdown => 0,
+            Self::Config => 2,
+            Self::NetworkTables => 3,
+            Self::Photon => 4,
+            Self::Robot => 5,
+            Self::Replay => 6,
+        }
+    }
+
+    pub fn of(err: &PhotonWorkerError) -> Self {
+        match err {
+            PhotonWorkerError::ConnectError { .. }
+            | PhotonWorkerError::Disconnected { .. }
+            | PhotonWorkerError::NTError { .. }
+            | PhotonWorkerError::NTPublishError { .. }
+            | PhotonWorkerError::IOError { .. } => Self::NetworkTables,
+            PhotonWorkerError::DeserializationError { .. }
+            | PhotonWorkerError::SchemaError { .. } => Self::Photon,
+            PhotonWorkerError::StructError { .. } | PhotonWorkerError::ProtoError { .. } => {
+                Self::Robot
+            }
+        }
+    }
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        ExitCode::from(exit.code())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CameraConfig {
+    /// As in `/photonvision/<name>/rawData`
+    pub name: String,
+    pub robot_to_camera: Transform3d,
+}
+
+impl CameraConfig {
+    /// A camera at the robot's center, facing forward, for cameras that are
+    /// named without a mount
+    pub fn centered(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            robot_to_camera: Transform3d {
+                translation: Translate3d {
+                    x: 0.0,
+                    y: 0.0,
+                    z: 0.0,
+                },
+                rotation: Quaternion::from_rpy(
+                    Angle::default(),
+                    Angle::default(),
+                    Angle::default(),
+                ),
+            },
+        }
+    }
+}
+
+/// On-disk layout of the service's JSON config. Every field is optional.
+#[derive(Default, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    team: Option<u16>,
+    address: Option<Ipv4Addr>,
+    #[serde(default)]
+    cameras: Vec<CameraFile>,
+    navgrid: Option<PathBuf>,
+    log_level: Option<String>,
+    log_file: Option<PathBuf>,
+    log_rotation: Option<String>,
+    log_stderr: Option<bool>,
+    metrics_address: Option<SocketAddr>,
+    replay: Option<PathBuf>,
+    photon_version: Option<String>,
+    schema_policy: Option<String>,
+    path_codec: Option<String>,
+}
+
+/// A camera's mount, in meters and degrees from the robot's center
+#[derive(serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CameraFile {
+    name: String,
+    #[serde(default)]
+    x: f64,
+    #[serde(default)]
+    y: f64,
+    #[serde(default)]
+    z: f64,
+    #[serde(default)]
+    roll: f64,
+    #[serde(default)]
+    pitch: f64,
+    #[serde(default)]
+    yaw: f64,
+}
+
+impl From<CameraFile> for CameraConfig {
+    fn from(file: CameraFile) -> Self {
+        Self {
+            name: file.name,
+            robot_to_camera: Transform3d {
+                translation: Translate3d {
+                    x: file.x,
+                    y: file.y,
+                    z: file.z,
+                },
+                rotation: Quaternion::from_rpy(
+                    Angle::new::<degree>(file.roll),
+                    Angle::new::<degree>(file.pitch),
+                    Angle::new::<degree>(file.yaw),
+                ),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    pub worker: WorkerOptions,
+    pub cameras: Vec<CameraConfig>,
+    /// PathPlanner's `navgrid.json`, or an empty field if `None`
+    pub navgrid: Option<PathBuf>,
+    pub log: LogConfig,
+    /// Where to write an animated SVG of the session when it stops
+    pub replay: Option<PathBuf>,
+    /// Where to serve the metrics for Prometheus, if anywhere
+    pub metrics_address: Option<SocketAddr>,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let path = path.as_ref();
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::from_json(&fs::read_to_string(path)?, dir)
+    }
+
+    /// Relative paths in the config are taken from `dir`, where it was read
+    /// from
+    #[track_caller]
+    pub fn from_json(json: &str, dir: &Path) -> Result<Self, ConfigError> {
+        let location = Location::caller();
+        let file: ConfigFile = serde_json::from_str(json)?;
+        let mut config = Self::default();
+
+        config.worker.addr = match (file.team, file.address) {
+            (Some(_), Some(_)) => {
+                return Err(ConfigError::Invalid {
+                    field: "address",
+                    reason: "give a team or an address, not both".to_string(),
+                    location,
+                })
+            }
+            (Some(team), None) => NTAddr::TeamNumber(team),
+            (None, Some(address)) => NTAddr::Custom(address),
+            (None, None) => NTAddr::Local,
+        };
+
+        if let Some(version) = file.photon_version {
+            config.worker.photon_version = Some(version.parse()?);
+        }
+
+        if let Some(policy) = file.schema_policy {
+            config.worker.schema_policy = match policy.as_str() {
+                "refuse" => SchemaPolicy::Refuse,
+                "warn" => SchemaPolicy::Warn,
+                _ => {
+                    return Err(ConfigError::Invalid {
+                        field: "schema_policy",
+                        reason: format!("{policy:?} isn't refuse or warn"),
+                        location,
+                    })
+                }
+            };
+        }
+
+        if let Some(codec) = file.path_codec {
+            config.worker.path_codec = match codec.as_str() {
+                "photon" => Codec::Photon,
+                "struct" => Codec::Struct,
+                "proto" => Codec::Proto,
+                _ => {
+                    return Err(ConfigError::Invalid {
+                        field: "path_codec",
+                        reason: format!("{codec:?} isn't photon, struct or proto"),
+                        location,
+                    })
+                }
+            };
+        }
+
+        if let Some(level) = file.log_level {
+            config.log.level = level.parse()?;
+        }
+
+        if let Some(rotation) = file.log_rotation {
+            config.log.rotation = rotation.parse()?;
+        }
+
+        if let Some(stderr) = file.log_stderr {
+            config.log.stderr = stderr;
+        }
+
+        config.cameras = file.cameras.into_iter().map(CameraConfig::from).collect();
+        config.navgrid = file.navgrid.map(|path| dir.join(path));
+        config.replay = file.replay.map(|path| dir.join(path));
+        config.log.file = file.log_file.map(|path| dir.join(path));
+        config.metrics_address = file.metrics_address;
+
+        Ok(config)
+    }
+
+    pub fn load_navgrid(&self) -> Result<NavGrid, NavGridError> {
+        match &self.navgrid {
+            Some(path) => NavGrid::load(path),
+            None => NavGrid::empty(Length::new::<meter>(NODE_SIZE)),
+        }
+    }
+}
+
+/// Everything the worker's callbacks share: where we are, where we're going,
+/// who's in the way, and the path around them
+#[derive(Clone, Debug)]
+pub struct Pipeline {
+    pub cameras: HashMap<String, Transform3d>,
+    pub navgrid: NavGrid,
+    pub tracker: Tracker,
+    pub robot: Option<Pose2d>,
+    pub dest: Option<Pose2d>,
+    pub trajectory: Option<Trajectory>,
+    /// Recorded when a replay is going to be written
+    pub replay: Option<Vec<ReplayFrame>>,
+    /// The worker's, so tracking and planning show up with its stages
+    pub metrics: Arc<Metrics>,
+}
+
+impl Pipeline {
+    pub fn new(config: &Config, navgrid: NavGrid) -> Self {
+        Self {
+            cameras: config
+                .cameras
+                .iter()
+                .map(|camera| (camera.name.clone(), camera.robot_to_camera))
+                .collect(),
+            navgrid,
+            tracker: Tracker::default(),
+            robot: None,
+            dest: None,
+            trajectory: None,
+            replay: config.replay.as_ref().map(|_| vec![]),
+            metrics: config.worker.metrics.clone(),
+        }
+    }
+
+    pub fn track(&mut self, response: &PreprocessorResponse) {
+        let _timer = self.metrics.start(Stage::Track);
+        let tracks = self.tracker.update(response).len();
+        self.metrics.set_tracks(tracks);
+    }
+
+    /// Plans from the robot to the destination around every tracked enemy. Gives
+    /// `None`, and drops the old path, when either end is unknown or the robot
+    /// has already arrived. A failed plan keeps the old path.
+    pub fn replan(&mut self) -> Result<Option<&Trajectory>, PlanError> {
+        let metrics = self.metrics.clone();
+        let _timer = metrics.start(Stage::Plan);
+
+        let (Some(robot), Some(dest)) = (self.robot, self.dest) else {
+            self.trajectory = None;
+            return Ok(None);
+        };
+
+        let distance = |a: Translate2d, b: Translate2d| (a.x - b.x).hypot(a.y - b.y);
+        if distance(robot.translate, dest.translate) < Length::new::<meter>(ARRIVED) {
+            self.trajectory = None;
+            return Ok(None);
+        }
+
+        // robots we're already touching would box us in, and we can't drive
+        // out of them any more than we can through them
+        let obstacles = self
+            .tracker
+            .enemies
+            .iter()
+            .map(|enemy| enemy.pose().translate)
+            .filter(|&enemy| distance(enemy, robot.translate) >= ROBOT_SIZE())
+            .collect_vec();
+        let navgrid = self.navgrid.with_obstacles(&obstacles, ROBOT_SIZE());
+
+        // searching a node further out leaves the smoothing room to cut the
+        // corners of the route, which otherwise hugs the obstacles node by node
+        let margin = self
+            .navgrid
+            .with_obstacles(&obstacles, ROBOT_SIZE() + self.navgrid.node_size);
+
+        let route = search::search(&margin, robot.translate, dest.translate)?;
+        let path = smoothing::smooth(&navgrid, &route, &SmoothingConfig::default())?;
+        let points = path.sample(Length::new::<meter>(PATH_STEP));
+        let poses = heading::plan(
+            HeadingProfile::RotateToGoal,
+            &points,
+            robot.rotate,
+            dest.rotate,
+            &self.tracker.enemies,
+        );
+
+        let trajectory = Trajectory::generate(&poses, &TrajectoryConfig::default())?;
+        metrics.planned();
+        Ok(Some(self.trajectory.insert(trajectory)))
+    }
+
+    /// What the dashboard should show
+    pub fn field(&self) -> Field {
+        let path = self.trajectory.as_ref().map_or(vec![], |trajectory| {
+            trajectory.states.iter().map(|state| state.pose).collect()
+        });
+
+        match self.robot {
+            Some(robot) => Field::new(robot, &self.tracker.enemies, LOOKAHEAD, path),
+            None => Field {
+                path,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Adds a replay frame at `time`, unless one was added less than
+    /// [`REPLAY_PERIOD`] ago or no replay is being recorded
+    pub fn record(&mut self, time: Duration) {
+        let due = match &self.replay {
+            Some(replay) => replay
+                .last()
+                .is_none_or(|frame| time >= frame.time + REPLAY_PERIOD),
+            None => false,
+        };
+
+        if !due {
+            return;
+        }
+
+        let frame = ReplayFrame {
+            time,
+            enemies: self.tracker.enemies.clone(),
+            path: self.field().path,
+            ours: self.robot,
+        };
+
+        if let Some(replay) = &mut self.replay {
+            replay.push(frame);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! The [`Status`] as a Prometheus text endpoint, for bench testing without a
+//! dashboard. Any request on the socket gets the current metrics back.
+
+use std::{fmt::Write, sync::Arc};
+
+use crate::util::metrics::{ErrorClass, Metrics, Stage, Status};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+/// Prometheus' text exposition format, version 0.0.4
+pub fn render(status: &Status) -> String {
+    let mut out = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+        let _ = writeln!(out, "# HELP pathforger_{name} {help}");
+        let _ = writeln!(out, "# TYPE pathforger_{name} {kind}");
+        let _ = writeln!(out, "pathforger_{name} {value}");
+    };
+
+    metric(
+        "uptime_seconds",
+        "gauge",
+        "Time since the service started",
+        status.uptime.as_secs_f64(),
+    );
+    metric(
+        "loop_rate_hertz",
+        "gauge",
+        "Worker loop iterations per second",
+        status.loop_rate,
+    );
+    metric(
+        "frames_total",
+        "counter",
+        "Photon results decoded",
+        status.frames as f64,
+    );
+    metric(
+        "frames_dropped_total",
+        "counter",
+        "Photon results missed or thrown away",
+        status.frames_dropped as f64,
+    );
+    metric(
+        "deserialize_errors_total",
+        "counter",
+        "Photon results that failed to decode",
+        status.deserialize_errors as f64,
+    );
+    metric(
+        "tracks",
+        "gauge",
+        "Enemy robots being tracked",
+        status.tracks as f64,
+    );
+    metric(
+        "last_plan_age_seconds",
+        "gauge",
+        "Time since the last successful plan, -1 before the first",
+        status.last_plan_age.map_or(-1.0, |age| age.as_secs_f64()),
+    );
+    metric(
+        "nt_connected",
+        "gauge",
+        "1 while connected to NetworkTables",
+        status.connected as u8 as f64,
+    );
+
+    let _ = writeln!(
+        out,
+        "# HELP pathforger_errors_total Worker errors by class, skipped or not"
+    );
+    let _ = writeln!(out, "# TYPE pathforger_errors_total counter");
+
+    for class in ErrorClass::ALL {
+        let _ = writeln!(
+            out,
+            "pathforger_errors_total{{class=\"{}\"}} {}",
+            class.name(),
+            status.errors.get(&class).copied().unwrap_or_default()
+        );
+    }
+
+    let _ = writeln!(
+        out,
+        "# HELP pathforger_stage_latency_seconds Latency of each pipeline stage, over its last few hundred runs"
+    );
+    let _ = writeln!(out, "# TYPE pathforger_stage_latency_seconds summary");
+
+    for stage in Stage::ALL {
+        let latency = status.latency.get(&stage).copied().unwrap_or_default();
+
+        for (quantile, value) in [
+            ("0.5", latency.p50),
+            ("0.9", latency.p90),
+            ("0.99", latency.p99),
+        ] {
+            let _ = writeln!(
+                out,
+                "pathforger_stage_latency_seconds{{stage=\"{stage}\",quantile=\"{quantile}\"}} {}",
+                value.as_secs_f64()
+            );
+        }
+    }
+
+    out
+}
+
+async fn respond(mut stream: TcpStream, metrics: &Metrics) -> std::io::Result<()> {
+    // the request itself doesn't matter, every path gets the metrics
+    let mut request = [0; 1024];
+    let _ = stream.read(&mut request).await?;
+
+    let body = render(&metrics.status());
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Answers scrapes on `listener` until the service stops. A scrape that fails
+/// only costs that scrape.
+pub async fn serve(listener: TcpListener, metrics: Arc<Metrics>) -> ! {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(err) => {
+                tracing::warn!("Couldn't accept a metrics scrape: {err}");
+                continue;
+            }
+        };
+
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            if let Err(err) = respond(stream, &metrics).await {
+                tracing::debug!("Metrics scrape failed: {err}");
+            }
+        });
+    }
+}
@@ -0,0 +1,264 @@
+use super::{
+    error::ConfigError,
+    logging::{self, LogConfig, LogLevel, Rotation},
+    prometheus, Config, Exit, Pipeline,
+};
+use crate::prelude::*;
+use crate::util::fixtures::pose;
+use std::{
+    panic::Location,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use game::{
+    consts::ROBOT_SIZE,
+    enemy::{DataPoint, Enemy},
+};
+use metrics::Stage;
+use networktables::{error::PhotonWorkerError, SchemaPolicy};
+use nt_client::NTAddr;
+use planner::navgrid::NavGrid;
+
+/// An enemy parked at (x, y)
+fn parked(id: u8, x: f64, y: f64) -> Enemy {
+    let start = Instant::now();
+    Enemy {
+        id,
+        history: (0..3)
+            .map(|i| DataPoint {
+                time: start + Duration::from_millis(100 * i),
+                pose: pose(x, y, 0.0),
+                size: (ROBOT_SIZE(), ROBOT_SIZE()),
+                confidence: 1.0,
+            })
+            .collect(),
+    }
+}
+
+#[test]
+fn config_from_json() {
+    let json = r#"{
+        "team": 9999,
+        "cameras": [
+            { "name": "front", "x": 0.3, "z": 0.5, "pitch": -15 },
+            { "name": "back", "yaw": 180 }
+        ],
+        "navgrid": "navgrid.json",
+        "replay": "/tmp/replay.svg",
+        "log_level": "debug",
+        "log_file": "logs/pathforger.log",
+        "log_rotation": "hourly",
+        "metrics_address": "127.0.0.1:9184",
+        "photon_version": "2024",
+        "schema_policy": "warn"
+    }"#;
+    let config = Config::from_json(json, Path::new("/etc/pathforger")).unwrap();
+
+    assert!(matches!(config.worker.addr, NTAddr::TeamNumber(9999)));
+    assert_eq!(config.worker.photon_version, Some(PhotonVersion::V2024));
+    assert_eq!(config.worker.schema_policy, SchemaPolicy::Warn);
+    assert_eq!(config.log.level, LogLevel::Debug);
+    assert_eq!(config.log.rotation, Rotation::Hourly);
+    assert!(config.log.stderr);
+    assert_eq!(config.metrics_address, Some(([127, 0, 0, 1], 9184).into()));
+
+    // relative paths are from the config, absolute ones are left alone
+    assert_eq!(
+        config.navgrid.as_deref(),
+        Some(Path::new("/etc/pathforger/navgrid.json"))
+    );
+    assert_eq!(config.replay.as_deref(), Some(Path::new("/tmp/replay.svg")));
+    assert_eq!(
+        config.log.file.as_deref(),
+        Some(Path::new("/etc/pathforger/logs/pathforger.log"))
+    );
+
+    let names = config
+        .cameras
+        .iter()
+        .map(|camera| &camera.name)
+        .collect_vec();
+    assert_eq!(names, ["front", "back"]);
+
+    let front = config.cameras[0].robot_to_camera;
+    assert_eq!(front.translation.x, 0.3);
+    assert_eq!(front.translation.y, 0.0);
+    assert_eq!(front.translation.z, 0.5);
+    assert_eq!(
+        front.rotation,
+        Quaternion::from_rpy(
+            Angle::default(),
+            Angle::new::<degree>(-15.0),
+            Angle::default()
+        )
+    );
+}
+
+#[test]
+fn config_rejects() {
+    let dir = Path::new("");
+
+    assert!(matches!(
+        Config::from_json(r#"{ "team": 9999, "address": "10.99.99.2" }"#, dir),
+        Err(ConfigError::Invalid {
+            field: "address",
+            ..
+        })
+    ));
+    assert!(matches!(
+        Config::from_json(r#"{ "photon_version": "2019" }"#, dir),
+        Err(ConfigError::UnknownVersion { .. })
+    ));
+    assert!(matches!(
+        Config::from_json(r#"{ "log_level": "loud" }"#, dir),
+        Err(ConfigError::Invalid { .. })
+    ));
+    assert!(matches!(
+        Config::from_json(r#"{ "log_rotation": "weekly" }"#, dir),
+        Err(ConfigError::Invalid {
+            field: "log rotation",
+            ..
+        })
+    ));
+    assert!(matches!(
+        Config::from_json(r#"{ "camera": "front" }"#, dir),
+        Err(ConfigError::JsonError { .. })
+    ));
+
+    // nothing given is a local server and no cameras
+    let config = Config::from_json("{}", dir).unwrap();
+    assert!(matches!(config.worker.addr, NTAddr::Local));
+    assert!(config.cameras.is_empty());
+}
+
+#[test]
+fn pipeline_plans_around_enemies() {
+    let navgrid = NavGrid::empty(Length::new::<meter>(0.2)).unwrap();
+    let mut pipeline = Pipeline::new(&Config::default(), navgrid);
+
+    // nothing to plan until both ends are known
+    pipeline.robot = Some(pose(2.0, 4.0, 0.0));
+    assert!(pipeline.replan().unwrap().is_none());
+
+    pipeline.dest = Some(pose(8.0, 4.0, 0.0));
+    pipeline.tracker.enemies = vec![parked(1, 5.0, 4.0)];
+
+    let trajectory = pipeline.replan().unwrap().unwrap();
+    let first = trajectory.states.first().unwrap().pose.translate;
+    let last = trajectory.states.last().unwrap().pose.translate;
+    let meters = |point: Translate2d| (point.x.get::<meter>(), point.y.get::<meter>());
+
+    assert!((meters(first).0 - 2.0).abs() < 1e-6);
+    assert!((meters(last).0 - 8.0).abs() < 1e-6);
+
+    // the straight line goes through the enemy, the path has to leave it
+    // a robot's width
+    let closest = trajectory
+        .states
+        .iter()
+        .map(|state| {
+            let (x, y) = meters(state.pose.translate);
+            (x - 5.0).hypot(y - 4.0)
+        })
+        .fold(f64::INFINITY, f64::min);
+    assert!(closest >= ROBOT_SIZE().get::<meter>() * 0.9, "{closest}");
+
+    assert!(pipeline.trajectory.is_some());
+    assert!(!pipeline.field().path.is_empty());
+
+    // once there, the path is dropped
+    pipeline.robot = Some(pose(8.0, 4.0, 0.0));
+    assert!(pipeline.replan().unwrap().is_none());
+    assert!(pipeline.trajectory.is_none());
+}
+
+#[test]
+fn pipeline_records_replay() {
+    let config = Config {
+        replay: Some("replay.svg".into()),
+        ..Default::default()
+    };
+    let navgrid = NavGrid::empty(Length::new::<meter>(0.2)).unwrap();
+    let mut pipeline = Pipeline::new(&config, navgrid);
+
+    // frames closer together than the replay period are dropped
+    for millis in [0, 50, 100, 120, 250] {
+        pipeline.record(Duration::from_millis(millis));
+    }
+
+    let times = pipeline
+        .replay
+        .unwrap()
+        .iter()
+        .map(|frame| frame.time.as_millis())
+        .collect_vec();
+    assert_eq!(times, [0, 100, 250]);
+}
+
+#[test]
+fn exit_codes() {
+    let exits = [
+        Exit::Shutdown,
+        Exit::Config,
+        Exit::NetworkTables,
+        Exit::Photon,
+        Exit::Robot,
+        Exit::Replay,
+    ];
+    let codes = exits.map(Exit::code);
+
+    assert_eq!(codes[0], 0);
+    assert!(codes.iter().all_unique());
+
+    let disconnected = PhotonWorkerError::Disconnected {
+        location: Location::caller(),
+    };
+    assert_eq!(Exit::of(&disconnected), Exit::NetworkTables);
+}
+
+#[test]
+fn log_file_sink() {
+    let dir = std::env::temp_dir().join(format!("pathforger-log-{}", std::process::id()));
+    let path = dir.join("service.log");
+    let config = LogConfig {
+        stderr: false,
+        file: Some(path.clone()),
+        rotation: Rotation::Never,
+        ..Default::default()
+    };
+
+    // the only test that installs the global subscriber
+    let guard = logging::init(&config).unwrap();
+    tracing::debug_span!("frame", seqid = 42).in_scope(|| tracing::info!("planned"));
+    drop(guard);
+
+    let log = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert!(log.contains("planned"), "{log}");
+    // spans below the level aren't logged
+    assert!(!log.contains("seqid"), "{log}");
+}
+
+#[test]
+fn pipeline_reports_metrics() {
+    let navgrid = NavGrid::empty(Length::new::<meter>(0.2)).unwrap();
+    let mut pipeline = Pipeline::new(&Config::default(), navgrid);
+
+    pipeline.robot = Some(pose(2.0, 4.0, 0.0));
+    pipeline.dest = Some(pose(8.0, 4.0, 0.0));
+    pipeline.replan().unwrap();
+
+    let status = pipeline.metrics.status();
+    assert!(status.last_plan_age.is_some());
+    assert!(status.latency.contains_key(&Stage::Plan));
+
+    let text = prometheus::render(&status);
+    assert!(text.contains("# TYPE pathforger_frames_total counter\n"));
+    assert!(text.contains("pathforger_nt_connected 0\n"));
+    assert!(text.contains("pathforger_stage_latency_seconds{stage=\"plan\",quantile=\"0.99\"}"));
+    // stages that haven't run still show, as zero
+    assert!(text.contains("pathforger_stage_latency_seconds{stage=\"track\",quantile=\"0.5\"} 0\n"));
+    assert!(text.contains("pathforger_errors_total{class=\"fatal\"} 0\n"));
+}
@@ -0,0 +1,433 @@
+use crate::prelude::*;
+use rand::Rng;
+use std::time::Duration;
+
+use game::{
+    consts::{BUMPER_HEIGHT, ROBOT_SIZE, TAG_SIZE},
+    layout::AprilTag,
+};
+
+use super::gaussian;
+
+/// Object detection class id for robots
+pub const ROBOT_CLASS: u64 = 0;
+
+/// Points closer than this in front of the lens can't be projected sensibly
+const NEAR_PLANE: f64 = 0.05;
+
+/// Pinhole camera intrinsics, in pixels
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraIntrinsics {
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+}
+
+impl CameraIntrinsics {
+    /// Square pixels and a centered principal point, from the horizontal FOV
+    pub fn from_fov((width, height): (u32, u32), horizontal: Angle) -> Self {
+        let f = width as f64 / 2.0 / (horizontal.get::<radian>() / 2.0).tan();
+
+        Self {
+            fx: f,
+            fy: f,
+            cx: width as f64 / 2.0,
+            cy: height as f64 / 2.0,
+        }
+    }
+}
+
+/// Something on the field a camera can see
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SimObject {
+    /// An enemy robot's bumpers, found by object detection
+    Robot(Pose2d),
+    /// A tag from the field layout, found by the AprilTag pipeline
+    AprilTag(AprilTag),
+}
+
+/// A camera on our robot that sees enemy bumpers and AprilTags, along with how
+/// badly its pipeline behaves
+#[derive(Clone, Debug, PartialEq)]
+pub struct VirtualCamera {
+    pub name: String,
+    pub robot_to_camera: Transform3d,
+    /// (width, height) in pixels
+    pub resolution: (u32, u32),
+    pub intrinsics: CameraIntrinsics,
+    pub max_range: Length,
+    /// Standard deviation of the yaw and pitch noise
+    pub noise: Angle,
+    /// Standard deviation of the corner noise, in pixels
+    pub corner_noise: f64,
+    /// Chance each visible object is missed in a frame
+    pub dropout: f64,
+    /// Chance each frame has a spurious detection
+    pub false_positive: f64,
+    /// Reported for every tag, since we don't model the alternate PnP solution
+    pub tag_ambiguity: f64,
+    pub latency: Duration,
+}
+
+impl Default for VirtualCamera {
+    fn default() -> Self {
+        let resolution = (1280, 800);
+
+        Self {
+            name: "sim".to_string(),
+            // facing forward, 30cm up, tilted 10deg down
+            robot_to_camera: Transform3d {
+                translation: [0.3, 0.0, 0.3].into(),
+                rotation: Quaternion::from_rpy(
+                    Angle::default(),
+                    Angle::new::<degree>(10.0),
+                    Angle::default(),
+                ),
+            },
+            resolution,
+            intrinsics: CameraIntrinsics::from_fov(resolution, Angle::new::<degree>(70.0)),
+            max_range: Length::new::<meter>(8.0),
+            noise: Angle::new::<degree>(0.3),
+            corner_noise: 1.0,
+            dropout: 0.05,
+            false_positive: 0.02,
+            tag_ambiguity: 0.1,
+            latency: Duration::from_millis(30),
+        }
+    }
+}
+
+/// The smallest (possibly rotated) rectangle around `points`, as its corners and
+/// angle in degrees. Like OpenCV's `minAreaRect`, which photon uses.
+fn min_area_rect(points: &[(f64, f64)]) -> ([(f64, f64); 4], f64) {
+    let mut best = ([(0.0, 0.0); 4], 0.0, f64::INFINITY);
+
+    for i in 0..points.len() {
+        let (a, b) = (points[i], points[(i + 1) % points.len()]);
+        let angle = (b.1 - a.1).atan2(b.0 - a.0);
+        let (sin, cos) = angle.sin_cos();
+
+        // in the frame of this edge
+        let local = points
+            .iter()
+            .map(|p| (p.0 * cos + p.1 * sin, -p.0 * sin + p.1 * cos))
+            .collect_vec();
+
+        let (min_u, max_u) = local
+            .iter()
+            .map(|p| p.0)
+            .minmax_by(f64::total_cmp)
+            .into_option()
+            .unwrap();
+        let (min_v, max_v) = local
+            .iter()
+            .map(|p| p.1)
+            .minmax_by(f64::total_cmp)
+            .into_option()
+            .unwrap();
+        let area = (max_u - min_u) * (max_v - min_v);
+
+        if area < best.2 {
+            let corner = |u: f64, v: f64| (u * cos - v * sin, u * sin + v * cos);
+            let corners = [
+                corner(min_u, max_v),
+                corner(max_u, max_v),
+                corner(max_u, min_v),
+                corner(min_u, min_v),
+            ];
+
+            best = (corners, angle.to_degrees(), area);
+        }
+    }
+
+    // report the smallest rotation that describes the rect
+    let skew = (best.1 + 45.0).rem_euclid(90.0) - 45.0;
+    (best.0, skew)
+}
+
+impl VirtualCamera {
+    /// (horizontal, vertical)
+    pub fn fov(&self) -> (Angle, Angle) {
+        let (w, h) = self.resolution;
+        let CameraIntrinsics { fx, fy, .. } = self.intrinsics;
+
+        (
+            Angle::new::<radian>(2.0 * (w as f64 / 2.0 / fx).atan()),
+            Angle::new::<radian>(2.0 * (h as f64 / 2.0 / fy).atan()),
+        )
+    }
+
+    /// Pixel of a point in the camera frame, or `None` if it's behind the lens
+    pub fn project(&self, [x, y, z]: [f64; 3]) -> Option<(f64, f64)> {
+        if x < NEAR_PLANE {
+            return None;
+        }
+
+        let CameraIntrinsics { fx, fy, cx, cy } = self.intrinsics;
+        Some((cx - fx * y / x, cy - fy * z / x))
+    }
+
+    fn in_image(&self, (u, v): (f64, f64)) -> bool {
+        let (w, h) = self.resolution;
+        (0.0..=w as f64).contains(&u) && (0.0..=h as f64).contains(&v)
+    }
+
+    /// A field point in the camera frame, when our robot is at `ours`
+    pub fn camera_point(&self, ours: Pose2d, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        let (rx, ry) = ours.unapply(x, y);
+        self.robot_to_camera.unapply([rx, ry, z])
+    }
+
+    /// Where the camera is on the field, when our robot is at `ours`
+    pub fn field_to_camera(&self, ours: Pose2d) -> Transform3d {
+        let [x, y, z] = self.robot_to_camera.translation.to_array();
+        let (fx, fy) = ours.apply(x, y);
+        let heading = Quaternion::from_rpy(Angle::default(), Angle::default(), ours.rotate.angle);
+
+        Transform3d {
+            translation: [fx, fy, z].into(),
+            rotation: heading * self.robot_to_camera.rotation,
+        }
+    }
+
+    /// Yaw and pitch (photon's degrees, positive right and up) of a pixel
+    fn angles(&self, (u, v): (f64, f64)) -> (f64, f64) {
+        let CameraIntrinsics { fx, fy, cx, cy } = self.intrinsics;
+        (
+            ((u - cx) / fx).atan().to_degrees(),
+            ((cy - v) / fy).atan().to_degrees(),
+        )
+    }
+
+    /// An object detection target from a bounding rect in pixels
+    fn detection(
+        &self,
+        (left, top): (f64, f64),
+        (right, bottom): (f64, f64),
+    ) -> PhotonTrackedTarget {
+        let (w, h) = self.resolution;
+        let (yaw, pitch) = self.angles(((left + right) / 2.0, (top + bottom) / 2.0));
+        let corners = vec![
+            TargetCorner { x: left, y: bottom },
+            TargetCorner {
+                x: right,
+                y: bottom,
+            },
+            TargetCorner { x: right, y: top },
+            TargetCorner { x: left, y: top },
+        ];
+
+        PhotonTrackedTarget {
+            yaw,
+            pitch,
+            area: (right - left) * (bottom - top) / (w as f64 * h as f64) * 100.0,
+            skew: 0.0,
+            fiducial_id: FiducialId(None),
+            detected: DetectedObject {
+                id: ROBOT_CLASS,
+                confidence: 0.9,
+            },
+            to_target: TargetTransforms {
+                best: Transform3d::IDENTITY,
+                alt: Transform3d::IDENTITY,
+            },
+            ambiguity: -1.0,
+            area_rect_corners: corners.clone(),
+            detected_corners: corners,
+        }
+    }
+
+    /// The target an enemy's bumpers would produce, ignoring noise and occlusion
+    pub fn observe_robot(&self, ours: Pose2d, enemy: Pose2d) -> Option<PhotonTrackedTarget> {
+        let half = ROBOT_SIZE().get::<meter>() / 2.0;
+        let height = BUMPER_HEIGHT().get::<meter>();
+        let (w, h) = self.resolution;
+
+        let (x, y) = enemy.apply(0.0, 0.0);
+        let center = self.camera_point(ours, [x, y, height / 2.0]);
+        if center[0].hypot(center[1]) > self.max_range.get::<meter>() {
+            return None;
+        }
+
+        let mut pixels = vec![];
+        for (sx, sy, z) in itertools::iproduct!([-1.0, 1.0], [-1.0, 1.0], [0.0, height]) {
+            let (x, y) = enemy.apply(sx * half, sy * half);
+            pixels.push(self.project(self.camera_point(ours, [x, y, z]))?);
+        }
+
+        let (left, right) = pixels
+            .iter()
+            .map(|p| p.0)
+            .minmax_by(f64::total_cmp)
+            .into_option()?;
+        let (top, bottom) = pixels
+            .iter()
+            .map(|p| p.1)
+            .minmax_by(f64::total_cmp)
+            .into_option()?;
+        let (left, right) = (left.max(0.0), right.min(w as f64));
+        let (top, bottom) = (top.max(0.0), bottom.min(h as f64));
+
+        if left >= right || top >= bottom {
+            return None;
+        }
+
+        Some(self.detection((left, top), (right, bottom)))
+    }
+
+    /// The target a tag would produce, ignoring noise and occlusion. Tags are only
+    /// reported when all four corners are in view.
+    pub fn observe_tag(&self, ours: Pose2d, tag: AprilTag) -> Option<PhotonTrackedTarget> {
+        let half = TAG_SIZE().get::<meter>() / 2.0;
+        let (w, h) = self.resolution;
+
+        let camera_to_tag = Transform3d {
+            translation: self
+                .camera_point(ours, tag.pose.translation.to_array())
+                .into(),
+            rotation: self.field_to_camera(ours).rotation.conjugate() * tag.pose.rotation,
+        };
+
+        let [x, y, _] = camera_to_tag.translation.to_array();
+        if x.hypot(y) > self.max_range.get::<meter>() {
+            return None;
+        }
+
+        // the tag's x axis points out of its face, so +y is on the viewer's right
+        let mut corners = vec![];
+        for (ty, tz) in [(-half, -half), (half, -half), (half, half), (-half, half)] {
+            let pixel = self.project(camera_to_tag.apply([0.0, ty, tz]))?;
+
+            if !self.in_image(pixel) {
+                return None;
+            }
+
+            corners.push(pixel);
+        }
+
+        // back faces don't count, photon can't see the tag from behind
+        let [tx, ..] = camera_to_tag.rotation.rotate([1.0, 0.0, 0.0]);
+        if tx >= 0.0 {
+            return None;
+        }
+
+        let (rect, skew) = min_area_rect(&corners);
+        let center = rect
+            .iter()
+            .fold((0.0, 0.0), |acc, p| (acc.0 + p.0 / 4.0, acc.1 + p.1 / 4.0));
+        let (yaw, pitch) = self.angles(center);
+
+        // shoelace formula over the detected quad
+        let area = (0..4)
+            .map(|i| {
+                let (a, b) = (corners[i], corners[(i + 1) % 4]);
+                a.0 * b.1 - b.0 * a.1
+            })
+            .sum::<f64>()
+            .abs()
+            / 2.0;
+
+        let to_corner = |(x, y): (f64, f64)| TargetCorner { x, y };
+
+        Some(PhotonTrackedTarget {
+            yaw,
+            pitch,
+            area: area / (w as f64 * h as f64) * 100.0,
+            skew,
+            fiducial_id: FiducialId(Some(tag.id)),
+            detected: DetectedObject {
+                id: NO_CLASS,
+                confidence: -1.0,
+            },
+            to_target: TargetTransforms {
+                best: camera_to_tag,
+                alt: camera_to_tag,
+            },
+            ambiguity: self.tag_ambiguity,
+            area_rect_corners: rect.into_iter().map(to_corner).collect(),
+            detected_corners: corners.into_iter().map(to_corner).collect(),
+        })
+    }
+
+    pub fn observe(&self, ours: Pose2d, object: SimObject) -> Option<PhotonTrackedTarget> {
+        match object {
+            SimObject::Robot(pose) => self.observe_robot(ours, pose),
+            SimObject::AprilTag(tag) => self.observe_tag(ours, tag),
+        }
+    }
+
+    /// One frame of what the pipeline would publish, noise, dropouts and false
+    /// positives included. Two or more tags in view also produce a multi-tag
+    /// result.
+    pub fn frame(
+        &self,
+        seqid: u64,
+        capture_time: Duration,
+        ours: Pose2d,
+        objects: &[SimObject],
+        rng: &mut impl Rng,
+    ) -> PhotonResult {
+        let noise = self.noise.get::<degree>();
+        let (w, h) = (self.resolution.0 as f64, self.resolution.1 as f64);
+
+        let mut targets = objects
+            .iter()
+            .filter_map(|object| self.observe(ours, *object))
+            .filter(|_| !rng.gen_bool(self.dropout))
+            .collect_vec();
+
+        let tags = targets
+            .iter()
+            .filter(|target| target.fiducial_id.0.is_some())
+            .count();
+
+        let pnp = (tags >= 2).then(|| {
+            let field_to_camera = self.field_to_camera(ours);
+
+            MultiTargetPNP {
+                pnp: PNPResult {
+                    best: field_to_camera,
+                    alt: field_to_camera,
+                    error: 0.0,
+                    alt_error: 0.0,
+                    ambiguity: 0.0,
+                },
+                num_fiducials: tags as u16,
+            }
+        });
+
+        if rng.gen_bool(self.false_positive) {
+            let size = (rng.gen_range(10.0..w / 8.0), rng.gen_range(10.0..h / 8.0));
+            let left = rng.gen_range(0.0..w - size.0);
+            let top = rng.gen_range(0.0..h - size.1);
+
+            targets.push(self.detection((left, top), (left + size.0, top + size.1)));
+        }
+
+        for target in &mut targets {
+            target.yaw += gaussian(rng, noise);
+            target.pitch += gaussian(rng, noise);
+
+            for corner in target
+                .area_rect_corners
+                .iter_mut()
+                .chain(target.detected_corners.iter_mut())
+            {
+                corner.x += gaussian(rng, self.corner_noise);
+                corner.y += gaussian(rng, self.corner_noise);
+            }
+        }
+
+        PhotonResult {
+            metadata: PhotonPipelineMetadata {
+                seqid,
+                capture_time,
+                publish_time: capture_time + self.latency,
+                last_handshake: capture_time,
+            },
+            targets,
+            pnp,
+        }
+    }
+}
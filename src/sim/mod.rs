@@ -0,0 +1,190 @@
+pub mod camera;
+pub mod robots;
+
+#[cfg(test)]
+mod test;
+
+use crate::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use std::{f64::consts::PI, time::Duration};
+
+use camera::{SimObject, VirtualCamera};
+use game::{
+    consts::{FIELD_WIDTH, MAX_ACCEL, MAX_SPEED},
+    layout::FieldLayout,
+};
+use robots::{Behavior, SimRobot};
+
+/// Normally distributed noise (Box-Muller), since rand doesn't ship one
+pub(crate) fn gaussian(rng: &mut impl Rng, std_dev: f64) -> f64 {
+    if std_dev <= 0.0 {
+        return 0.0;
+    }
+
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    std_dev * (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Each enemy gets its own `u8` id
+pub const MAX_ENEMIES: usize = u8::MAX as usize + 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimConfig {
+    /// At most [`MAX_ENEMIES`]
+    pub enemies: usize,
+    pub seed: u64,
+    pub tick: Duration,
+    /// Time between camera frames
+    pub frame_period: Duration,
+    /// Our robot, which stays put
+    pub ours: Pose2d,
+    pub camera: VirtualCamera,
+    /// Scripted routes, handed out to enemies in order. Enemies past the end
+    /// drive randomly.
+    pub scripts: Vec<Vec<Translate2d>>,
+    /// Tags the camera can also see, if any
+    pub layout: Option<FieldLayout>,
+}
+
+impl Default for SimConfig {
+    fn default() -> Self {
+        Self {
+            enemies: 3,
+            seed: 0,
+            tick: Duration::from_millis(5),
+            frame_period: Duration::from_millis(20),
+            // at our driver station wall, looking downfield
+            ours: Pose2d {
+                translate: Translate2d {
+                    x: Length::new::<meter>(1.0),
+                    y: FIELD_WIDTH() / 2.0,
+                },
+                rotate: Rotate2d::default(),
+            },
+            camera: VirtualCamera::default(),
+            scripts: vec![],
+            layout: None,
+        }
+    }
+}
+
+/// Where an enemy really was
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GroundTruth {
+    pub id: u8,
+    pub time: Duration,
+    pub pose: Pose2d,
+    pub velocity: (Velocity, Velocity),
+}
+
+/// Enemies driving around the field, seen by one virtual camera
+pub struct Simulation {
+    pub config: SimConfig,
+    pub robots: Vec<SimRobot>,
+    /// Since the start of the simulation
+    pub time: Duration,
+    rng: StdRng,
+    seqid: u64,
+    next_frame: Duration,
+}
+
+impl Simulation {
+    pub fn new(config: SimConfig) -> Self {
+        assert!(
+            config.enemies <= MAX_ENEMIES,
+            "{} enemies can't each have a u8 id",
+            config.enemies
+        );
+        let mut rng = StdRng::seed_from_u64(config.seed);
+
+        // robots drive slower than the theoretical max, but never faster
+        let max_speed = MAX_SPEED().min(Velocity::new::<mps>(4.5));
+
+        let robots = (0..config.enemies)
+            .map(|i| {
+                let behavior = config
+                    .scripts
+                    .get(i)
+                    .cloned()
+                    .map_or(Behavior::Random, Behavior::Scripted);
+
+                let pose = Pose2d {
+                    translate: SimRobot::random_point(&mut rng),
+                    rotate: Rotate2d::default(),
+                };
+
+                SimRobot::new(i as u8, pose, max_speed, MAX_ACCEL(), behavior, &mut rng)
+            })
+            .collect();
+
+        Self {
+            config,
+            robots,
+            time: Duration::ZERO,
+            rng,
+            seqid: 0,
+            next_frame: Duration::ZERO,
+        }
+    }
+
+    /// Advances one tick, returning a frame if the camera captured one
+    pub fn step(&mut self) -> Option<PhotonResult> {
+        let dt = Time::new::<second>(self.config.tick.as_secs_f64());
+
+        for robot in &mut self.robots {
+            robot.step(dt, &mut self.rng);
+        }
+
+        self.time += self.config.tick;
+
+        if self.time < self.next_frame {
+            return None;
+        }
+
+        self.next_frame = self.time + self.config.frame_period;
+        self.seqid += 1;
+
+        let tags = self.config.layout.iter().flat_map(|layout| &layout.tags);
+        let objects = self
+            .robots
+            .iter()
+            .map(|robot| SimObject::Robot(robot.pose))
+            .chain(tags.map(|tag| SimObject::AprilTag(*tag)))
+            .collect_vec();
+
+        Some(self.config.camera.frame(
+            self.seqid,
+            self.time,
+            self.config.ours,
+            &objects,
+            &mut self.rng,
+        ))
+    }
+
+    pub fn truth(&self) -> Vec<GroundTruth> {
+        self.robots
+            .iter()
+            .map(|robot| GroundTruth {
+                id: robot.id,
+                time: self.time,
+                pose: robot.pose,
+                velocity: robot.velocity,
+            })
+            .collect()
+    }
+
+    /// Enemies the camera could see right now, noise aside
+    pub fn visible(&self) -> Vec<u8> {
+        self.robots
+            .iter()
+            .filter(|robot| {
+                self.config
+                    .camera
+                    .observe_robot(self.config.ours, robot.pose)
+                    .is_some()
+            })
+            .map(|robot| robot.id)
+            .collect()
+    }
+}
@@ -0,0 +1,126 @@
+use crate::prelude::*;
+use rand::Rng;
+
+use game::consts::{FIELD_LENGTH, FIELD_WIDTH, ROBOT_SIZE};
+
+/// Close enough to a waypoint to move on to the next
+const ARRIVED: f64 = 0.2;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Behavior {
+    /// Drive the waypoints in order, looping back to the first
+    Scripted(Vec<Translate2d>),
+    /// Drive to random points on the field
+    Random,
+}
+
+/// A ground-truth enemy robot, driven around the field by its `behavior`
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimRobot {
+    pub id: u8,
+    pub pose: Pose2d,
+    pub velocity: (Velocity, Velocity),
+    pub max_speed: Velocity,
+    pub max_accel: Acceleration,
+    pub behavior: Behavior,
+    target: Translate2d,
+    waypoint: usize,
+}
+
+impl SimRobot {
+    pub fn new(
+        id: u8,
+        pose: Pose2d,
+        max_speed: Velocity,
+        max_accel: Acceleration,
+        behavior: Behavior,
+        rng: &mut impl Rng,
+    ) -> Self {
+        let mut robot = Self {
+            id,
+            pose,
+            velocity: Default::default(),
+            max_speed,
+            max_accel,
+            behavior,
+            target: pose.translate,
+            waypoint: 0,
+        };
+
+        robot.retarget(rng);
+        robot
+    }
+
+    /// A random point a robot can actually reach, i.e. not half into a wall
+    pub fn random_point(rng: &mut impl Rng) -> Translate2d {
+        let margin = ROBOT_SIZE().get::<meter>() / 2.0;
+
+        Translate2d {
+            x: Length::new::<meter>(rng.gen_range(margin..FIELD_LENGTH().get::<meter>() - margin)),
+            y: Length::new::<meter>(rng.gen_range(margin..FIELD_WIDTH().get::<meter>() - margin)),
+        }
+    }
+
+    fn retarget(&mut self, rng: &mut impl Rng) {
+        self.target = match &self.behavior {
+            Behavior::Scripted(waypoints) if !waypoints.is_empty() => {
+                let target = waypoints[self.waypoint % waypoints.len()];
+                self.waypoint += 1;
+                target
+            }
+            _ => Self::random_point(rng),
+        };
+    }
+
+    /// Drives towards the current target for `dt`, speeding up and braking within
+    /// `max_accel` so the robot stops on its target
+    pub fn step(&mut self, dt: Time, rng: &mut impl Rng) {
+        let dt = dt.get::<second>();
+        let max_speed = self.max_speed.get::<mps>();
+        let max_accel = self.max_accel.get::<mps2>();
+
+        let (x, y) = (
+            self.pose.translate.x.get::<meter>(),
+            self.pose.translate.y.get::<meter>(),
+        );
+        let (tx, ty) = (self.target.x.get::<meter>(), self.target.y.get::<meter>());
+        let (vx, vy) = (self.velocity.0.get::<mps>(), self.velocity.1.get::<mps>());
+
+        let dist = (tx - x).hypot(ty - y);
+        if dist < ARRIVED {
+            self.retarget(rng);
+        }
+
+        // fastest speed we can still stop from in time
+        let speed = max_speed.min((2.0 * max_accel * dist).sqrt());
+        let (wx, wy) = if dist > 1e-9 {
+            ((tx - x) / dist * speed, (ty - y) / dist * speed)
+        } else {
+            (0.0, 0.0)
+        };
+
+        let (mut dvx, mut dvy) = (wx - vx, wy - vy);
+        let dv = dvx.hypot(dvy);
+        if dv > max_accel * dt {
+            dvx *= max_accel * dt / dv;
+            dvy *= max_accel * dt / dv;
+        }
+
+        let (vx, vy) = (vx + dvx, vy + dvy);
+        let margin = ROBOT_SIZE().get::<meter>() / 2.0;
+        let x = (x + vx * dt).clamp(margin, FIELD_LENGTH().get::<meter>() - margin);
+        let y = (y + vy * dt).clamp(margin, FIELD_WIDTH().get::<meter>() - margin);
+
+        self.velocity = (Velocity::new::<mps>(vx), Velocity::new::<mps>(vy));
+        self.pose.translate = Translate2d {
+            x: Length::new::<meter>(x),
+            y: Length::new::<meter>(y),
+        };
+
+        if vx.hypot(vy) > 0.1 {
+            self.pose.rotate = Rotate2d {
+                angle: Angle::new::<radian>(vy.atan2(vx)),
+            };
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use super::camera::{SimObject, VirtualCamera};
+use crate::prelude::*;
+use crate::util::fixtures::{pose, start_clock};
+use rand::{rngs::StdRng, SeedableRng};
+use std::time::Duration;
+
+use crate::util::preprocessor;
+use game::layout::AprilTag;
+
+fn perfect_camera() -> VirtualCamera {
+    VirtualCamera {
+        noise: Angle::default(),
+        corner_noise: 0.0,
+        dropout: 0.0,
+        false_positive: 0.0,
+        ..Default::default()
+    }
+}
+
+/// A tag at (x, y), 0.5m up, facing back towards the origin
+fn tag(id: u32, x: f64, y: f64) -> AprilTag {
+    AprilTag {
+        id,
+        pose: Transform3d {
+            translation: [x, y, 0.5].into(),
+            rotation: Quaternion::from_rpy(
+                Angle::default(),
+                Angle::default(),
+                Angle::new::<degree>(180.0),
+            ),
+        },
+    }
+}
+
+#[tokio::test]
+async fn enemy_ahead_is_located() {
+    start_clock();
+
+    let camera = perfect_camera();
+    let ours = pose(1.0, 4.0, 0.0);
+    let enemy = pose(5.0, 4.5, 90.0);
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let frame = camera.frame(
+        1,
+        Duration::ZERO,
+        ours,
+        &[SimObject::Robot(enemy)],
+        &mut rng,
+    );
+    assert_eq!(frame.targets.len(), 1);
+    assert!(frame.pnp.is_none());
+
+    let res = preprocessor::photon(frame, camera.robot_to_camera, ours).await;
+    assert_eq!(res.enemies.len(), 1);
+
+    let estimate = res.enemies[0].translate;
+    let dx = (estimate.x - enemy.translate.x).get::<meter>();
+    let dy = (estimate.y - enemy.translate.y).get::<meter>();
+    assert!(dx.hypot(dy) < 0.4, "off by ({dx}, {dy})");
+}
+
+#[test]
+fn enemy_behind_is_not_seen() {
+    let camera = perfect_camera();
+    let ours = pose(5.0, 4.0, 0.0);
+
+    assert!(camera.observe_robot(ours, pose(2.0, 4.0, 0.0)).is_none());
+}
+
+#[test]
+fn tag_ahead() {
+    let camera = VirtualCamera {
+        robot_to_camera: Transform3d {
+            translation: [0.0, 0.0, 0.5].into(),
+            rotation: Quaternion::IDENTITY,
+        },
+        ..perfect_camera()
+    };
+    let ours = pose(0.0, 0.0, 0.0);
+    let target = camera.observe_tag(ours, tag(7, 3.0, 0.0)).unwrap();
+
+    assert_eq!(target.fiducial_id.0, Some(7));
+    assert!(target.yaw.abs() < 1e-9);
+    assert!(target.pitch.abs() < 1e-9);
+    assert!(target.skew.abs() < 1e-9);
+
+    let [x, y, z] = target.to_target.best.translation.to_array();
+    assert!((x - 3.0).abs() < 1e-9 && y.abs() < 1e-9 && z.abs() < 1e-9);
+
+    // bottom left, bottom right, top right, top left in the image
+    let corners = &target.detected_corners;
+    assert!(corners[0].x < corners[1].x && corners[0].y > corners[3].y);
+    assert!(corners[2].x > corners[3].x && corners[2].y < corners[1].y);
+
+    // seen from behind, the tag isn't detected
+    assert!(camera
+        .observe_tag(pose(6.0, 0.0, 180.0), tag(7, 3.0, 0.0))
+        .is_none());
+}
+
+#[test]
+fn two_tags_produce_multitag() {
+    let camera = perfect_camera();
+    let ours = pose(0.0, 0.0, 0.0);
+    let mut rng = StdRng::seed_from_u64(0);
+
+    let objects = [
+        SimObject::AprilTag(tag(1, 4.0, 0.5)),
+        SimObject::AprilTag(tag(2, 4.0, -0.5)),
+    ];
+    let frame = camera.frame(1, Duration::ZERO, ours, &objects, &mut rng);
+
+    assert_eq!(frame.targets.len(), 2);
+
+    let pnp = frame.pnp.unwrap();
+    assert_eq!(pnp.num_fiducials, 2);
+
+    let [x, y, z] = pnp.pnp.best.translation.to_array();
+    assert!((x - 0.3).abs() < 1e-9 && y.abs() < 1e-9 && (z - 0.3).abs() < 1e-9);
+}
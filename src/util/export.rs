@@ -0,0 +1,87 @@
+//! JSON and MessagePack export of decoded photon and robot types for logs and
+//! dashboards. Everything here is behind the `serde` feature.
+//!
+//! Quantities are written as plain numbers in fixed units, so readers never
+//! have to guess: lengths in meters, angles in radians, [`Duration`]s in whole
+//! microseconds, and [`Instant`]s in microseconds since [`time::initialize`].
+
+use std::{
+    backtrace::Backtrace,
+    panic::Location,
+    time::{Duration, Instant},
+};
+
+use crate::prelude::*;
+use serde::{
+    ser::{Error as _, SerializeTuple},
+    Serializer,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error("At {location}: JSON error:\n{source}")]
+    Json {
+        #[from]
+        source: serde_json::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+
+    #[error("At {location}: MessagePack error:\n{source}")]
+    MsgPack {
+        #[from]
+        source: rmp_serde::encode::Error,
+        location: &'static Location<'static>,
+        backtrace: Backtrace,
+    },
+}
+
+#[track_caller]
+pub fn to_json<T: serde::Serialize + ?Sized>(value: &T) -> Result<String, ExportError> {
+    Ok(serde_json::to_string(value)?)
+}
+
+/// MessagePack with field names kept, so it reads the same as [`to_json`]
+#[track_caller]
+pub fn to_msgpack<T: serde::Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ExportError> {
+    Ok(rmp_serde::to_vec_named(value)?)
+}
+
+pub fn meters<S: Serializer>(value: &Length, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(value.get::<meter>())
+}
+
+/// A `(Length, Length)`, as a pair of meters
+pub fn meters_pair<S: Serializer>(
+    value: &(Length, Length),
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut tuple = serializer.serialize_tuple(2)?;
+    tuple.serialize_element(&value.0.get::<meter>())?;
+    tuple.serialize_element(&value.1.get::<meter>())?;
+    tuple.end()
+}
+
+pub fn radians<S: Serializer>(value: &Angle, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(value.get::<radian>())
+}
+
+pub fn micros<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(value.as_micros() as u64)
+}
+
+/// On the same clock as the pipeline timestamps [`time::instant_of`] converts.
+/// Fails if [`time::initialize`] hasn't been called, or `value` predates it.
+pub fn micros_since_start<S: Serializer>(
+    value: &Instant,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let Some(since) = time::checked_duration_of(*value) else {
+        return Err(S::Error::custom(
+            "instant from before time::initialize, or it wasn't called",
+        ));
+    };
+
+    micros(&since, serializer)
+}
@@ -0,0 +1,25 @@
+//! Builders the tests of every module share
+
+use crate::prelude::*;
+use std::sync::Once;
+
+static CLOCK: Once = Once::new();
+
+/// Calls [`time::initialize`] the first time any test asks, since it can only
+/// be called once per process
+pub fn start_clock() {
+    CLOCK.call_once(time::initialize);
+}
+
+/// At (`x`, `y`) meters, facing `heading` degrees
+pub fn pose(x: f64, y: f64, heading: f64) -> Pose2d {
+    Pose2d {
+        translate: Translate2d {
+            x: Length::new::<meter>(x),
+            y: Length::new::<meter>(y),
+        },
+        rotate: Rotate2d {
+            angle: Angle::new::<degree>(heading),
+        },
+    }
+}
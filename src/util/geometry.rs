@@ -0,0 +1,112 @@
+use crate::prelude::*;
+use std::ops::Mul;
+
+// WPILib conventions throughout: x forward, y left, z up, counter-clockwise positive
+
+impl Quaternion {
+    pub const IDENTITY: Self = Self {
+        w: 1.0,
+        x: 0.0,
+        y: 0.0,
+        z: 0.0,
+    };
+
+    /// Extrinsic roll (x), then pitch (y), then yaw (z), like WPILib's `Rotation3d`
+    pub fn from_rpy(roll: Angle, pitch: Angle, yaw: Angle) -> Self {
+        let (sr, cr) = (roll.get::<radian>() / 2.0).sin_cos();
+        let (sp, cp) = (pitch.get::<radian>() / 2.0).sin_cos();
+        let (sy, cy) = (yaw.get::<radian>() / 2.0).sin_cos();
+
+        Self {
+            w: cr * cp * cy + sr * sp * sy,
+            x: sr * cp * cy - cr * sp * sy,
+            y: cr * sp * cy + sr * cp * sy,
+            z: cr * cp * sy - sr * sp * cy,
+        }
+    }
+
+    pub fn conjugate(&self) -> Self {
+        Self {
+            w: self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+
+    pub fn rotate(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        let v = Quaternion { w: 0.0, x, y, z };
+        let Quaternion { x, y, z, .. } = *self * v * self.conjugate();
+        [x, y, z]
+    }
+}
+
+impl Mul for Quaternion {
+    type Output = Quaternion;
+
+    fn mul(self, rhs: Quaternion) -> Quaternion {
+        Quaternion {
+            w: self.w * rhs.w - self.x * rhs.x - self.y * rhs.y - self.z * rhs.z,
+            x: self.w * rhs.x + self.x * rhs.w + self.y * rhs.z - self.z * rhs.y,
+            y: self.w * rhs.y - self.x * rhs.z + self.y * rhs.w + self.z * rhs.x,
+            z: self.w * rhs.z + self.x * rhs.y - self.y * rhs.x + self.z * rhs.w,
+        }
+    }
+}
+
+impl Translate3d {
+    pub fn to_array(self) -> [f64; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+impl From<[f64; 3]> for Translate3d {
+    fn from([x, y, z]: [f64; 3]) -> Self {
+        Self { x, y, z }
+    }
+}
+
+impl Transform3d {
+    pub const IDENTITY: Self = Self {
+        translation: Translate3d {
+            x: 0.0,
+            y: 0.0,
+            z: 0.0,
+        },
+        rotation: Quaternion::IDENTITY,
+    };
+
+    /// Takes a point in the child frame (e.g. camera) into the parent frame (e.g. robot)
+    pub fn apply(&self, point: [f64; 3]) -> [f64; 3] {
+        let [x, y, z] = self.rotation.rotate(point);
+        let [tx, ty, tz] = self.translation.to_array();
+        [x + tx, y + ty, z + tz]
+    }
+
+    /// Takes a point in the parent frame into the child frame
+    pub fn unapply(&self, [x, y, z]: [f64; 3]) -> [f64; 3] {
+        let [tx, ty, tz] = self.translation.to_array();
+        self.rotation.conjugate().rotate([x - tx, y - ty, z - tz])
+    }
+}
+
+impl Pose2d {
+    /// Takes a point relative to this pose onto the field
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.rotate.angle.get::<radian>().sin_cos();
+
+        (
+            self.translate.x.get::<meter>() + x * cos - y * sin,
+            self.translate.y.get::<meter>() + x * sin + y * cos,
+        )
+    }
+
+    /// Takes a field point relative to this pose
+    pub fn unapply(&self, x: f64, y: f64) -> (f64, f64) {
+        let (sin, cos) = self.rotate.angle.get::<radian>().sin_cos();
+        let dx = x - self.translate.x.get::<meter>();
+        let dy = y - self.translate.y.get::<meter>();
+
+        (dx * cos + dy * sin, -dx * sin + dy * cos)
+    }
+}
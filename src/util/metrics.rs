@@ -0,0 +1,279 @@
+//! Health and performance counters, shared between the NT worker and whatever
+//! reports them. Cheap to update from every stage of every frame.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// Latencies kept per stage, so the percentiles follow the last few seconds
+const LATENCY_SAMPLES: usize = 256;
+
+/// Loop rate is the number of ticks in this window
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// How far a worker error reaches, which decides what the worker does about it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorClass {
+    /// Likely to pass on its own: skipped, retried or reconnected
+    Transient,
+    /// Only one message is bad: it's skipped
+    Frame,
+    /// Stops the worker
+    Fatal,
+}
+
+impl ErrorClass {
+    pub const ALL: [ErrorClass; 3] = [ErrorClass::Transient, ErrorClass::Frame, ErrorClass::Fatal];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorClass::Transient => "transient",
+            ErrorClass::Frame => "frame",
+            ErrorClass::Fatal => "fatal",
+        }
+    }
+}
+
+/// The parts of a frame's trip through the pipeline, in order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Stage {
+    /// Working out which layout and version a result is in, once it arrived
+    Identify,
+    Deserialize,
+    Preprocess,
+    Track,
+    Plan,
+    Publish,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 6] = [
+        Stage::Identify,
+        Stage::Deserialize,
+        Stage::Preprocess,
+        Stage::Track,
+        Stage::Plan,
+        Stage::Publish,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Identify => "identify",
+            Stage::Deserialize => "deserialize",
+            Stage::Preprocess => "preprocess",
+            Stage::Track => "track",
+            Stage::Plan => "plan",
+            Stage::Publish => "publish",
+        }
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Percentiles {
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+}
+
+impl Percentiles {
+    /// Nearest-rank percentiles, or all zero with no samples
+    pub fn of(samples: impl IntoIterator<Item = Duration>) -> Self {
+        let mut sorted = samples.into_iter().collect::<Vec<_>>();
+        sorted.sort_unstable();
+
+        let rank = |p: f64| {
+            let i = ((p * sorted.len() as f64).ceil() as usize).saturating_sub(1);
+            sorted.get(i).copied().unwrap_or_default()
+        };
+
+        Self {
+            p50: rank(0.5),
+            p90: rank(0.9),
+            p99: rank(0.99),
+        }
+    }
+}
+
+/// What the metrics looked like at one point in time
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Status {
+    pub uptime: Duration,
+    /// Worker loop iterations per second
+    pub loop_rate: f64,
+    pub latency: HashMap<Stage, Percentiles>,
+    pub frames: u64,
+    /// Photon results we never saw (gaps in a camera's seqid) or couldn't use
+    pub frames_dropped: u64,
+    pub deserialize_errors: u64,
+    /// Worker errors of each class, skipped or not
+    pub errors: HashMap<ErrorClass, u64>,
+    pub tracks: usize,
+    /// Since the last successful plan, `None` before the first
+    pub last_plan_age: Option<Duration>,
+    pub connected: bool,
+}
+
+#[derive(Debug)]
+struct State {
+    started: Instant,
+    ticks: VecDeque<Instant>,
+    latency: HashMap<Stage, VecDeque<Duration>>,
+    frames: u64,
+    frames_dropped: u64,
+    deserialize_errors: u64,
+    errors: HashMap<ErrorClass, u64>,
+    last_seqid: HashMap<String, u64>,
+    tracks: usize,
+    last_plan: Option<Instant>,
+    connected: bool,
+}
+
+#[derive(Debug)]
+pub struct Metrics {
+    state: Mutex<State>,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State {
+                started: Instant::now(),
+                ticks: VecDeque::new(),
+                latency: HashMap::new(),
+                frames: 0,
+                frames_dropped: 0,
+                deserialize_errors: 0,
+                errors: HashMap::new(),
+                last_seqid: HashMap::new(),
+                tracks: 0,
+                last_plan: None,
+                connected: false,
+            }),
+        }
+    }
+}
+
+impl Metrics {
+    pub fn record(&self, stage: Stage, latency: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let samples = state.latency.entry(stage).or_default();
+
+        if samples.len() == LATENCY_SAMPLES {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    /// Times `stage` until the returned guard is dropped
+    pub fn start(&self, stage: Stage) -> StageTimer<'_> {
+        StageTimer {
+            metrics: self,
+            stage,
+            started: Instant::now(),
+        }
+    }
+
+    pub fn tick(&self) {
+        let now = Instant::now();
+        let mut state = self.state.lock().unwrap();
+
+        state.ticks.push_back(now);
+        while state
+            .ticks
+            .front()
+            .is_some_and(|&tick| now - tick > RATE_WINDOW)
+        {
+            state.ticks.pop_front();
+        }
+    }
+
+    /// Counts a decoded frame from `camera`, and any before it that never
+    /// arrived. Layouts before 2025 don't number their results, so they never
+    /// show gaps.
+    pub fn frame(&self, camera: &str, seqid: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.frames += 1;
+
+        let missed = match state.last_seqid.insert(camera.to_string(), seqid) {
+            // a smaller seqid is photon restarting, not frames going missing
+            Some(last) if seqid > last => seqid - last - 1,
+            _ => 0,
+        };
+        state.frames_dropped += missed;
+    }
+
+    /// Counts a decoded frame that was thrown away
+    pub fn drop_frame(&self) {
+        self.state.lock().unwrap().frames_dropped += 1;
+    }
+
+    pub fn deserialize_error(&self) {
+        self.state.lock().unwrap().deserialize_errors += 1;
+    }
+
+    pub fn error(&self, class: ErrorClass) {
+        *self.state.lock().unwrap().errors.entry(class).or_default() += 1;
+    }
+
+    pub fn set_tracks(&self, tracks: usize) {
+        self.state.lock().unwrap().tracks = tracks;
+    }
+
+    pub fn planned(&self) {
+        self.state.lock().unwrap().last_plan = Some(Instant::now());
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.state.lock().unwrap().connected = connected;
+    }
+
+    pub fn status(&self) -> Status {
+        let now = Instant::now();
+        let state = self.state.lock().unwrap();
+
+        let recent = state
+            .ticks
+            .iter()
+            .filter(|&&tick| now - tick <= RATE_WINDOW)
+            .count();
+
+        Status {
+            uptime: now - state.started,
+            loop_rate: recent as f64 / RATE_WINDOW.as_secs_f64(),
+            latency: state
+                .latency
+                .iter()
+                .map(|(&stage, samples)| (stage, Percentiles::of(samples.iter().copied())))
+                .collect(),
+            frames: state.frames,
+            frames_dropped: state.frames_dropped,
+            deserialize_errors: state.deserialize_errors,
+            errors: state.errors.clone(),
+            tracks: state.tracks,
+            last_plan_age: state.last_plan.map(|plan| now - plan),
+            connected: state.connected,
+        }
+    }
+}
+
+/// Records how long it lived as the latency of its stage
+pub struct StageTimer<'a> {
+    metrics: &'a Metrics,
+    stage: Stage,
+    started: Instant,
+}
+
+impl Drop for StageTimer<'_> {
+    fn drop(&mut self) {
+        self.metrics.record(self.stage, self.started.elapsed());
+    }
+}
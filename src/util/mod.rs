@@ -1,4 +1,13 @@
-mod preprocessor;
+#[cfg(feature = "serde")]
+pub mod export;
+#[cfg(test)]
+pub mod fixtures;
+pub mod geometry;
+pub mod metrics;
+pub mod preprocessor;
 pub mod time;
 
+#[cfg(test)]
+mod test;
+
 pub use preprocessor::PreprocessorResponse;
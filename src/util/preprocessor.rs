@@ -1,16 +1,65 @@
 use crate::prelude::*;
 use std::time::Instant;
 
+use game::consts::{BUMPER_HEIGHT, ROBOT_SIZE};
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub struct PreprocessorResponse {
     pub enemies: Vec<Pose2d>,
     pub timestamp: Instant,
 }
 
-/// When finished, this should take the `res` and process it into
-/// a list of `Pose2d` objects representing the center positions of enemy robots
-pub async fn photon(res: PhotonResult) -> PreprocessorResponse {
+/// Takes the `res` from a camera mounted at `robot_to_camera` and processes it
+/// into a list of `Pose2d` objects representing the center positions of enemy
+/// robots. `robot` is where we were when the frame was captured.
+///
+/// Every object detection (non-fiducial) target is assumed to be a robot. Its
+/// center ray is intersected with the middle of the bumpers, then pushed back
+/// half a robot, since the camera sees the near face.
+pub async fn photon(
+    res: PhotonResult,
+    robot_to_camera: Transform3d,
+    robot: Pose2d,
+) -> PreprocessorResponse {
     let timestamp = time::instant_of(res.metadata.capture_time);
+    let height = BUMPER_HEIGHT().get::<meter>() / 2.0;
+    let [ox, oy, oz] = robot_to_camera.translation.to_array();
+
+    let enemies = res
+        .targets
+        .iter()
+        .filter(|target| target.fiducial_id.0.is_none())
+        .filter_map(|target| {
+            // photon's yaw is positive to the right
+            let ray = [
+                1.0,
+                -target.yaw.to_radians().tan(),
+                target.pitch.to_radians().tan(),
+            ];
+            let [dx, dy, dz] = robot_to_camera.rotation.rotate(ray);
+
+            let t = (height - oz) / dz;
+            if !t.is_finite() || t <= 0.0 {
+                return None;
+            }
+
+            let reach = dx.hypot(dy);
+            if reach < 1e-9 {
+                return None;
+            }
+
+            let push = ROBOT_SIZE().get::<meter>() / 2.0 / reach;
+            let (x, y) = robot.apply(ox + dx * (t + push), oy + dy * (t + push));
+
+            Some(Pose2d {
+                translate: Translate2d {
+                    x: Length::new::<meter>(x),
+                    y: Length::new::<meter>(y),
+                },
+                rotate: Rotate2d::default(),
+            })
+        })
+        .collect();
 
-    unimplemented!()
+    PreprocessorResponse { enemies, timestamp }
 }
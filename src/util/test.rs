@@ -0,0 +1,92 @@
+use super::metrics::{Metrics, Stage};
+use std::time::Duration;
+
+#[test]
+fn metrics_status() {
+    let metrics = Metrics::default();
+
+    for millis in 1..=100 {
+        metrics.record(Stage::Plan, Duration::from_millis(millis));
+    }
+
+    // 3 and 4 never arrived, and a restart isn't counted as missing frames
+    for seqid in [1, 2, 5, 6, 0, 1] {
+        metrics.frame("front", seqid);
+    }
+    metrics.frame("back", 10);
+    metrics.deserialize_error();
+
+    for _ in 0..5 {
+        metrics.tick();
+    }
+
+    let status = metrics.status();
+    let plan = status.latency[&Stage::Plan];
+
+    assert_eq!(plan.p50, Duration::from_millis(50));
+    assert_eq!(plan.p90, Duration::from_millis(90));
+    assert_eq!(plan.p99, Duration::from_millis(99));
+    assert!(!status.latency.contains_key(&Stage::Track));
+
+    assert_eq!(status.frames, 7);
+    assert_eq!(status.frames_dropped, 2);
+    assert_eq!(status.deserialize_errors, 1);
+    assert_eq!(status.loop_rate, 5.0);
+    assert_eq!(status.last_plan_age, None);
+    assert!(!status.connected);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn enemy_export_units() {
+    use crate::prelude::*;
+    use fixtures::{pose, start_clock};
+    use game::enemy::{DataPoint, Enemy};
+    use serde_json::json;
+
+    start_clock();
+
+    let enemy = Enemy {
+        id: 4,
+        history: vec![DataPoint {
+            time: time::instant_of(Duration::from_millis(1500)),
+            pose: pose(1.5, 2.0, 180.0),
+            size: (Length::new::<meter>(0.9), Length::new::<meter>(0.8)),
+            confidence: 0.5,
+        }],
+    };
+    let enemy_json = json!({
+        "id": 4,
+        "history": [{
+            "time": 1_500_000,
+            "pose": {
+                "translate": { "x": 1.5, "y": 2.0 },
+                "rotate": { "angle": std::f64::consts::PI },
+            },
+            "size": [0.9, 0.8],
+            "confidence": 0.5,
+        }],
+    });
+
+    let exported = export::to_json(&enemy).unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&exported).unwrap(),
+        enemy_json
+    );
+
+    let exported = export::to_msgpack(&enemy).unwrap();
+    assert_eq!(
+        rmp_serde::from_slice::<serde_json::Value>(&exported).unwrap(),
+        enemy_json
+    );
+
+    // an instant from before the clock started has no time to export
+    let early = Enemy {
+        id: 4,
+        history: vec![DataPoint {
+            time: time::instant_of(Duration::ZERO) - Duration::from_secs(1),
+            ..enemy.history[0]
+        }],
+    };
+    assert!(export::to_json(&early).is_err());
+}
@@ -30,3 +30,9 @@ pub fn duration_of(inst: Instant) -> Duration {
 
     inst - *start
 }
+
+/// [`duration_of`], or `None` before [`initialize`] or for an `inst` before it
+pub fn checked_duration_of(inst: Instant) -> Option<Duration> {
+    let start = unsafe { PROGRAM_START.as_ref() }?;
+    inst.checked_duration_since(*start)
+}